@@ -0,0 +1,66 @@
+use criterion::{criterion_group, criterion_main, Criterion, BenchmarkId, Throughput};
+use file_sys::wrapper::{EncryptedFormat, Format};
+
+const SIZES: [usize; 2] = [1024 * 1024, 16 * 1024 * 1024];
+
+fn payload(size: usize) -> Vec<u64> {
+    (0..(size / 8) as u64).collect()
+}
+
+fn formats() -> [(&'static str, EncryptedFormat); 2] {
+    [
+        ("single", EncryptedFormat::new([1; 32])),
+        ("chunked", EncryptedFormat::new([1; 32]).with_chunk_size(64 * 1024)),
+    ]
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let value = payload(size);
+
+        group.throughput(Throughput::Bytes(size as u64));
+
+        for (name, format) in formats() {
+            group.bench_with_input(BenchmarkId::new(name, size), &value, |b, value| b.iter(|| {
+                let mut output = Vec::with_capacity(size + 1024);
+
+                format.to_writer(&mut output, value).unwrap();
+
+                output
+            }));
+        }
+    }
+
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+
+    for size in SIZES {
+        let value = payload(size);
+
+        group.throughput(Throughput::Bytes(size as u64));
+
+        for (name, format) in formats() {
+            let mut encrypted = Vec::new();
+
+            format.to_writer(&mut encrypted, &value).unwrap();
+
+            group.bench_with_input(BenchmarkId::new(name, size), &encrypted, |b, encrypted| b.iter(|| {
+                let value: Vec<u64> = format.from_reader(encrypted.as_slice()).unwrap();
+
+                value
+            }));
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::io::Error as IoError;
+
+use crate::wrapper::FileError;
+
+type BoxDynError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// the general category of an error from any wrapper
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// an io error from reading or writing data
+    Io,
+    /// an io error from a file operation with the path attached
+    File,
+    /// the value could not be encoded or decoded by the format
+    Format,
+    /// the checksum stored in the file does not match the data
+    Corrupted,
+    /// the file does not have the expected header
+    InvalidHeader,
+    /// the file was written with a newer layout version
+    UnsupportedVersion,
+    /// encryption or decryption failed
+    Crypto,
+    /// the encrypted data is not encoded properly
+    InvalidEncoding,
+}
+
+impl ErrorKind {
+    /// returns the name of the kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Io => "Io",
+            ErrorKind::File => "File",
+            ErrorKind::Format => "Format",
+            ErrorKind::Corrupted => "Corrupted",
+            ErrorKind::InvalidHeader => "InvalidHeader",
+            ErrorKind::UnsupportedVersion => "UnsupportedVersion",
+            ErrorKind::Crypto => "Crypto",
+            ErrorKind::InvalidEncoding => "InvalidEncoding",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// an error from any wrapper
+///
+/// every wrapper error converts into this while keeping the original error
+/// as the source so generic code over multiple formats can use a single
+/// error type
+pub struct Error {
+    kind: ErrorKind,
+    source: BoxDynError,
+}
+
+impl Error {
+    /// creates a new Error with the given kind and source
+    pub fn new<E>(kind: ErrorKind, source: E) -> Self
+    where
+        E: Into<BoxDynError>
+    {
+        Error {
+            kind,
+            source: source.into(),
+        }
+    }
+
+    /// returns the kind of error
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// returns a reference to the original error
+    pub fn get_ref(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        &*self.source
+    }
+
+    /// consumes the error returning the original error
+    pub fn into_inner(self) -> BoxDynError {
+        self.source
+    }
+
+    /// attempts to downcast the original error to the given type
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: std::error::Error + 'static
+    {
+        self.source.downcast_ref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.source, f)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Error")
+            .field("kind", &self.kind)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::new(ErrorKind::Io, err)
+    }
+}
+
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::new(ErrorKind::File, err)
+    }
+}
+
+/// implements From for a wrapper error using its kind method
+macro_rules! from_wrapper_error {
+    ($feature:literal, $module:ident) => {
+        #[cfg(all(feature = $feature, feature = "serde"))]
+        impl From<crate::wrapper::$module::Error> for Error {
+            fn from(err: crate::wrapper::$module::Error) -> Self {
+                Error::new(err.kind(), err)
+            }
+        }
+    };
+}
+
+from_wrapper_error!("binary", binary);
+from_wrapper_error!("cbor", cbor);
+from_wrapper_error!("json", json);
+from_wrapper_error!("postcard", postcard);
+from_wrapper_error!("yaml", yaml);
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+impl From<crate::wrapper::encrypted::Error> for Error {
+    fn from(err: crate::wrapper::encrypted::Error) -> Self {
+        Error::new(err.kind(), err)
+    }
+}
+
+#[cfg(all(test, feature = "json", feature = "binary", feature = "serde"))]
+mod test {
+    use super::*;
+    use crate::wrapper::{Json, FileWrapped, Format};
+
+    fn load_any<F>(path: &str) -> Result<FileWrapped<u64, F>, Error>
+    where
+        F: Format + Default
+    {
+        FileWrapped::load_with(path, F::default())
+            .map_err(Into::into)
+    }
+
+    #[test]
+    fn unified() {
+        let file_name = "test.unified.json";
+
+        Json::new(1u64, file_name).save().expect("failed to save json file");
+
+        let loaded = load_any::<crate::wrapper::JsonFormat>(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(loaded.inner(), &1);
+
+        let err = load_any::<crate::wrapper::BinaryFormat>(file_name)
+            .expect_err("loaded json file as binary");
+
+        assert_eq!(err.kind(), ErrorKind::InvalidHeader);
+        assert!(err.downcast_ref::<crate::wrapper::binary::Error>().is_some());
+    }
+}
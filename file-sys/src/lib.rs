@@ -1 +1,14 @@
+//! file system helpers for wrapping values saved to files
+//!
+//! # breaking changes
+//!
+//! files saved with `wrapper::BinaryFormat` now start with a header and end
+//! with a checksum. files saved before the header was added fail to load
+//! with the default format and return `wrapper::binary::Error::InvalidHeader`.
+//! load them with `wrapper::BinaryFormat::legacy` and save them again to
+//! move them to the new layout.
+
+pub mod error;
 pub mod wrapper;
+
+pub use error::{Error, ErrorKind};
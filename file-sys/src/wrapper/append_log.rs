@@ -0,0 +1,571 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write, Seek, SeekFrom, BufReader, BufWriter};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::marker::PhantomData;
+use std::path::{PathBuf, Path};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::atomic;
+use super::error::{FileError, Operation};
+use super::format::Format;
+
+const LEN_SIZE: u64 = 4;
+
+const CHECKSUM_SIZE: u64 = 4;
+
+const PREFIX_SIZE: u64 = LEN_SIZE + CHECKSUM_SIZE;
+
+/// a file of records that are only ever added to the end
+///
+/// every record is the value encoded with the Format prefixed with its
+/// length and the crc32 of the encoded value as little endian u32s. a
+/// partially written last record, from a crash during append, is removed
+/// when the log is opened. a record that does not match its checksum with
+/// more records after it is reported as an InvalidData error instead, use
+/// open_truncating_with to drop it and every record after it. only one
+/// AppendLog should be open for a file at a time.
+pub struct AppendLog<T, F> {
+    file: File,
+    path: Box<Path>,
+    format: F,
+    offsets: Vec<u64>,
+    end: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// splits the prefix of a record into the length and checksum
+fn split_prefix(prefix: &[u8; PREFIX_SIZE as usize]) -> (u32, u32) {
+    let (len, checksum) = prefix.split_at(LEN_SIZE as usize);
+
+    (
+        u32::from_le_bytes(len.try_into().unwrap()),
+        u32::from_le_bytes(checksum.try_into().unwrap()),
+    )
+}
+
+/// the records found by scan
+struct Scanned {
+    offsets: Vec<u64>,
+    end: u64,
+    /// set if a record before the last one does not match its checksum
+    corrupted: bool,
+}
+
+/// reads every record returning the start of each one and the end of the
+/// last complete record
+///
+/// stops at the first record that is cut short or does not match its
+/// checksum
+fn scan(file: &File) -> Result<Scanned, IoError> {
+    let total = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut offsets = Vec::new();
+    let mut record = Vec::new();
+    let mut end = 0;
+
+    reader.seek(SeekFrom::Start(0))?;
+
+    while end + PREFIX_SIZE <= total {
+        let mut prefix = [0; PREFIX_SIZE as usize];
+
+        reader.read_exact(&mut prefix)?;
+
+        let (len, checksum) = split_prefix(&prefix);
+        let next = end + PREFIX_SIZE + len as u64;
+
+        if next > total {
+            break;
+        }
+
+        record.resize(len as usize, 0);
+        reader.read_exact(&mut record)?;
+
+        if crc32fast::hash(&record) != checksum {
+            return Ok(Scanned {
+                offsets,
+                end,
+                corrupted: next < total,
+            });
+        }
+
+        offsets.push(end);
+        end = next;
+    }
+
+    Ok(Scanned {
+        offsets,
+        end,
+        corrupted: false,
+    })
+}
+
+/// the error returned when a record in the middle of the log is corrupted
+fn corrupted_record(path: &Path, offset: u64) -> FileError {
+    FileError::new(
+        Operation::Read,
+        path,
+        IoError::new(
+            IoErrorKind::InvalidData,
+            format!("record at {} does not match its checksum", offset)
+        )
+    )
+}
+
+impl<T, F> AppendLog<T, F>
+where
+    F: Format
+{
+    /// opens the log at the given path using the provided format creating
+    /// the file if it does not exist
+    ///
+    /// a partially written last record is removed. an InvalidData error is
+    /// returned if an earlier record does not match its checksum
+    pub fn open_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::open_inner(given.into(), format, false)
+    }
+
+    /// opens the log at the given path using the provided format removing
+    /// the first record that does not match its checksum and every record
+    /// after it
+    ///
+    /// used to recover a log that open_with reports as corrupted
+    pub fn open_truncating_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::open_inner(given.into(), format, true)
+    }
+
+    fn open_inner(given: PathBuf, format: F, truncate: bool) -> Result<Self, F::Error> {
+        let path: Box<Path> = given.into();
+        let exists = path.try_exists()
+            .map_err(FileError::map(Operation::Open, &path))?;
+
+        if !exists {
+            super::format::touch_file(&path, &format.permissions())?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(FileError::map(Operation::Open, &path))?;
+
+        let Scanned { offsets, end, corrupted } = scan(&file)
+            .map_err(FileError::map(Operation::Read, &path))?;
+
+        if corrupted && !truncate {
+            return Err(corrupted_record(&path, end).into());
+        }
+
+        if end != file.metadata().map_err(FileError::map(Operation::Read, &path))?.len() {
+            file.set_len(end)
+                .map_err(FileError::map(Operation::Write, &path))?;
+        }
+
+        Ok(AppendLog {
+            file,
+            path,
+            format,
+            offsets,
+            end,
+            _marker: PhantomData,
+        })
+    }
+
+    /// removes every record after the first count records
+    ///
+    /// does nothing if the log has count or fewer records
+    pub fn truncate(&mut self, count: usize) -> Result<(), F::Error> {
+        let Some(end) = self.offsets.get(count).copied() else {
+            return Ok(());
+        };
+
+        self.file.set_len(end)
+            .map_err(FileError::map(Operation::Write, &self.path))?;
+        self.format.sync_mode().file(&self.file)
+            .map_err(FileError::map(Operation::Sync, &self.path))?;
+
+        self.offsets.truncate(count);
+        self.end = end;
+
+        Ok(())
+    }
+
+    /// removes every record
+    pub fn clear(&mut self) -> Result<(), F::Error> {
+        self.truncate(0)
+    }
+}
+
+impl<T, F> AppendLog<T, F> {
+    /// returns the path of the log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// returns the format used for every record
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// returns the number of records
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// checks if the log has no records
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+}
+
+impl<T, F> AppendLog<T, F>
+where
+    F: Format + Default
+{
+    /// opens the log at the given path creating the file if it does not
+    /// exist
+    pub fn open<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::open_with(given, F::default())
+    }
+
+    /// opens the log at the given path removing the first corrupted record
+    /// and every record after it
+    ///
+    /// see open_truncating_with for details
+    pub fn open_truncating<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::open_truncating_with(given, F::default())
+    }
+}
+
+/// encodes the value with the length and checksum prefix
+fn encode_record<T, F>(format: &F, value: &T) -> Result<Vec<u8>, F::Error>
+where
+    T: Serialize,
+    F: Format
+{
+    let mut buffer = vec![0; PREFIX_SIZE as usize];
+
+    format.to_writer(&mut buffer, value)?;
+
+    let Ok(len) = u32::try_from(buffer.len() - PREFIX_SIZE as usize) else {
+        return Err(IoError::new(IoErrorKind::InvalidInput, "record is too large").into());
+    };
+    let checksum = crc32fast::hash(&buffer[PREFIX_SIZE as usize..]);
+
+    buffer[..LEN_SIZE as usize].copy_from_slice(&len.to_le_bytes());
+    buffer[LEN_SIZE as usize..PREFIX_SIZE as usize].copy_from_slice(&checksum.to_le_bytes());
+
+    Ok(buffer)
+}
+
+impl<T, F> AppendLog<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// encodes the value and adds it to the end of the log
+    ///
+    /// the file is flushed to disk based on the sync mode of the format
+    /// before returning
+    pub fn append(&mut self, value: &T) -> Result<(), F::Error> {
+        let record = encode_record(&self.format, value)?;
+
+        let result = (|| {
+            self.file.seek(SeekFrom::Start(self.end))?;
+            self.file.write_all(&record)?;
+            self.format.sync_mode().file(&self.file)
+        })();
+
+        if let Err(err) = result {
+            // drop whatever part of the record was written so the next
+            // append starts at the correct offset
+            let _ = self.file.set_len(self.end);
+
+            return Err(FileError::new(Operation::Write, self.path.to_path_buf(), err).into());
+        }
+
+        self.offsets.push(self.end);
+        self.end += record.len() as u64;
+
+        Ok(())
+    }
+
+    /// replaces every record in the log with the given values
+    ///
+    /// the new log is written to a temp file and renamed over the current one
+    /// so a failed compaction leaves the log unchanged
+    pub fn compact<'a, I>(&mut self, values: I) -> Result<(), F::Error>
+    where
+        T: 'a,
+        I: IntoIterator<Item = &'a T>
+    {
+        atomic::save(
+            &self.path,
+            &self.format.permissions(),
+            self.format.sync_mode(),
+            |writer: &mut BufWriter<File>| {
+                for value in values {
+                    writer.write_all(&encode_record(&self.format, value)?)?;
+                }
+
+                Ok::<(), F::Error>(())
+            }
+        )?;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&self.path)
+            .map_err(FileError::map(Operation::Open, &self.path))?;
+
+        let Scanned { offsets, end, corrupted } = scan(&file)
+            .map_err(FileError::map(Operation::Read, &self.path))?;
+
+        if corrupted {
+            return Err(corrupted_record(&self.path, end).into());
+        }
+
+        self.file = file;
+        self.offsets = offsets;
+        self.end = end;
+
+        Ok(())
+    }
+}
+
+impl<T, F> AppendLog<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// returns an iterator that decodes every record from the start of the
+    /// log
+    ///
+    /// records appended after the iterator is created are not returned
+    pub fn iter(&self) -> Result<Iter<'_, T, F>, F::Error> {
+        self.iter_from(0)
+    }
+
+    /// returns an iterator that decodes every record starting at the given
+    /// index
+    ///
+    /// the file is read from the start of the record so the records before
+    /// it are not decoded. an index past the end returns an empty iterator
+    pub fn iter_from(&self, index: usize) -> Result<Iter<'_, T, F>, F::Error> {
+        let mut file = File::open(&self.path)
+            .map_err(FileError::map(Operation::Open, &self.path))?;
+
+        let (offset, remaining) = match self.offsets.get(index) {
+            Some(offset) => (*offset, self.offsets.len() - index),
+            None => (self.end, 0),
+        };
+
+        file.seek(SeekFrom::Start(offset))
+            .map_err(FileError::map(Operation::Read, &self.path))?;
+
+        Ok(Iter {
+            reader: BufReader::new(file),
+            path: &self.path,
+            format: &self.format,
+            remaining,
+            _marker: PhantomData,
+        })
+    }
+
+    /// decodes the last record in the log
+    pub fn last(&self) -> Result<Option<T>, F::Error> {
+        let Some(index) = self.offsets.len().checked_sub(1) else {
+            return Ok(None);
+        };
+
+        self.iter_from(index)?.next().transpose()
+    }
+}
+
+impl<T, F> fmt::Debug for AppendLog<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppendLog")
+            .field("path", &self.path)
+            .field("len", &self.offsets.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// decodes the records of an AppendLog in order
+pub struct Iter<'a, T, F> {
+    reader: BufReader<File>,
+    path: &'a Path,
+    format: &'a F,
+    remaining: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, F> Iterator for Iter<'_, T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    type Item = Result<T, F::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        let mut prefix = [0; PREFIX_SIZE as usize];
+        let mut record = Vec::new();
+
+        let result = self.reader.read_exact(&mut prefix).and_then(|_| {
+            let (len, checksum) = split_prefix(&prefix);
+
+            record.resize(len as usize, 0);
+
+            self.reader.read_exact(&mut record)?;
+
+            if crc32fast::hash(&record) != checksum {
+                return Err(IoError::new(IoErrorKind::InvalidData, "record does not match its checksum"));
+            }
+
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            self.remaining = 0;
+
+            return Some(Err(FileError::new(Operation::Read, self.path, err).into()));
+        }
+
+        Some(self.format.from_reader(record.as_slice()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use crate::wrapper::JsonFormat;
+
+    fn collect(log: &AppendLog<u64, JsonFormat>) -> Vec<u64> {
+        log.iter()
+            .expect("failed to iterate log")
+            .collect::<Result<_, _>>()
+            .expect("failed to read record")
+    }
+
+    #[test]
+    fn base() {
+        let file_name = "test.append_log";
+        let _ = std::fs::remove_file(file_name);
+
+        let mut log: AppendLog<u64, JsonFormat> = AppendLog::open(file_name)
+            .expect("failed to open log");
+
+        for value in 1..=4 {
+            log.append(&value).expect("failed to append record");
+        }
+
+        assert_eq!(collect(&log), vec![1, 2, 3, 4]);
+
+        log.truncate(3).expect("failed to truncate log");
+        log.append(&5).expect("failed to append record");
+
+        assert_eq!(collect(&log), vec![1, 2, 3, 5]);
+
+        let tail: Vec<u64> = log.iter_from(2)
+            .expect("failed to iterate log")
+            .collect::<Result<_, _>>()
+            .expect("failed to decode record");
+
+        assert_eq!(tail, vec![3, 5]);
+        assert_eq!(log.iter_from(4).expect("failed to iterate log").count(), 0);
+        assert_eq!(log.last().expect("failed to read last record"), Some(5));
+
+        log.compact(&[15]).expect("failed to compact log");
+        log.append(&6).expect("failed to append record");
+
+        drop(log);
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(file_name)
+            .expect("failed to open log file");
+        file.write_all(&[10, 0, 0, 0, 0, 0, 0, 0, b'1']).expect("failed to write partial record");
+
+        let log: AppendLog<u64, JsonFormat> = AppendLog::open(file_name)
+            .expect("failed to open log");
+
+        assert_eq!(log.len(), 2);
+        assert_eq!(collect(&log), vec![15, 6]);
+    }
+
+    #[test]
+    fn corrupted() {
+        let file_name = "test.append_log_corrupted";
+        let _ = std::fs::remove_file(file_name);
+
+        let mut log: AppendLog<u64, JsonFormat> = AppendLog::open(file_name)
+            .expect("failed to open log");
+
+        for value in [10, 20, 30] {
+            log.append(&value).expect("failed to append record");
+        }
+
+        drop(log);
+
+        // each record is the prefix followed by two digits, flip the first
+        // digit of the second record
+        let mut bytes = std::fs::read(file_name).expect("failed to read log file");
+        bytes[PREFIX_SIZE as usize * 2 + 2] = b'9';
+        std::fs::write(file_name, &bytes).expect("failed to write log file");
+
+        match AppendLog::<u64, JsonFormat>::open(file_name) {
+            Err(crate::wrapper::json::Error::File(err)) => {
+                assert_eq!(err.io().kind(), IoErrorKind::InvalidData);
+            }
+            result => panic!("unexpected open result: {:?}", result),
+        }
+
+        assert_eq!(std::fs::read(file_name).unwrap(), bytes, "log was changed");
+
+        let mut log: AppendLog<u64, JsonFormat> = AppendLog::open_truncating(file_name)
+            .expect("failed to open log");
+
+        assert_eq!(log.len(), 1);
+        assert_eq!(collect(&log), vec![10]);
+
+        log.append(&40).expect("failed to append record");
+
+        assert_eq!(collect(&log), vec![10, 40]);
+
+        drop(log);
+
+        // a bad last record is treated the same as a partial write
+        let mut bytes = std::fs::read(file_name).expect("failed to read log file");
+        bytes[PREFIX_SIZE as usize * 2 + 2] = b'9';
+        std::fs::write(file_name, &bytes).expect("failed to write log file");
+
+        let log: AppendLog<u64, JsonFormat> = AppendLog::open(file_name)
+            .expect("failed to open log");
+
+        assert_eq!(collect(&log), vec![10]);
+    }
+}
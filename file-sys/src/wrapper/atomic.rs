@@ -0,0 +1,345 @@
+use std::path::{PathBuf, Path};
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::io::Error as IoError;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::error::{FileError, Operation};
+use super::permissions::Permissions;
+
+static TMP_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// how data is flushed to the disk before a save returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// leaves flushing to the os. a save can be lost on power loss
+    Skip,
+    /// flushes the file contents with sync_data and the directory entry
+    Data,
+    /// flushes the file contents and metadata with sync_all and the
+    /// directory entry
+    All,
+}
+
+impl SyncMode {
+    /// flushes the file based on the mode
+    pub(crate) fn file(&self, file: &File) -> Result<(), IoError> {
+        match self {
+            SyncMode::Skip => Ok(()),
+            SyncMode::Data => file.sync_data(),
+            SyncMode::All => file.sync_all(),
+        }
+    }
+
+    /// flushes the file based on the mode using tokio fs
+    #[cfg(feature = "tokio")]
+    async fn file_async(&self, file: &tokio::fs::File) -> Result<(), IoError> {
+        match self {
+            SyncMode::Skip => Ok(()),
+            SyncMode::Data => file.sync_data().await,
+            SyncMode::All => file.sync_all().await,
+        }
+    }
+}
+
+/// returns the directory containing the path
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// flushes the directory entry of the path so the rename is not lost
+///
+/// directories cannot be opened as files on windows so this only happens on
+/// unix
+fn sync_dir(path: &Path) -> Result<(), FileError> {
+    #[cfg(unix)]
+    {
+        let dir = parent_dir(path);
+
+        File::open(dir)
+            .and_then(|file| file.sync_all())
+            .map_err(FileError::map(Operation::Sync, dir))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// flushes the directory entry of the path using tokio fs
+///
+/// similar to the blocking sync_dir
+#[cfg(feature = "tokio")]
+async fn sync_dir_async(path: &Path) -> Result<(), FileError> {
+    #[cfg(unix)]
+    {
+        let dir = parent_dir(path);
+        let file = tokio::fs::File::open(dir)
+            .await
+            .map_err(FileError::map(Operation::Sync, dir))?;
+
+        file.sync_all()
+            .await
+            .map_err(FileError::map(Operation::Sync, dir))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// creates a unique path in the same directory as the given path
+///
+/// the temp file must be in the same directory so that the rename does not
+/// cross file systems
+pub(crate) fn tmp_path(path: &Path) -> PathBuf {
+    let count = TMP_COUNT.fetch_add(1, Ordering::Relaxed);
+    let mut name = std::ffi::OsString::from(".");
+
+    if let Some(file_name) = path.file_name() {
+        name.push(file_name);
+    }
+
+    name.push(format!(".{}.{}.tmp", std::process::id(), count));
+
+    path.with_file_name(name)
+}
+
+/// moves the temp file over the destination
+///
+/// on windows the rename can fail if the destination is in use or read only
+/// so the destination is removed and the rename is tried again
+pub(crate) fn replace(tmp: &Path, path: &Path) -> Result<(), IoError> {
+    match std::fs::rename(tmp, path) {
+        Ok(()) => Ok(()),
+        #[cfg(windows)]
+        Err(err) if path.exists() => {
+            if std::fs::remove_file(path).is_err() {
+                return Err(err);
+            }
+
+            std::fs::rename(tmp, path)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// copies the permissions of the destination to the temp file if the
+/// destination exists otherwise the given permissions are applied
+///
+/// enforced permissions are always applied
+fn copy_permissions(tmp: &File, path: &Path, permissions: &Permissions) -> Result<(), IoError> {
+    if permissions.enforce() {
+        return permissions.apply(tmp);
+    }
+
+    match std::fs::metadata(path) {
+        Ok(metadata) => tmp.set_permissions(metadata.permissions()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => permissions.apply(tmp),
+        Err(err) => Err(err),
+    }
+}
+
+/// writes to a sibling temp file and then renames it over the destination
+///
+/// the destination is left untouched if any step fails and the temp file is
+/// removed. the permissions are only used if the destination does not exist
+/// or they are enforced. returns the metadata of the written file
+pub(crate) fn save<F, E>(
+    path: &Path,
+    permissions: &Permissions,
+    sync: SyncMode,
+    write: F
+) -> Result<Metadata, E>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), E>,
+    E: From<FileError>
+{
+    let tmp = tmp_path(path);
+
+    let result = (|| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        permissions.open_options(&mut options);
+
+        let file = options.open(&tmp)
+            .map_err(FileError::map(Operation::Create, &tmp))?;
+
+        copy_permissions(&file, path, permissions)
+            .map_err(FileError::map(Operation::Create, &tmp))?;
+
+        let mut writer = BufWriter::new(file);
+
+        write(&mut writer)?;
+
+        writer.flush()
+            .map_err(FileError::map(Operation::Write, &tmp))?;
+        sync.file(writer.get_ref())
+            .map_err(FileError::map(Operation::Sync, &tmp))?;
+
+        let metadata = writer.get_ref().metadata()
+            .map_err(FileError::map(Operation::Write, &tmp))?;
+
+        drop(writer);
+
+        replace(&tmp, path)
+            .map_err(FileError::map(Operation::Rename, path))?;
+
+        if sync != SyncMode::Skip {
+            sync_dir(path)?;
+        }
+
+        Ok(metadata)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+
+    result
+}
+
+/// writes the bytes to a sibling temp file and then renames it over the
+/// destination using tokio fs
+///
+/// similar to the blocking save
+#[cfg(feature = "tokio")]
+pub(crate) async fn save_async(
+    path: &Path,
+    permissions: &Permissions,
+    sync: SyncMode,
+    bytes: &[u8]
+) -> Result<Metadata, FileError> {
+    use tokio::io::AsyncWriteExt;
+
+    let tmp = tmp_path(path);
+
+    let result = async {
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        permissions.open_options_async(&mut options);
+
+        let file = options.open(&tmp)
+            .await
+            .map_err(FileError::map(Operation::Create, &tmp))?;
+
+        let existing = if permissions.enforce() {
+            None
+        } else {
+            match tokio::fs::metadata(path).await {
+                Ok(metadata) => Some(metadata),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => return Err(FileError::new(Operation::Open, path, err)),
+            }
+        };
+
+        match existing {
+            Some(metadata) => file.set_permissions(metadata.permissions())
+                .await
+                .map_err(FileError::map(Operation::Create, &tmp))?,
+            None => permissions.apply_async(&file)
+                .await
+                .map_err(FileError::map(Operation::Create, &tmp))?,
+        }
+
+        let mut writer = tokio::io::BufWriter::new(file);
+
+        writer.write_all(bytes)
+            .await
+            .map_err(FileError::map(Operation::Write, &tmp))?;
+        writer.flush()
+            .await
+            .map_err(FileError::map(Operation::Write, &tmp))?;
+        sync.file_async(writer.get_ref())
+            .await
+            .map_err(FileError::map(Operation::Sync, &tmp))?;
+
+        let metadata = writer.get_ref().metadata()
+            .await
+            .map_err(FileError::map(Operation::Write, &tmp))?;
+
+        drop(writer);
+
+        let renamed = match tokio::fs::rename(&tmp, path).await {
+            Ok(()) => Ok(()),
+            #[cfg(windows)]
+            Err(err) if path.exists() => {
+                if tokio::fs::remove_file(path).await.is_err() {
+                    Err(err)
+                } else {
+                    tokio::fs::rename(&tmp, path).await
+                }
+            }
+            Err(err) => Err(err),
+        };
+
+        renamed.map_err(FileError::map(Operation::Rename, path))?;
+
+        if sync != SyncMode::Skip {
+            sync_dir_async(path).await?;
+        }
+
+        Ok(metadata)
+    }.await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn failed_save_keeps_original() {
+        let file_name = Path::new("test.atomic");
+
+        std::fs::write(file_name, b"original").expect("failed to create test file");
+
+        let result: Result<Metadata, IoError> = save(file_name, &Permissions::new(), SyncMode::All, |writer| {
+            writer.write_all(b"partial")?;
+
+            Err(IoError::other("failed mid write"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(
+            std::fs::read(file_name).expect("failed to read test file"),
+            b"original"
+        );
+
+        save::<_, IoError>(file_name, &Permissions::new(), SyncMode::Data, |writer| writer.write_all(b"updated"))
+            .expect("failed to save test file");
+
+        assert_eq!(
+            std::fs::read(file_name).expect("failed to read test file"),
+            b"updated"
+        );
+    }
+
+    #[test]
+    fn sync_modes() {
+        let file_name = Path::new("test.atomic_sync");
+
+        for sync in [SyncMode::Skip, SyncMode::Data, SyncMode::All] {
+            let data = format!("{:?}", sync);
+
+            save::<_, IoError>(file_name, &Permissions::new(), sync, |writer| {
+                writer.write_all(data.as_bytes())
+            }).expect("failed to save test file");
+
+            assert_eq!(
+                std::fs::read(file_name).expect("failed to read test file"),
+                data.as_bytes()
+            );
+        }
+    }
+}
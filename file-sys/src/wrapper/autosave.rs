@@ -0,0 +1,307 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::Serialize;
+
+use super::format::{Format, FileWrapped};
+
+/// saves the wrapped value when dropped
+///
+/// a failed save on drop is passed to the error handler given when created
+/// since drop cannot return it. use finish to get the result of the save
+/// directly.
+pub struct AutoSave<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    wrapped: Option<FileWrapped<T, F>>,
+    on_error: fn(&F::Error),
+}
+
+impl<T, F> AutoSave<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// creates a new AutoSave that passes save errors on drop to the given
+    /// handler
+    pub fn new(wrapped: FileWrapped<T, F>, on_error: fn(&F::Error)) -> Self {
+        AutoSave {
+            wrapped: Some(wrapped),
+            on_error,
+        }
+    }
+
+    /// updates the handler for save errors
+    pub fn set_handler(&mut self, on_error: fn(&F::Error)) {
+        self.on_error = on_error;
+    }
+
+    /// returns a guard to the inner value that saves when dropped
+    ///
+    /// a failed save on drop is passed to the handler of the AutoSave
+    pub fn write(&mut self) -> WriteGuard<'_, T, F> {
+        let on_error = self.on_error;
+
+        WriteGuard {
+            wrapped: self.wrapped.as_mut()
+                .expect("AutoSave wrapper already taken"),
+            on_error,
+            done: false,
+        }
+    }
+
+    /// saves the value and returns the wrapper without saving again on drop
+    pub fn finish(mut self) -> Result<FileWrapped<T, F>, F::Error> {
+        let wrapped = self.take();
+
+        wrapped.save()?;
+
+        Ok(wrapped)
+    }
+
+    /// returns the wrapper without saving
+    pub fn cancel(mut self) -> FileWrapped<T, F> {
+        self.take()
+    }
+
+    fn take(&mut self) -> FileWrapped<T, F> {
+        self.wrapped.take()
+            .expect("AutoSave wrapper already taken")
+    }
+}
+
+/// a mutable borrow of the inner value that saves when dropped
+///
+/// created by FileWrapped::write. use commit to get the result of the save or
+/// abandon to skip it.
+pub struct WriteGuard<'a, T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    wrapped: &'a mut FileWrapped<T, F>,
+    on_error: fn(&F::Error),
+    done: bool,
+}
+
+impl<T, F> WriteGuard<'_, T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// saves the inner value returning the result
+    pub fn commit(mut self) -> Result<(), F::Error> {
+        self.done = true;
+
+        self.wrapped.save()
+    }
+
+    /// releases the guard without saving
+    ///
+    /// changes made to the inner value are kept in memory
+    pub fn abandon(mut self) {
+        self.done = true;
+    }
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// wraps the value in an AutoSave that will save when dropped
+    ///
+    /// a failed save on drop is passed to on_error
+    pub fn auto_save(self, on_error: fn(&F::Error)) -> AutoSave<T, F> {
+        AutoSave::new(self, on_error)
+    }
+
+    /// returns a guard to the inner value that saves when dropped
+    ///
+    /// a failed save on drop is passed to on_error. use commit on the guard
+    /// to get the result of the save directly
+    pub fn write(&mut self, on_error: fn(&F::Error)) -> WriteGuard<'_, T, F> {
+        WriteGuard {
+            wrapped: self,
+            on_error,
+            done: false,
+        }
+    }
+}
+
+impl<T, F> Deref for AutoSave<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    type Target = FileWrapped<T, F>;
+
+    fn deref(&self) -> &Self::Target {
+        self.wrapped.as_ref()
+            .expect("AutoSave wrapper already taken")
+    }
+}
+
+impl<T, F> DerefMut for AutoSave<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.wrapped.as_mut()
+            .expect("AutoSave wrapper already taken")
+    }
+}
+
+impl<T, F> Drop for AutoSave<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    fn drop(&mut self) {
+        if let Some(wrapped) = self.wrapped.take() {
+            if let Err(err) = wrapped.save() {
+                (self.on_error)(&err);
+            }
+        }
+    }
+}
+
+impl<T, F> Deref for WriteGuard<'_, T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.wrapped.inner()
+    }
+}
+
+impl<T, F> DerefMut for WriteGuard<'_, T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.wrapped.inner_mut()
+    }
+}
+
+impl<T, F> Drop for WriteGuard<'_, T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    fn drop(&mut self) {
+        if !self.done {
+            if let Err(err) = self.wrapped.save() {
+                (self.on_error)(&err);
+            }
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for AutoSave<T, F>
+where
+    T: Serialize + fmt::Debug,
+    F: Format
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoSave")
+            .field("wrapped", &self.wrapped)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::wrapper::Json;
+
+    fn fail_save(err: &crate::wrapper::json::Error) {
+        panic!("failed to save json file: {err}");
+    }
+
+    #[test]
+    fn save_on_drop() {
+        let file_name = "test.autosave.json";
+
+        {
+            let mut wrapper = Json::new(1u64, file_name).auto_save(fail_save);
+
+            *wrapper.inner_mut() = 2;
+        }
+
+        let and_back: Json<u64> = Json::load(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(and_back.inner(), &2);
+
+        let mut wrapper = and_back.auto_save(fail_save);
+        *wrapper.inner_mut() = 3;
+
+        drop(wrapper.cancel());
+
+        let and_back: Json<u64> = Json::load(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(and_back.inner(), &2);
+    }
+
+    #[test]
+    fn write_guard() {
+        let file_name = "test.write_guard.json";
+        let mut wrapper = Json::new(vec![1u64], file_name);
+
+        wrapper.write(fail_save).push(2);
+
+        let mut guard = wrapper.write(fail_save);
+        guard.push(3);
+        guard.abandon();
+
+        let and_back: Json<Vec<u64>> = Json::load(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(and_back.inner(), &vec![1, 2]);
+
+        let mut guard = wrapper.write(fail_save);
+        guard.push(4);
+        guard.commit().expect("failed to commit json file");
+
+        let and_back: Json<Vec<u64>> = Json::load(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(and_back.inner(), &vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn handler_on_failed_save() {
+        static FAILED: AtomicUsize = AtomicUsize::new(0);
+
+        fn count_failed(_err: &crate::wrapper::json::Error) {
+            FAILED.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let file_name = "test.missing_dir/test.handler.json";
+        let mut wrapper = Json::new(1u64, file_name).auto_save(count_failed);
+
+        *wrapper.write() = 2;
+
+        assert_eq!(FAILED.load(Ordering::SeqCst), 1);
+
+        drop(wrapper);
+
+        assert_eq!(FAILED.load(Ordering::SeqCst), 2);
+
+        let mut wrapper = Json::new(1u64, file_name);
+
+        *wrapper.write(count_failed) = 3;
+
+        assert_eq!(FAILED.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,23 +1,40 @@
-use std::path::{PathBuf, Path};
-use std::fs::OpenOptions;
-use std::io::{BufReader, BufWriter};
+use std::io::{Read, Write};
 use std::io::Error as IoError;
 use std::fmt;
 
+use bincode::Options;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+use crate::ErrorKind;
+use super::error::FileError;
+#[cfg(feature = "mmap")]
+use super::error::Operation;
+use super::format::{Format, FileWrapped};
+
 #[derive(Debug)]
 pub enum Error {
     Io(IoError),
+    File(FileError),
     Bincode(bincode::Error),
+    /// the checksum stored in the file does not match the data
+    Corrupted,
+    /// the file does not start with the expected magic bytes or contains
+    /// unknown flags
+    InvalidHeader,
+    /// the file was written with a newer layout version
+    UnsupportedVersion(u8),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::File(e) => fmt::Display::fmt(e, f),
             Error::Bincode(e) => fmt::Display::fmt(e, f),
+            Error::Corrupted => f.write_str("Corrupted"),
+            Error::InvalidHeader => f.write_str("InvalidHeader"),
+            Error::UnsupportedVersion(version) => write!(f, "UnsupportedVersion({})", version),
         }
     }
 }
@@ -26,143 +43,509 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::Io(e) => Some(e),
+            Error::File(e) => Some(e),
             Error::Bincode(e) => Some(e),
+            _ => None
         }
     }
 }
 
-pub struct Binary<T> {
-    inner: T,
-    path: Box<Path>,
+impl Error {
+    /// returns the general category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) => ErrorKind::Io,
+            Error::File(_) => ErrorKind::File,
+            Error::Bincode(_) => ErrorKind::Format,
+            Error::Corrupted => ErrorKind::Corrupted,
+            Error::InvalidHeader => ErrorKind::InvalidHeader,
+            Error::UnsupportedVersion(_) => ErrorKind::UnsupportedVersion,
+        }
+    }
 }
 
-impl<T> Binary<T> {
-    pub fn new<P>(inner: T, path: P) -> Self
-    where
-        P: Into<PathBuf>
-    {
-        let buf = path.into();
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::File(err)
+    }
+}
+
+fn map_error(e: bincode::Error) -> Error {
+    match *e {
+        bincode::ErrorKind::Io(io) => Error::Io(io),
+        _ => Error::Bincode(e)
+    }
+}
+
+const CHECKSUM_LEN: usize = 4;
+
+/// the bytes every file with a header starts with
+pub const MAGIC: [u8; 4] = *b"FSBN";
+
+/// the current layout version written to the header
+pub const VERSION: u8 = 1;
+
+/// the header flag for a crc32 footer
+const FLAG_CHECKSUM: u8 = 0b0000_0001;
+
+/// the header flag for variable length integers
+const FLAG_VARINT: u8 = 0b0000_0010;
+
+/// the header flag for big endian integers
+const FLAG_BIG_ENDIAN: u8 = 0b0000_0100;
+
+const KNOWN_FLAGS: u8 = FLAG_CHECKSUM | FLAG_VARINT | FLAG_BIG_ENDIAN;
+
+const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// passes writes through while calculating the crc32 of the data written
+struct ChecksumWriter<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W> Write for ChecksumWriter<W>
+where
+    W: Write
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+
+        self.hasher.update(&buf[..written]);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// how integers are encoded by bincode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntEncoding {
+    /// every integer uses the size of its type
+    #[default]
+    Fixed,
+    /// smaller values use fewer bytes
+    Variable,
+}
+
+/// the byte order of encoded integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// calls the body with the bincode options of the format
+///
+/// bincode options are separate types so each combination needs its own
+/// branch
+macro_rules! with_options {
+    ($format:expr, |$opts:ident| $body:expr) => {{
+        let format: &BinaryFormat = $format;
+        let $opts = bincode::DefaultOptions::new()
+            .allow_trailing_bytes()
+            .with_limit(format.limit.unwrap_or(u64::MAX));
+
+        match (format.int_encoding, format.endian) {
+            (IntEncoding::Fixed, Endian::Little) => {
+                let $opts = $opts.with_fixint_encoding().with_little_endian();
+                $body
+            }
+            (IntEncoding::Fixed, Endian::Big) => {
+                let $opts = $opts.with_fixint_encoding().with_big_endian();
+                $body
+            }
+            (IntEncoding::Variable, Endian::Little) => {
+                let $opts = $opts.with_varint_encoding().with_little_endian();
+                $body
+            }
+            (IntEncoding::Variable, Endian::Big) => {
+                let $opts = $opts.with_varint_encoding().with_big_endian();
+                $body
+            }
+        }
+    }};
+}
+
+/// encodes values with bincode
+///
+/// by default files start with a header of the magic bytes, layout version
+/// and flags followed by the encoded data and a crc32 of the encoded data.
+/// when loading, the flags of the header decide if the checksum is verified
+/// and how integers are decoded.
+///
+/// files saved before the header was added do not load with the default
+/// format and need BinaryFormat::legacy, which reads them with the same
+/// encoding as bincode::serialize. a limit bounds the bytes read while decoding
+/// which protects against length prefixes in untrusted data allocating more
+/// memory than the file holds.
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryFormat {
+    header: bool,
+    checksum: bool,
+    limit: Option<u64>,
+    int_encoding: IntEncoding,
+    endian: Endian,
+}
+
+impl BinaryFormat {
+    /// creates a format that does not write the checksum
+    ///
+    /// the header is still written so files will load with any format that
+    /// uses a header
+    pub fn without_checksum() -> Self {
+        BinaryFormat {
+            checksum: false,
+            ..BinaryFormat::default()
+        }
+    }
+
+    /// creates a format with only the encoded data
+    ///
+    /// used for files saved before the header and checksum were added
+    pub fn legacy() -> Self {
+        BinaryFormat {
+            header: false,
+            checksum: false,
+            ..BinaryFormat::default()
+        }
+    }
+
+    /// checks if the header is written and expected
+    pub fn header(&self) -> bool {
+        self.header
+    }
+
+    /// checks if the checksum is written
+    pub fn checksum(&self) -> bool {
+        self.checksum
+    }
+
+    /// sets the max number of bytes bincode will encode or decode
+    ///
+    /// the header and checksum are not counted
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 
-        Binary {
-            inner,
-            path: buf.into(),
+    /// sets how integers are encoded
+    ///
+    /// files with a header record the encoding so they load with any
+    /// encoding set
+    pub fn with_int_encoding(mut self, int_encoding: IntEncoding) -> Self {
+        self.int_encoding = int_encoding;
+        self
+    }
+
+    /// sets the byte order of encoded integers
+    ///
+    /// files with a header record the byte order so they load with any byte
+    /// order set
+    pub fn with_endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    /// returns the max number of bytes bincode will encode or decode
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// returns how integers are encoded
+    pub fn int_encoding(&self) -> IntEncoding {
+        self.int_encoding
+    }
+
+    /// returns the byte order of encoded integers
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// returns the header flags for the format
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+
+        if self.checksum {
+            flags |= FLAG_CHECKSUM;
+        }
+
+        if self.int_encoding == IntEncoding::Variable {
+            flags |= FLAG_VARINT;
+        }
+
+        if self.endian == Endian::Big {
+            flags |= FLAG_BIG_ENDIAN;
         }
+
+        flags
     }
 
-    pub fn path(&self) -> &Path {
-        &self.path
+    /// validates the header returning the format described by its flags
+    ///
+    /// the limit is kept from the current format
+    fn read_header(&self, header: &[u8]) -> Result<BinaryFormat, Error> {
+        if header.len() < HEADER_LEN || header[..MAGIC.len()] != MAGIC {
+            return Err(Error::InvalidHeader);
+        }
+
+        let version = header[MAGIC.len()];
+        let flags = header[MAGIC.len() + 1];
+
+        if version > VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::InvalidHeader);
+        }
+
+        Ok(BinaryFormat {
+            header: true,
+            checksum: flags & FLAG_CHECKSUM != 0,
+            limit: self.limit,
+            int_encoding: if flags & FLAG_VARINT != 0 {
+                IntEncoding::Variable
+            } else {
+                IntEncoding::Fixed
+            },
+            endian: if flags & FLAG_BIG_ENDIAN != 0 {
+                Endian::Big
+            } else {
+                Endian::Little
+            },
+        })
     }
 
-    pub fn set_path<P>(&mut self, path: P)
+    /// encodes the value with the bincode options of the format
+    fn serialize_into<W, T>(&self, writer: W, value: &T) -> Result<(), Error>
     where
-        P: Into<PathBuf>
+        W: Write,
+        T: Serialize + ?Sized
     {
-        self.path = path.into().into();
+        with_options!(self, |opts| opts.serialize_into(writer, value))
+            .map_err(map_error)
     }
 
-    pub fn inner(&self) -> &T {
-        &self.inner
+    /// decodes a value from the reader with the bincode options of the
+    /// format
+    fn deserialize_from<R, T>(&self, reader: R) -> Result<T, Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        with_options!(self, |opts| opts.deserialize_from(reader))
+            .map_err(map_error)
     }
 
-    pub fn inner_mut(&mut self) -> &mut T {
-        &mut self.inner
+    /// decodes a value from the slice with the bincode options of the format
+    ///
+    /// bincode ignores the limit when decoding a slice so a limited format
+    /// decodes the slice as a reader
+    fn deserialize<T>(&self, bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned
+    {
+        if self.limit.is_some() {
+            return self.deserialize_from(bytes);
+        }
+
+        with_options!(self, |opts| opts.deserialize(bytes))
+            .map_err(map_error)
     }
+}
 
-    pub fn into_inner(self) -> T {
-        self.inner
+impl Default for BinaryFormat {
+    fn default() -> Self {
+        BinaryFormat {
+            header: true,
+            checksum: true,
+            limit: None,
+            int_encoding: IntEncoding::Fixed,
+            endian: Endian::Little,
+        }
     }
 }
 
-impl<T> Binary<T>
-where
-    T: Serialize
-{
-    pub fn save(&self) -> Result<(), Error> {
-        let file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.path)
-            .map_err(|e| Error::Io(e))?;
-        let writer = BufWriter::new(file);
-
-        bincode::serialize_into(writer, &self.inner)
-            .map_err(|e| match *e {
-                bincode::ErrorKind::Io(io) => Error::Io(io),
-                _ => Error::Bincode(e)
-            })?;
+impl Format for BinaryFormat {
+    type Error = Error;
+
+    fn to_writer<W, T>(&self, mut writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized
+    {
+        if self.header {
+            writer.write_all(&MAGIC)?;
+            writer.write_all(&[VERSION, self.flags()])?;
+        }
+
+        if !self.checksum {
+            return self.serialize_into(writer, value);
+        }
+
+        let mut writer = ChecksumWriter {
+            inner: writer,
+            hasher: crc32fast::Hasher::new(),
+        };
+
+        self.serialize_into(&mut writer, value)?;
+
+        let checksum = writer.hasher.finalize();
+
+        writer.inner.write_all(&checksum.to_le_bytes())?;
 
         Ok(())
     }
-}
 
-impl<T> Binary<T>
-where
-    T: DeserializeOwned
-{
-    pub fn load<P>(given: P) -> Result<Self, Error>
+    fn from_reader<R, T>(&self, mut reader: R) -> Result<T, Self::Error>
     where
-        P: Into<PathBuf>
+        R: Read,
+        T: DeserializeOwned
     {
-        let path = given.into().into();
-        let file = OpenOptions::new()
-            .read(true)
-            .open(&path)
-            .map_err(|e| Error::Io(e))?;
-        let reader = BufReader::new(file);
-
-        let inner = bincode::deserialize_from(reader)
-            .map_err(|e| match *e {
-                bincode::ErrorKind::Io(io) => Error::Io(io),
-                _ => Error::Bincode(e)
-            })?;
-
-        Ok(Binary {
-            inner,
-            path
-        })
+        let format = if self.header {
+            let mut header = [0; HEADER_LEN];
+
+            reader.read_exact(&mut header)
+                .map_err(|err| match err.kind() {
+                    std::io::ErrorKind::UnexpectedEof => Error::InvalidHeader,
+                    _ => Error::Io(err),
+                })?;
+
+            self.read_header(&header)?
+        } else {
+            *self
+        };
+
+        if !format.checksum {
+            return format.deserialize_from(reader);
+        }
+
+        let mut buffer = Vec::new();
+
+        reader.read_to_end(&mut buffer)?;
+
+        decode_checked(&format, &buffer)
     }
 }
 
-impl<T> std::fmt::Debug for Binary<T>
+/// verifies the checksum footer and decodes the data before it
+fn decode_checked<T>(format: &BinaryFormat, buffer: &[u8]) -> Result<T, Error>
 where
-    T: std::fmt::Debug
+    T: DeserializeOwned
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Binary")
-            .field("inner", &self.inner)
-            .field("path", &self.path)
-            .finish()
-    }
-}
+    let Some(split) = buffer.len().checked_sub(CHECKSUM_LEN) else {
+        return Err(Error::Corrupted);
+    };
+    let (data, footer) = buffer.split_at(split);
+    let mut expected = [0; CHECKSUM_LEN];
+    expected.copy_from_slice(footer);
 
-impl<T> std::convert::AsRef<T> for Binary<T> {
-    fn as_ref(&self) -> &T {
-        &self.inner
+    if crc32fast::hash(data) != u32::from_le_bytes(expected) {
+        return Err(Error::Corrupted);
     }
+
+    format.deserialize(data)
 }
 
-impl<T> std::convert::AsMut<T> for Binary<T> {
-    fn as_mut(&mut self) -> &mut T {
-        &mut self.inner
+impl BinaryFormat {
+    /// decodes a value from a slice containing the entire file
+    ///
+    /// same as from_reader without copying the data into a buffer first
+    pub fn from_slice<T>(&self, bytes: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned
+    {
+        let (format, body) = if self.header {
+            if bytes.len() < HEADER_LEN {
+                return Err(Error::InvalidHeader);
+            }
+
+            let (header, body) = bytes.split_at(HEADER_LEN);
+
+            (self.read_header(header)?, body)
+        } else {
+            (*self, bytes)
+        };
+
+        if format.checksum {
+            decode_checked(&format, body)
+        } else {
+            format.deserialize(body)
+        }
     }
 }
 
-impl<T> Clone for Binary<T>
+#[cfg(feature = "mmap")]
+impl<T> FileWrapped<T, BinaryFormat>
 where
-    T: Clone
+    T: DeserializeOwned
 {
-    fn clone(&self) -> Self {
-        Binary {
-            inner: self.inner.clone(),
-            path: self.path.clone(),
-        }
+    /// loads the specified file by memory mapping it instead of reading it
+    /// into memory first
+    ///
+    /// saves replace the file with a rename so a mapped file is not changed
+    /// by them. the file must not be truncated or written to in place by
+    /// other processes while it is loading.
+    pub fn load_mmap<P>(given: P) -> Result<Self, Error>
+    where
+        P: Into<std::path::PathBuf>
+    {
+        Self::load_mmap_with(given, BinaryFormat::default())
+    }
+
+    /// loads the specified file by memory mapping it using the provided
+    /// format
+    ///
+    /// see load_mmap for details
+    pub fn load_mmap_with<P>(given: P, format: BinaryFormat) -> Result<Self, Error>
+    where
+        P: Into<std::path::PathBuf>
+    {
+        let path = given.into();
+        let file = std::fs::File::open(&path)
+            .map_err(|e| FileError::new(Operation::Open, &path, e))?;
+
+        // SAFETY: the map is only read while decoding and dropped before
+        // returning. see the function docs for the requirements on other
+        // writers
+        let map = unsafe { memmap2::Mmap::map(&file) }
+            .map_err(|e| FileError::new(Operation::Read, &path, e))?;
+
+        let inner = format.from_slice(&map)?;
+
+        Ok(Self::with_format(inner, path, format))
     }
 }
 
+/// a value stored in a binary file
+pub type Binary<T> = FileWrapped<T, BinaryFormat>;
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::wrapper;
+    use std::path::PathBuf;
+
+    /// a reader that always fails
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(IoError::other("failed to read"))
+        }
+    }
+    use crate::wrapper::ModifyError;
 
     #[test]
     fn base() {
@@ -180,4 +563,211 @@ mod test {
 
         assert_eq!(wrapper.inner(), and_back.inner());
     }
+
+    #[test]
+    fn corrupted() {
+        let file_name = "test.corrupted.binary";
+        let wrapper = Binary::new(vec![1u64, 2, 3], file_name);
+
+        wrapper.save().expect("failed to save binary file");
+
+        let mut bytes = std::fs::read(file_name).expect("failed to read binary file");
+        bytes[HEADER_LEN] ^= 0xff;
+        std::fs::write(file_name, &bytes).expect("failed to write binary file");
+
+        match Binary::<Vec<u64>>::load(file_name) {
+            Err(Error::Corrupted) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        std::fs::write(file_name, &bytes[..bytes.len() - 6]).expect("failed to write binary file");
+
+        match Binary::<Vec<u64>>::load(file_name) {
+            Err(Error::Corrupted) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap() {
+        let file_name = "test.mmap.binary";
+        let data: Vec<u64> = (0..1024).collect();
+
+        Binary::new(data.clone(), file_name).save()
+            .expect("failed to save binary file");
+
+        let and_back: Binary<Vec<u64>> = Binary::load_mmap(file_name)
+            .expect("failed to load mapped binary file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        Binary::with_format(data.clone(), file_name, BinaryFormat::legacy())
+            .save()
+            .expect("failed to save legacy binary file");
+
+        let and_back: Binary<Vec<u64>> = Binary::load_mmap_with(file_name, BinaryFormat::legacy())
+            .expect("failed to load mapped legacy binary file");
+
+        assert_eq!(and_back.inner(), &data);
+    }
+
+    #[test]
+    fn header() {
+        let file_name = "test.header.binary";
+
+        Binary::with_format(1u64, file_name, BinaryFormat::without_checksum())
+            .save()
+            .expect("failed to save binary file");
+
+        let and_back: Binary<u64> = Binary::load(file_name)
+            .expect("failed to load binary file without checksum");
+
+        assert_eq!(and_back.inner(), &1);
+
+        Binary::with_format(1u64, file_name, BinaryFormat::legacy())
+            .save()
+            .expect("failed to save legacy binary file");
+
+        match Binary::<u64>::load(file_name) {
+            Err(Error::InvalidHeader) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([VERSION + 1, 0]);
+        std::fs::write(file_name, &bytes).expect("failed to write binary file");
+
+        match Binary::<u64>::load(file_name) {
+            Err(Error::UnsupportedVersion(version)) => assert_eq!(version, VERSION + 1),
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        std::fs::write(file_name, MAGIC).expect("failed to write binary file");
+
+        match Binary::<u64>::load(file_name) {
+            Err(Error::InvalidHeader) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        let failing = std::io::Read::chain(&MAGIC[..2], FailingReader);
+
+        match BinaryFormat::default().from_reader::<_, u64>(failing) {
+            Err(Error::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::Other),
+            result => panic!("unexpected read result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn create() {
+        let file_name = "test.create.binary";
+
+        let _ = std::fs::remove_file(file_name);
+
+        let wrapper = Binary::create(1u64, file_name)
+            .expect("failed to create binary file");
+
+        wrapper.save().expect("failed to save binary file");
+
+        match Binary::create(2u64, file_name) {
+            Err(Error::File(err)) => assert_eq!(err.io().kind(), std::io::ErrorKind::AlreadyExists),
+            Err(err) => panic!("unexpected create error: {}", err),
+            Ok(_) => panic!("created over an existing binary file"),
+        }
+    }
+
+    #[test]
+    fn modify() {
+        let file_name = "test.modify.binary";
+        let mut wrapper = Binary::new(vec![1u64], file_name);
+
+        let len = wrapper.modify(|inner| {
+            inner.push(2);
+            inner.len()
+        }).expect("failed to modify binary file");
+
+        assert_eq!(len, 2);
+
+        let result = wrapper.try_modify(|inner| {
+            if inner.len() > 1 {
+                Err("too many values")
+            } else {
+                inner.push(3);
+                Ok(())
+            }
+        });
+
+        assert!(matches!(result, Err(ModifyError::Modify("too many values"))));
+
+        let and_back: Binary<Vec<u64>> = Binary::load(file_name)
+            .expect("failed to load binary file");
+
+        assert_eq!(and_back.inner(), &vec![1, 2]);
+    }
+
+    #[test]
+    fn options() {
+        let file_name = "test.options.binary";
+        let data: Vec<u64> = vec![1, 2, 3];
+
+        let format = BinaryFormat::default()
+            .with_int_encoding(IntEncoding::Variable)
+            .with_endian(Endian::Big);
+
+        let mut varint = Vec::new();
+        format.to_writer(&mut varint, &data).expect("failed to write varint data");
+
+        let mut fixint = Vec::new();
+        BinaryFormat::default().to_writer(&mut fixint, &data).expect("failed to write fixint data");
+
+        assert!(varint.len() < fixint.len());
+
+        // the header records the encoding
+        let and_back: Vec<u64> = BinaryFormat::default().from_reader(varint.as_slice())
+            .expect("failed to read varint data");
+
+        assert_eq!(and_back, data);
+
+        let legacy = BinaryFormat::legacy().with_int_encoding(IntEncoding::Variable);
+
+        Binary::with_format(data.clone(), file_name, legacy).save()
+            .expect("failed to save binary file");
+
+        let and_back: Binary<Vec<u64>> = Binary::load_with(file_name, legacy)
+            .expect("failed to load binary file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        // decoding stops once more than the limit is read
+        let mut bytes = u64::MAX.to_le_bytes().to_vec();
+        bytes.extend([0; 128]);
+
+        let result: Result<Vec<u64>, _> = BinaryFormat::legacy()
+            .with_limit(64)
+            .from_slice(&bytes);
+
+        match result {
+            Err(Error::Bincode(err)) => assert!(matches!(*err, bincode::ErrorKind::SizeLimit)),
+            result => panic!("unexpected decode result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio() {
+        let file_name = "test.tokio.binary";
+        let inner = usize::MAX;
+
+        let wrapper = Binary::new(inner, file_name);
+
+        wrapper.save_async()
+            .await
+            .expect("failed to save to tokio binary file");
+
+        let and_back: Binary<usize> = Binary::load_async(file_name)
+            .await
+            .expect("failed to load tokio binary file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
 }
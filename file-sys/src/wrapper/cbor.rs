@@ -0,0 +1,150 @@
+use std::io::{Read, Write};
+use std::io::Error as IoError;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::ErrorKind;
+use super::error::FileError;
+use super::format::{Format, FileWrapped};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    File(FileError),
+    Encode(String),
+    Decode(ciborium::de::Error<IoError>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::File(e) => fmt::Display::fmt(e, f),
+            Error::Encode(msg) => write!(f, "Encode({})", msg),
+            Error::Decode(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::File(e) => Some(e),
+            Error::Decode(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl Error {
+    /// returns the general category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) => ErrorKind::Io,
+            Error::File(_) => ErrorKind::File,
+            Error::Encode(_) |
+            Error::Decode(_) => ErrorKind::Format,
+        }
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::File(err)
+    }
+}
+
+fn map_ser_error(err: ciborium::ser::Error<IoError>) -> Error {
+    match err {
+        ciborium::ser::Error::Io(io) => Error::Io(io),
+        ciborium::ser::Error::Value(msg) => Error::Encode(msg),
+    }
+}
+
+fn map_de_error(err: ciborium::de::Error<IoError>) -> Error {
+    match err {
+        ciborium::de::Error::Io(io) => Error::Io(io),
+        _ => Error::Decode(err),
+    }
+}
+
+/// encodes values as cbor
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CborFormat;
+
+impl Format for CborFormat {
+    type Error = Error;
+
+    fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized
+    {
+        ciborium::into_writer(value, writer)
+            .map_err(map_ser_error)
+    }
+
+    fn from_reader<R, T>(&self, reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        ciborium::from_reader(reader)
+            .map_err(map_de_error)
+    }
+}
+
+/// a value stored in a cbor file
+pub type Cbor<T> = FileWrapped<T, CborFormat>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wrapper;
+    use std::path::PathBuf;
+
+    #[test]
+    fn base() {
+        let file_name = "test.cbor";
+        let inner = usize::MAX;
+
+        wrapper::test::create_test_file(file_name);
+
+        let wrapper = Cbor::new(inner, file_name);
+
+        wrapper.save().expect("failed to save to cbor file");
+
+        let and_back: Cbor<usize> = Cbor::load(PathBuf::from(file_name))
+            .expect("failed to load cbor file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio() {
+        let file_name = "test.tokio.cbor";
+        let inner = vec![String::from("a"), String::from("b")];
+
+        let wrapper = Cbor::new(inner, file_name);
+
+        wrapper.save_async()
+            .await
+            .expect("failed to save to tokio cbor file");
+
+        let and_back: Cbor<Vec<String>> = Cbor::load_async(file_name)
+            .await
+            .expect("failed to load tokio cbor file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
+}
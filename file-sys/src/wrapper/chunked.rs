@@ -0,0 +1,348 @@
+use std::io::{Read, Write};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+
+use chacha20poly1305::{
+    aead::{OsRng, rand_core::RngCore, stream::{DecryptorBE32, EncryptorBE32}},
+    ChaCha20Poly1305, Key, XChaCha20Poly1305
+};
+use aes_gcm_siv::Aes256GcmSiv;
+
+use super::encrypted::{Cipher, Error};
+use super::nonce::{self, NonceCheck};
+
+/// the length of the tag added to every segment
+const TAG_LEN: usize = 16;
+
+/// the bytes of the nonce used by the STREAM counter and last segment flag
+const STREAM_OVERHEAD: usize = 5;
+
+/// returns the length of the nonce prefix stored at the start of the file
+pub(crate) fn nonce_len(cipher: Cipher) -> usize {
+    match cipher {
+        Cipher::XChaCha20Poly1305 => 24 - STREAM_OVERHEAD,
+        Cipher::ChaCha20Poly1305 => 12 - STREAM_OVERHEAD,
+        Cipher::Aes256GcmSiv => 12 - STREAM_OVERHEAD,
+    }
+}
+
+/// the error returned when a segment fails to encrypt
+fn encrypt_failed() -> IoError {
+    IoError::other("failed to encrypt segment")
+}
+
+enum StreamEncryptor {
+    XChaCha20Poly1305(EncryptorBE32<XChaCha20Poly1305>),
+    ChaCha20Poly1305(EncryptorBE32<ChaCha20Poly1305>),
+    // the expanded aes key schedule is much larger than the chacha keys
+    Aes256GcmSiv(Box<EncryptorBE32<Aes256GcmSiv>>),
+}
+
+impl StreamEncryptor {
+    /// creates an encryptor with a random nonce prefix returning the prefix
+    fn new(cipher: Cipher, key: &Key) -> (Self, Vec<u8>) {
+        let mut nonce = vec![0; nonce_len(cipher)];
+
+        OsRng.fill_bytes(&mut nonce);
+
+        let rtn = match cipher {
+            Cipher::XChaCha20Poly1305 => StreamEncryptor::XChaCha20Poly1305(
+                EncryptorBE32::new(key, nonce.as_slice().into())
+            ),
+            Cipher::ChaCha20Poly1305 => StreamEncryptor::ChaCha20Poly1305(
+                EncryptorBE32::new(key, nonce.as_slice().into())
+            ),
+            Cipher::Aes256GcmSiv => StreamEncryptor::Aes256GcmSiv(Box::new(
+                EncryptorBE32::new(key, nonce.as_slice().into())
+            )),
+        };
+
+        (rtn, nonce)
+    }
+
+    fn next(&mut self, aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), IoError> {
+        match self {
+            StreamEncryptor::XChaCha20Poly1305(e) => e.encrypt_next_in_place(aad, buffer),
+            StreamEncryptor::ChaCha20Poly1305(e) => e.encrypt_next_in_place(aad, buffer),
+            StreamEncryptor::Aes256GcmSiv(e) => e.encrypt_next_in_place(aad, buffer),
+        }.map_err(|_| encrypt_failed())
+    }
+
+    fn last(self, aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), IoError> {
+        match self {
+            StreamEncryptor::XChaCha20Poly1305(e) => e.encrypt_last_in_place(aad, buffer),
+            StreamEncryptor::ChaCha20Poly1305(e) => e.encrypt_last_in_place(aad, buffer),
+            StreamEncryptor::Aes256GcmSiv(e) => e.encrypt_last_in_place(aad, buffer),
+        }.map_err(|_| encrypt_failed())
+    }
+}
+
+enum StreamDecryptor {
+    XChaCha20Poly1305(DecryptorBE32<XChaCha20Poly1305>),
+    ChaCha20Poly1305(DecryptorBE32<ChaCha20Poly1305>),
+    Aes256GcmSiv(Box<DecryptorBE32<Aes256GcmSiv>>),
+}
+
+impl StreamDecryptor {
+    /// creates a decryptor from the nonce prefix stored in the file
+    ///
+    /// the nonce must be the length returned by nonce_len
+    fn new(cipher: Cipher, key: &Key, nonce: &[u8]) -> Self {
+        match cipher {
+            Cipher::XChaCha20Poly1305 => StreamDecryptor::XChaCha20Poly1305(
+                DecryptorBE32::new(key, nonce.into())
+            ),
+            Cipher::ChaCha20Poly1305 => StreamDecryptor::ChaCha20Poly1305(
+                DecryptorBE32::new(key, nonce.into())
+            ),
+            Cipher::Aes256GcmSiv => StreamDecryptor::Aes256GcmSiv(Box::new(
+                DecryptorBE32::new(key, nonce.into())
+            )),
+        }
+    }
+
+    fn next(&mut self, aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+        match self {
+            StreamDecryptor::XChaCha20Poly1305(d) => d.decrypt_next_in_place(aad, buffer),
+            StreamDecryptor::ChaCha20Poly1305(d) => d.decrypt_next_in_place(aad, buffer),
+            StreamDecryptor::Aes256GcmSiv(d) => d.decrypt_next_in_place(aad, buffer),
+        }.is_ok()
+    }
+
+    fn last(self, aad: &[u8], buffer: &mut Vec<u8>) -> bool {
+        match self {
+            StreamDecryptor::XChaCha20Poly1305(d) => d.decrypt_last_in_place(aad, buffer),
+            StreamDecryptor::ChaCha20Poly1305(d) => d.decrypt_last_in_place(aad, buffer),
+            StreamDecryptor::Aes256GcmSiv(d) => d.decrypt_last_in_place(aad, buffer),
+        }.is_ok()
+    }
+}
+
+/// returns the associated data of every segment which is the given data
+/// followed by the chunk size
+fn segment_aad(aad: &[u8], chunk_size: u32) -> Vec<u8> {
+    let mut rtn = Vec::with_capacity(aad.len() + 4);
+    rtn.extend_from_slice(aad);
+    rtn.extend_from_slice(&chunk_size.to_le_bytes());
+    rtn
+}
+
+/// encrypts the data written to it in segments of chunk_size bytes
+///
+/// the nonce prefix and chunk size are written first. a segment is only
+/// encrypted once more data is written after it so the final segment can be
+/// marked as last by finish
+pub(crate) struct ChunkWriter<W> {
+    inner: W,
+    encryptor: StreamEncryptor,
+    chunk_size: usize,
+    aad: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<W> ChunkWriter<W>
+where
+    W: Write
+{
+    pub(crate) fn new(
+        mut inner: W,
+        cipher: Cipher,
+        key: &Key,
+        aad: &[u8],
+        chunk_size: u32,
+        nonces: Option<&NonceCheck>,
+    ) -> Result<Self, Error> {
+        let (encryptor, nonce) = StreamEncryptor::new(cipher, key);
+
+        nonce::record(nonces, key, &nonce)?;
+
+        inner.write_all(&nonce)?;
+        inner.write_all(&chunk_size.to_le_bytes())?;
+
+        Ok(ChunkWriter {
+            inner,
+            encryptor,
+            chunk_size: chunk_size as usize,
+            aad: segment_aad(aad, chunk_size),
+            buffer: Vec::with_capacity(chunk_size as usize + TAG_LEN),
+        })
+    }
+
+    /// encrypts the remaining data as the last segment
+    pub(crate) fn finish(mut self) -> Result<(), IoError> {
+        self.encryptor.last(&self.aad, &mut self.buffer)?;
+        self.inner.write_all(&self.buffer)?;
+        self.inner.flush()
+    }
+}
+
+impl<W> Write for ChunkWriter<W>
+where
+    W: Write
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.buffer.len() == self.chunk_size {
+            self.encryptor.next(&self.aad, &mut self.buffer)?;
+            self.inner.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+
+        let len = buf.len().min(self.chunk_size - self.buffer.len());
+
+        self.buffer.extend_from_slice(&buf[..len]);
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// decrypts the segments written by a ChunkWriter
+///
+/// one byte past every segment is read to know if it is the last one so a
+/// file missing its final segments fails to decrypt
+pub(crate) struct ChunkReader<R> {
+    inner: R,
+    decryptor: Option<StreamDecryptor>,
+    segment_len: usize,
+    aad: Vec<u8>,
+    buffer: Vec<u8>,
+    position: usize,
+    lookahead: Option<u8>,
+    failed: bool,
+}
+
+/// reads the nonce prefix and chunk size from the start of the reader
+///
+/// chunk sizes of 0 or larger than max_chunk_size are rejected
+fn read_prefix<R>(reader: &mut R, cipher: Cipher, max_chunk_size: u32) -> Result<(Vec<u8>, u32), Error>
+where
+    R: Read
+{
+    let mut nonce = vec![0; nonce_len(cipher)];
+    let mut aad = [0; 4];
+
+    reader.read_exact(&mut nonce)
+        .and_then(|_| reader.read_exact(&mut aad))
+        .map_err(|_| Error::TruncatedHeader)?;
+
+    let chunk_size = u32::from_le_bytes(aad);
+
+    if chunk_size == 0 || chunk_size > max_chunk_size {
+        return Err(Error::InvalidEncoding);
+    }
+
+    Ok((nonce, chunk_size))
+}
+
+/// reads the chunk size stored at the start of a chunked file
+pub(crate) fn read_chunk_size<R>(reader: &mut R, cipher: Cipher, max_chunk_size: u32) -> Result<u32, Error>
+where
+    R: Read
+{
+    read_prefix(reader, cipher, max_chunk_size)
+        .map(|(_, chunk_size)| chunk_size)
+}
+
+impl<R> ChunkReader<R>
+where
+    R: Read
+{
+    /// reads the nonce prefix and chunk size from the start of the reader
+    ///
+    /// chunk sizes larger than max_chunk_size are rejected before anything
+    /// is allocated for them
+    pub(crate) fn new(mut inner: R, cipher: Cipher, key: &Key, aad: &[u8], max_chunk_size: u32) -> Result<Self, Error> {
+        let (nonce, chunk_size) = read_prefix(&mut inner, cipher, max_chunk_size)?;
+        let aad = segment_aad(aad, chunk_size);
+
+        Ok(ChunkReader {
+            inner,
+            decryptor: Some(StreamDecryptor::new(cipher, key, &nonce)),
+            segment_len: chunk_size as usize + TAG_LEN,
+            aad,
+            buffer: Vec::new(),
+            position: 0,
+            lookahead: None,
+            failed: false,
+        })
+    }
+
+    /// checks if a segment failed to decrypt
+    pub(crate) fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// reads and decrypts the next segment into the buffer
+    fn fill(&mut self) -> Result<(), IoError> {
+        let Some(mut decryptor) = self.decryptor.take() else {
+            return Ok(());
+        };
+
+        self.buffer.clear();
+        self.position = 0;
+        self.buffer.extend(self.lookahead.take());
+
+        let mut read = self.buffer.len();
+        self.buffer.resize(self.segment_len + 1, 0);
+
+        while read < self.buffer.len() {
+            match self.inner.read(&mut self.buffer[read..]) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(err) if err.kind() == IoErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let decrypted = if read > self.segment_len {
+            self.lookahead = self.buffer.pop();
+
+            let decrypted = decryptor.next(&self.aad, &mut self.buffer);
+            self.decryptor = Some(decryptor);
+
+            decrypted
+        } else {
+            self.buffer.truncate(read);
+
+            decryptor.last(&self.aad, &mut self.buffer)
+        };
+
+        if !decrypted {
+            self.failed = true;
+            self.decryptor = None;
+            self.buffer.clear();
+
+            return Err(IoError::new(IoErrorKind::InvalidData, "failed to decrypt segment"));
+        }
+
+        Ok(())
+    }
+}
+
+impl<R> Read for ChunkReader<R>
+where
+    R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+
+            self.fill()?;
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.position);
+
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind as IoErrorKind};
+use std::path::{PathBuf, Path};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use super::atomic;
+use super::error::{FileError, Operation};
+use super::format::Format;
+use super::limit::check_size;
+
+/// merges the layer into the base
+///
+/// objects are merged key by key and any other value in the layer replaces
+/// the value in the base
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// returns the parts of the value that differ from the base
+///
+/// keys removed from the value are not returned since a layer cannot remove
+/// keys from the layers below it
+fn diff(base: &Value, value: &Value) -> Option<Value> {
+    if base == value {
+        return None;
+    }
+
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            let mut rtn = Map::new();
+
+            for (key, value) in value {
+                let changed = match base.get(key) {
+                    Some(existing) => diff(existing, value),
+                    None => Some(value.clone()),
+                };
+
+                if let Some(changed) = changed {
+                    rtn.insert(key.clone(), changed);
+                }
+            }
+
+            if rtn.is_empty() {
+                None
+            } else {
+                Some(Value::Object(rtn))
+            }
+        }
+        _ => Some(value.clone()),
+    }
+}
+
+/// converts the value by encoding it and decoding it again with the format
+///
+/// used when serde_json fails to convert the value directly so the error
+/// returned comes from the format instead of being an io error
+fn transcode<F, V, T>(format: &F, value: &V) -> Result<T, F::Error>
+where
+    F: Format,
+    V: Serialize,
+    T: DeserializeOwned
+{
+    let mut bytes = Vec::new();
+
+    format.to_writer(&mut bytes, value)?;
+    format.from_reader(bytes.as_slice())
+}
+
+/// reads a single layer returning None if the file is missing or empty
+fn read_layer<F>(path: &Path, format: &F) -> Result<Option<Value>, F::Error>
+where
+    F: Format
+{
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(FileError::new(Operation::Open, path, err).into()),
+    };
+
+    let len = file.metadata()
+        .map_err(FileError::map(Operation::Read, path))?
+        .len();
+
+    if len == 0 {
+        return Ok(None);
+    }
+
+    check_size(format.max_size(), path, len)?;
+
+    Ok(Some(format.from_reader(BufReader::new(file))?))
+}
+
+/// a value merged from an ordered list of files
+///
+/// layers are given from lowest to highest priority, e.g. defaults, system
+/// then user. each layer is decoded with the Format into a generic value and
+/// merged over the layers below it with objects merged by key and every
+/// other value replaced. missing or empty files are skipped. saving only
+/// writes the parts of the value that differ from the lower layers to the
+/// top layer.
+///
+/// the format must be self describing, like json, yaml or cbor, since the
+/// layers are decoded without knowing the type.
+pub struct ConfigStack<T, F> {
+    inner: T,
+    layers: Vec<PathBuf>,
+    format: F,
+    base: Value,
+}
+
+impl<T, F> ConfigStack<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// loads and merges the layers using the provided format
+    ///
+    /// panics if no layers are given
+    pub fn load_with<I, P>(layers: I, format: F) -> Result<Self, F::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>
+    {
+        let layers: Vec<PathBuf> = layers.into_iter()
+            .map(Into::into)
+            .collect();
+
+        assert!(!layers.is_empty(), "a config stack needs at least one layer");
+
+        let (inner, base) = merge_layers(&layers, &format)?;
+
+        Ok(ConfigStack {
+            inner,
+            layers,
+            format,
+            base,
+        })
+    }
+
+    /// loads and merges the layers again replacing the current value
+    pub fn reload(&mut self) -> Result<(), F::Error> {
+        let (inner, base) = merge_layers(&self.layers, &self.format)?;
+
+        self.inner = inner;
+        self.base = base;
+
+        Ok(())
+    }
+}
+
+/// merges every layer returning the decoded value and the merged value of
+/// every layer except the top
+fn merge_layers<T, F>(layers: &[PathBuf], format: &F) -> Result<(T, Value), F::Error>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    let mut base = Value::Null;
+
+    for path in &layers[..layers.len() - 1] {
+        if let Some(layer) = read_layer(path, format)? {
+            merge(&mut base, layer);
+        }
+    }
+
+    let mut merged = base.clone();
+
+    if let Some(layer) = read_layer(&layers[layers.len() - 1], format)? {
+        merge(&mut merged, layer);
+    }
+
+    let inner = match T::deserialize(&merged) {
+        Ok(inner) => inner,
+        Err(_) => transcode(format, &merged)?,
+    };
+
+    Ok((inner, base))
+}
+
+impl<T, F> ConfigStack<T, F>
+where
+    T: DeserializeOwned,
+    F: Format + Default
+{
+    /// loads and merges the layers
+    ///
+    /// see load_with for details
+    pub fn load<I, P>(layers: I) -> Result<Self, F::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>
+    {
+        Self::load_with(layers, F::default())
+    }
+}
+
+impl<T, F> ConfigStack<T, F> {
+    /// returns a reference to the merged value
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// returns a mutable reference to the merged value
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// returns the paths of every layer from lowest to highest priority
+    pub fn layers(&self) -> &[PathBuf] {
+        &self.layers
+    }
+
+    /// returns the path of the top layer that changes are saved to
+    pub fn top(&self) -> &Path {
+        &self.layers[self.layers.len() - 1]
+    }
+
+    /// returns the format used for every layer
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// consumes the struct returning the merged value
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, F> ConfigStack<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// saves the parts of the value that differ from the lower layers to the
+    /// top layer
+    ///
+    /// uses the same temp file and rename as FileWrapped::save. anything in
+    /// the top layer that matches the lower layers is removed from it
+    pub fn save(&self) -> Result<(), F::Error> {
+        let value = match serde_json::to_value(&self.inner) {
+            Ok(value) => value,
+            Err(_) => transcode(&self.format, &self.inner)?,
+        };
+
+        let top = match diff(&self.base, &value) {
+            Some(changed) => changed,
+            None if self.base.is_object() => Value::Object(Map::new()),
+            None => value,
+        };
+
+        atomic::save(
+            self.top(),
+            &self.format.permissions(),
+            self.format.sync_mode(),
+            |writer| self.format.to_writer(writer, &top)
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<T, F> fmt::Debug for ConfigStack<T, F>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigStack")
+            .field("inner", &self.inner)
+            .field("layers", &self.layers)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use crate::wrapper::JsonFormat;
+
+    #[test]
+    fn layers() {
+        let defaults = "test.config_stack.defaults.json";
+        let system = "test.config_stack.system.json";
+        let user = "test.config_stack.user.json";
+        let _ = std::fs::remove_file(system);
+
+        std::fs::write(defaults, r#"{"name":"a","port":1,"nested":{"x":1,"y":2}}"#)
+            .expect("failed to write defaults");
+        std::fs::write(user, r#"{"port":2,"nested":{"y":3}}"#)
+            .expect("failed to write user config");
+
+        let mut stack: ConfigStack<Value, JsonFormat> = ConfigStack::load([defaults, system, user])
+            .expect("failed to load config stack");
+
+        assert_eq!(stack.inner(), &json!({"name": "a", "port": 2, "nested": {"x": 1, "y": 3}}));
+
+        stack.inner_mut()["port"] = json!(5);
+        stack.inner_mut()["nested"]["y"] = json!(2);
+        stack.save().expect("failed to save config stack");
+
+        let saved: Value = serde_json::from_slice(&std::fs::read(user).expect("failed to read user config"))
+            .expect("failed to parse user config");
+
+        assert_eq!(saved, json!({"port": 5}));
+        assert_eq!(
+            std::fs::read_to_string(defaults).expect("failed to read defaults"),
+            r#"{"name":"a","port":1,"nested":{"x":1,"y":2}}"#
+        );
+
+        stack.reload().expect("failed to reload config stack");
+
+        assert_eq!(stack.inner(), &json!({"name": "a", "port": 5, "nested": {"x": 1, "y": 2}}));
+    }
+
+    #[test]
+    fn format_error() {
+        use std::collections::HashMap;
+
+        let defaults = "test.config_stack_error.defaults.json";
+        let user = "test.config_stack_error.user.json";
+
+        std::fs::write(defaults, r#"{"port":1}"#).expect("failed to write defaults");
+        std::fs::write(user, r#"{"port":"high"}"#).expect("failed to write user config");
+
+        match ConfigStack::<HashMap<String, u64>, JsonFormat>::load([defaults, user]) {
+            Err(err) => assert_eq!(err.kind(), crate::ErrorKind::Format),
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        let missing = "test.config_stack_error.missing.json";
+        let _ = std::fs::remove_file(missing);
+
+        let mut stack: ConfigStack<Option<HashMap<Vec<u8>, u64>>, JsonFormat> = ConfigStack::load([missing])
+            .expect("failed to load config stack");
+
+        *stack.inner_mut() = Some(HashMap::from([(vec![1], 1)]));
+
+        match stack.save() {
+            Err(err) => assert_eq!(err.kind(), crate::ErrorKind::Format),
+            result => panic!("unexpected save result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::format::{Format, FileWrapped};
+
+/// possible errors from converting a file between formats
+pub enum ConvertError<L, S> {
+    /// the source file could not be loaded
+    Load(L),
+    /// the destination file could not be saved
+    Save(S),
+}
+
+impl<L, S> fmt::Display for ConvertError<L, S>
+where
+    L: fmt::Display,
+    S: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Load(e) => fmt::Display::fmt(e, f),
+            ConvertError::Save(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl<L, S> fmt::Debug for ConvertError<L, S>
+where
+    L: fmt::Debug,
+    S: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Load(e) => f.debug_tuple("Load").field(e).finish(),
+            ConvertError::Save(e) => f.debug_tuple("Save").field(e).finish(),
+        }
+    }
+}
+
+impl<L, S> std::error::Error for ConvertError<L, S>
+where
+    L: std::error::Error + 'static,
+    S: std::error::Error + 'static
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::Load(e) => Some(e),
+            ConvertError::Save(e) => Some(e),
+        }
+    }
+}
+
+impl<T, F> FileWrapped<T, F> {
+    /// consumes the wrapper returning a new one with the same value and path
+    /// that uses the given format
+    ///
+    /// the permissions and sync mode set on the wrapper are kept
+    pub fn into_format<G>(self, format: G) -> FileWrapped<T, G> {
+        let permissions = self.permissions().copied();
+        let sync = self.sync_mode();
+        let path = self.path().to_path_buf();
+
+        let mut rtn = FileWrapped::with_format(self.into_inner(), path, format);
+        rtn.set_permissions(permissions);
+        rtn.set_sync_mode(sync);
+        rtn
+    }
+}
+
+/// loads the source file with one format and saves the value to the
+/// destination with another
+///
+/// the value is decoded into T so only data that T keeps is converted. the
+/// destination is saved with the same temp file and rename as
+/// FileWrapped::save
+pub fn convert_with<T, A, B, S, D>(
+    src: S,
+    from: A,
+    dst: D,
+    to: B
+) -> Result<FileWrapped<T, B>, ConvertError<A::Error, B::Error>>
+where
+    T: Serialize + DeserializeOwned,
+    A: Format,
+    B: Format,
+    S: Into<PathBuf>,
+    D: Into<PathBuf>
+{
+    let loaded = FileWrapped::<T, A>::load_with(src, from)
+        .map_err(ConvertError::Load)?;
+
+    let mut converted = loaded.into_format(to);
+    converted.set_path(dst);
+    converted.save().map_err(ConvertError::Save)?;
+
+    Ok(converted)
+}
+
+/// loads the source file with one format and saves the value to the
+/// destination with another using the default of each format
+///
+/// see convert_with for details
+pub fn convert<T, A, B, S, D>(
+    src: S,
+    dst: D
+) -> Result<FileWrapped<T, B>, ConvertError<A::Error, B::Error>>
+where
+    T: Serialize + DeserializeOwned,
+    A: Format + Default,
+    B: Format + Default,
+    S: Into<PathBuf>,
+    D: Into<PathBuf>
+{
+    convert_with(src, A::default(), dst, B::default())
+}
+
+#[cfg(all(test, feature = "json", feature = "binary"))]
+mod test {
+    use super::*;
+    use crate::wrapper::{Binary, BinaryFormat, Json, JsonFormat};
+
+    #[test]
+    fn binary_and_json() {
+        let binary_file = "test.convert.binary";
+        let json_file = "test.convert.json";
+
+        Binary::new(vec![1u64, 2, 3], binary_file).save()
+            .expect("failed to save binary file");
+
+        let json = convert::<Vec<u64>, BinaryFormat, JsonFormat, _, _>(binary_file, json_file)
+            .expect("failed to convert binary to json");
+
+        assert_eq!(json.path(), std::path::Path::new(json_file));
+        assert_eq!(
+            std::fs::read_to_string(json_file).expect("failed to read json file"),
+            "[1,2,3]"
+        );
+
+        std::fs::write(json_file, "[4,5]").expect("failed to write json file");
+
+        convert::<Vec<u64>, JsonFormat, BinaryFormat, _, _>(json_file, binary_file)
+            .expect("failed to convert json to binary");
+
+        let and_back: Binary<Vec<u64>> = Binary::load(binary_file)
+            .expect("failed to load binary file");
+
+        assert_eq!(and_back.inner(), &vec![4, 5]);
+
+        let json: Json<Vec<u64>> = and_back.into_format(JsonFormat);
+
+        assert_eq!(json.path(), std::path::Path::new(binary_file));
+
+        match convert::<Vec<u64>, BinaryFormat, JsonFormat, _, _>("test.convert.missing", json_file) {
+            Err(ConvertError::Load(_)) => {}
+            result => panic!("unexpected convert result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn encrypted() {
+        use crate::wrapper::{Encrypted, EncryptedFormat};
+
+        let json_file = "test.convert_encrypted.json";
+        let encrypted_file = "test.convert.encrypted";
+
+        Json::new(7u64, json_file).save().expect("failed to save json file");
+
+        convert_with::<u64, _, _, _, _>(json_file, JsonFormat, encrypted_file, EncryptedFormat::new([1; 32]))
+            .expect("failed to convert json to encrypted");
+
+        let and_back: Encrypted<u64> = Encrypted::load(encrypted_file, [1; 32])
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &7);
+    }
+}
@@ -0,0 +1,26 @@
+//! keys stored in hardware devices for envelope encryption
+
+/// the error returned by a device
+pub type DeviceError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// a key stored in a device such as a tpm or pkcs#11 token
+///
+/// the key never leaves the device. the random data key of a file is
+/// passed to the device to be wrapped when saving and the wrapped key is
+/// passed back to be unwrapped when loading. implementations hold the
+/// session or context of the device and call into its library
+pub trait KeyDevice: Send + Sync {
+    /// an id for the key on the device
+    ///
+    /// the id is stored next to the wrapped key so only stanzas wrapped by
+    /// the same key are passed to unwrap. it must be at most 255 bytes
+    fn key_id(&self) -> &[u8];
+
+    /// wraps the data key with the key on the device
+    ///
+    /// the wrapped key must be at most 65535 bytes
+    fn wrap(&self, data_key: &[u8]) -> Result<Vec<u8>, DeviceError>;
+
+    /// unwraps a data key returned by wrap
+    fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, DeviceError>;
+}
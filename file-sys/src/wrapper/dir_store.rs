@@ -0,0 +1,382 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{BufReader, ErrorKind as IoErrorKind};
+use std::marker::PhantomData;
+use std::path::{PathBuf, Path};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::atomic;
+use super::error::{FileError, Operation};
+use super::format::Format;
+
+/// the name used for an empty key
+///
+/// every other name has two hex digits after a '%' so it cannot collide
+const EMPTY_KEY: &str = "%";
+
+/// escapes every byte of the key that is not alphanumeric, '-' or '_'
+///
+/// the name never starts with a '.' so it will not collide with temp or lock
+/// files. an empty key is encoded as a lone '%' since an empty name would be
+/// the directory itself
+fn encode_key(key: &str) -> String {
+    if key.is_empty() {
+        return String::from(EMPTY_KEY);
+    }
+
+    let mut rtn = String::with_capacity(key.len());
+
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            rtn.push(byte as char);
+        } else {
+            rtn.push_str(&format!("%{:02x}", byte));
+        }
+    }
+
+    rtn
+}
+
+/// reverses encode_key returning None if the name is not a valid encoding
+///
+/// names that encode_key would not produce, such as unescaped punctuation
+/// or upper case hex, are rejected so every key maps to a single name
+fn decode_key(name: &str) -> Option<String> {
+    if name == EMPTY_KEY {
+        return Some(String::new());
+    }
+
+    let bytes = name.as_bytes();
+    let mut rtn = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+
+            rtn.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            rtn.push(bytes[index]);
+            index += 1;
+        }
+    }
+
+    let key = String::from_utf8(rtn).ok()?;
+
+    if encode_key(&key) != name {
+        return None;
+    }
+
+    Some(key)
+}
+
+/// a key value store where every key is saved to its own file in a directory
+///
+/// keys are converted to file names with Display and parsed back with
+/// FromStr. every entry is encoded with the same Format so using an
+/// EncryptedFormat will encrypt each entry.
+///
+/// letters are kept as is in the file names so keys that only differ in
+/// case share a file on case insensitive filesystems such as the defaults
+/// on windows and macos.
+pub struct DirStore<K, V, F> {
+    dir: Box<Path>,
+    format: F,
+    extension: Option<String>,
+    _marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V, F> DirStore<K, V, F> {
+    /// creates a new DirStore for the directory using the provided format
+    ///
+    /// no checks are made on the directory to ensure that it exists
+    pub fn with_format<P>(dir: P, format: F) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        DirStore {
+            dir: dir.into().into(),
+            format,
+            extension: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// sets the extension added to every entry file name
+    ///
+    /// only files with the extension are considered entries
+    pub fn with_extension<E>(mut self, extension: E) -> Self
+    where
+        E: Into<String>
+    {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// returns the directory of the store
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// returns the format used for every entry
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// returns the extension added to every entry
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// returns the key of the file name if it is an entry of the store
+    fn name_to_key(&self, name: &str) -> Option<String> {
+        let stem = match &self.extension {
+            Some(ext) => name.strip_suffix(ext.as_str())?.strip_suffix('.')?,
+            None => name,
+        };
+
+        if stem.is_empty() || stem.starts_with('.') {
+            return None;
+        }
+
+        decode_key(stem)
+    }
+}
+
+impl<K, V, F> DirStore<K, V, F>
+where
+    F: Default
+{
+    /// creates a new DirStore for the directory
+    ///
+    /// see with_format for details
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        Self::with_format(dir, F::default())
+    }
+}
+
+impl<K, V, F> DirStore<K, V, F>
+where
+    K: fmt::Display,
+    F: Format
+{
+    /// creates a new DirStore using the provided format and creates the
+    /// directory if it is missing
+    pub fn open_with<P>(dir: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let store = Self::with_format(dir, format);
+
+        std::fs::create_dir_all(&store.dir)
+            .map_err(FileError::map(Operation::Create, &store.dir))?;
+
+        Ok(store)
+    }
+
+    /// returns the file path of the key
+    pub fn path_for(&self, key: &K) -> PathBuf {
+        let mut name = encode_key(&key.to_string());
+
+        if let Some(ext) = &self.extension {
+            name.push('.');
+            name.push_str(ext);
+        }
+
+        self.dir.join(name)
+    }
+
+    /// checks if the key has a file
+    pub fn contains(&self, key: &K) -> Result<bool, F::Error> {
+        let path = self.path_for(key);
+
+        Ok(path.try_exists()
+            .map_err(FileError::map(Operation::Open, &path))?)
+    }
+
+    /// deletes the file of the key returning true if it existed
+    pub fn remove(&self, key: &K) -> Result<bool, F::Error> {
+        let path = self.path_for(key);
+
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == IoErrorKind::NotFound => Ok(false),
+            Err(err) => Err(FileError::new(Operation::Write, path, err).into()),
+        }
+    }
+}
+
+impl<K, V, F> DirStore<K, V, F>
+where
+    K: fmt::Display,
+    V: Serialize,
+    F: Format
+{
+    /// saves the value to the file of the key
+    ///
+    /// uses the same temp file and rename as FileWrapped::save
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), F::Error> {
+        atomic::save(
+            &self.path_for(key),
+            &self.format.permissions(),
+            self.format.sync_mode(),
+            |writer| self.format.to_writer(writer, value)
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<K, V, F> DirStore<K, V, F>
+where
+    K: fmt::Display,
+    V: DeserializeOwned,
+    F: Format
+{
+    /// loads the value from the file of the key
+    ///
+    /// returns None if the file does not exist
+    pub fn get(&self, key: &K) -> Result<Option<V>, F::Error> {
+        let path = self.path_for(key);
+
+        let file = match OpenOptions::new().read(true).open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(FileError::new(Operation::Open, path, err).into()),
+        };
+
+        Ok(Some(self.format.from_reader(BufReader::new(file))?))
+    }
+}
+
+impl<K, V, F> DirStore<K, V, F>
+where
+    K: FromStr,
+    F: Format
+{
+    /// scans the directory for the keys of every entry
+    ///
+    /// files that do not decode into a key are skipped
+    pub fn keys(&self) -> Result<Vec<K>, F::Error> {
+        let mut rtn = Vec::new();
+
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(FileError::map(Operation::Read, &self.dir))?;
+
+        for entry in entries {
+            let entry = entry.map_err(FileError::map(Operation::Read, &self.dir))?;
+
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if !file_type.is_file() {
+                continue;
+            }
+
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str() else {
+                continue;
+            };
+
+            if let Some(key) = self.name_to_key(name).and_then(|key| key.parse().ok()) {
+                rtn.push(key);
+            }
+        }
+
+        Ok(rtn)
+    }
+}
+
+impl<K, V, F> fmt::Debug for DirStore<K, V, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DirStore")
+            .field("dir", &self.dir)
+            .field("extension", &self.extension)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V, F> Clone for DirStore<K, V, F>
+where
+    F: Clone
+{
+    fn clone(&self) -> Self {
+        DirStore {
+            dir: self.dir.clone(),
+            format: self.format.clone(),
+            extension: self.extension.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn key_encoding() {
+        for key in ["plain", "with space", "../up", ".hidden", "100%", "ünï", ""] {
+            let encoded = encode_key(key);
+
+            assert!(!encoded.starts_with('.'));
+            assert!(!encoded.contains('/'));
+            assert_eq!(decode_key(&encoded).as_deref(), Some(key));
+        }
+
+        assert_eq!(decode_key("bad%2"), None);
+        assert_eq!(decode_key("a.b"), None);
+        assert_eq!(decode_key("%2F"), None);
+        assert_eq!(decode_key("%61"), None);
+        assert_eq!(encode_key(""), "%");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn base() {
+        use crate::wrapper::JsonFormat;
+
+        let dir = "test.dir_store";
+        let _ = std::fs::remove_dir_all(dir);
+
+        let store: DirStore<String, Vec<u64>, JsonFormat> = DirStore::open_with(dir, JsonFormat)
+            .expect("failed to open dir store");
+        let store = store.with_extension("json");
+
+        let first = String::from("first");
+        let second = String::from("second/key");
+        let empty = String::new();
+
+        store.insert(&first, &vec![1]).expect("failed to insert entry");
+        store.insert(&second, &vec![2, 3]).expect("failed to insert entry");
+        store.insert(&empty, &vec![4]).expect("failed to insert entry");
+        std::fs::write(Path::new(dir).join("other.txt"), b"ignored")
+            .expect("failed to write other file");
+
+        assert_eq!(store.get(&first).expect("failed to get entry"), Some(vec![1]));
+        assert_eq!(store.get(&second).expect("failed to get entry"), Some(vec![2, 3]));
+        assert_eq!(store.get(&empty).expect("failed to get entry"), Some(vec![4]));
+
+        let mut keys = store.keys().expect("failed to list keys");
+        keys.sort();
+
+        assert_eq!(keys, vec![empty.clone(), first.clone(), second.clone()]);
+
+        assert!(store.remove(&empty).expect("failed to remove entry"));
+
+        assert!(store.remove(&first).expect("failed to remove entry"));
+        assert!(!store.remove(&first).expect("failed to remove entry"));
+        assert_eq!(store.get(&first).expect("failed to get entry"), None);
+        assert_eq!(store.keys().expect("failed to list keys"), vec![second]);
+
+        std::fs::remove_dir_all(dir).expect("failed to remove test dir");
+    }
+}
@@ -1,34 +1,158 @@
-use std::path::{PathBuf, Path};
-use std::fs::OpenOptions;
-use std::io::{Read, Write, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::io::{BufReader, Cursor};
+use std::io::{Read, Write};
 use std::io::Error as IoError;
 use std::fmt;
 use std::default::Default;
+#[cfg(feature = "hardware-key")]
+use std::sync::Arc;
 
 use serde::{Serialize, de::DeserializeOwned};
 use chacha20poly1305::{
-    aead::{Aead, AeadCore, KeyInit, OsRng},
-    XChaCha20Poly1305, XNonce
+    aead::{AeadCore, AeadInPlace, KeyInit, Nonce, OsRng, Tag, generic_array::typenum::Unsigned},
+    ChaCha20Poly1305, XChaCha20Poly1305
 };
+use aes_gcm_siv::Aes256GcmSiv;
 pub use chacha20poly1305::Key;
 
-const NONCE_LEN: usize = 24;
+use crate::ErrorKind;
+use super::error::FileError;
+use super::atomic::{self, SyncMode};
+use super::chunked::{self, ChunkReader, ChunkWriter};
+#[cfg(feature = "hardware-key")]
+use super::device::{DeviceError, KeyDevice};
+use super::envelope::{self, Identity, Recipient};
+use super::error::Operation;
+use super::format::{Format, FileWrapped};
+use super::header::{self, Header};
+use super::nonce::{self, NonceCheck};
+use super::permissions::Permissions;
+use super::rotation::{DataKeyCache, DataKeyInfo, RotationPolicy};
+use super::x25519::{PublicKey, SecretKey};
+
+/// the default size of the data in each segment of a chunked file
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// the largest segment size accepted when loading a chunked file
+pub const MAX_CHUNK_SIZE: u32 = 64 * 1024 * 1024;
+
+/// the number of pbkdf2 rounds used by key_from_passphrase
+pub const PASSPHRASE_ROUNDS: u32 = 600_000;
+
+/// derives a key from the passphrase and salt with pbkdf2-hmac-sha256
+///
+/// the salt should be at least 16 random bytes that are unique to the file
+/// and stored next to it since the same salt is needed to load the file.
+pub fn key_from_passphrase<P, S>(passphrase: P, salt: S) -> Key
+where
+    P: AsRef<[u8]>,
+    S: AsRef<[u8]>
+{
+    let mut rtn = Key::default();
+
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_ref(), salt.as_ref(), PASSPHRASE_ROUNDS, &mut rtn);
+
+    rtn
+}
+
+/// derives a file key from the master key and label with hkdf-sha256
+///
+/// files saved with different labels use different keys so many files can
+/// share one master key
+pub fn key_from_master<M, L>(master: M, label: L) -> Key
+where
+    M: Into<Key>,
+    L: AsRef<[u8]>
+{
+    let mut rtn = Key::default();
+
+    hkdf::Hkdf::<sha2::Sha256>::new(None, master.into().as_slice())
+        .expand(label.as_ref(), &mut rtn)
+        .expect("key length is valid for hkdf-sha256");
+
+    rtn
+}
+
+/// how the key of a format was derived
+///
+/// the params are stored in the header of the file so the same key can be
+/// derived again when loading
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Kdf {
+    /// the key was given directly
+    #[default]
+    None,
+    /// the key was derived with key_from_passphrase
+    Pbkdf2Sha256 {
+        rounds: u32,
+        salt: Vec<u8>,
+    },
+    /// the key was derived with key_from_master
+    HkdfSha256 {
+        label: Vec<u8>,
+    },
+}
+
+impl Kdf {
+    /// returns the params used by key_from_passphrase with the salt
+    ///
+    /// panics if the salt is longer than 255 bytes
+    fn passphrase(salt: &[u8]) -> Self {
+        assert!(salt.len() <= u8::MAX as usize, "salt must be at most 255 bytes");
+
+        Kdf::Pbkdf2Sha256 {
+            rounds: PASSPHRASE_ROUNDS,
+            salt: salt.to_vec(),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum Error {
     Io(IoError),
+    File(FileError),
     Bincode(bincode::Error),
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// the cipher failed to encrypt or could not be created from the key
     Crypto,
+    /// the file data failed to decrypt because the key is wrong or the file
+    /// was changed
+    WrongKeyOrTampered,
+    /// the file ended before the header, nonce or wrapped keys were read
+    TruncatedHeader,
+    /// the file was written with a newer header version
+    UnsupportedVersion(u8),
+    /// the header contains unknown ids, flags or sizes
     InvalidEncoding,
+    /// a nonce was generated again for the same key
+    NonceReuse,
+    /// rewrap was called with a format that does not use envelope
+    /// encryption
+    EnvelopeRequired,
+    /// a device failed to wrap or unwrap a data key
+    #[cfg(feature = "hardware-key")]
+    Device(DeviceError),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::File(e) => fmt::Display::fmt(e, f),
             Error::Bincode(e) => fmt::Display::fmt(e, f),
+            #[cfg(feature = "json")]
+            Error::Json(e) => fmt::Display::fmt(e, f),
             Error::Crypto => f.write_str("Crypto"),
+            Error::WrongKeyOrTampered => f.write_str("WrongKeyOrTampered"),
+            Error::TruncatedHeader => f.write_str("TruncatedHeader"),
+            Error::UnsupportedVersion(version) => write!(f, "UnsupportedVersion({})", version),
             Error::InvalidEncoding => f.write_str("InvalidEncoding"),
+            Error::NonceReuse => f.write_str("NonceReuse"),
+            Error::EnvelopeRequired => f.write_str("EnvelopeRequired"),
+            #[cfg(feature = "hardware-key")]
+            Error::Device(e) => fmt::Display::fmt(e, f),
         }
     }
 }
@@ -37,423 +161,1854 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::Io(e) => Some(e),
+            Error::File(e) => Some(e),
             Error::Bincode(e) => Some(e),
+            #[cfg(feature = "json")]
+            Error::Json(e) => Some(e),
+            #[cfg(feature = "hardware-key")]
+            Error::Device(e) => Some(&**e),
             _ => None
         }
     }
 }
 
-fn encode_data(nonce: XNonce, data: Vec<u8>) -> Vec<u8> {
-    let mut rtn: Vec<u8> = Vec::with_capacity(NONCE_LEN + data.len());
-    rtn.extend(nonce);
-    rtn.extend(data);
+impl Error {
+    /// returns the general category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) => ErrorKind::Io,
+            Error::File(_) => ErrorKind::File,
+            Error::Bincode(_) => ErrorKind::Format,
+            #[cfg(feature = "json")]
+            Error::Json(_) => ErrorKind::Format,
+            Error::Crypto => ErrorKind::Crypto,
+            Error::WrongKeyOrTampered => ErrorKind::Crypto,
+            Error::TruncatedHeader => ErrorKind::InvalidHeader,
+            Error::UnsupportedVersion(_) => ErrorKind::UnsupportedVersion,
+            Error::InvalidEncoding => ErrorKind::InvalidEncoding,
+            Error::NonceReuse => ErrorKind::Crypto,
+            Error::EnvelopeRequired => ErrorKind::InvalidEncoding,
+            #[cfg(feature = "hardware-key")]
+            Error::Device(_) => ErrorKind::Crypto,
+        }
+    }
+}
 
-    rtn
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
 }
 
-fn decode_data(data: Vec<u8>) -> Result<(XNonce, Vec<u8>), Error> {
-    if data.len() < 24 {
-        return Err(Error::InvalidEncoding);
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::File(err)
     }
+}
 
-    let mut nonce = [0; NONCE_LEN];
-    let mut encrypted = Vec::with_capacity(data.len() - NONCE_LEN);
-    let mut iter = data.into_iter();
+/// the aead cipher used to encrypt the file data
+///
+/// the cipher is recorded in the header of the file so files load with any
+/// cipher set. files saved without a header use the cipher of the format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Cipher {
+    /// xchacha20-poly1305 with a random 24 byte nonce
+    #[default]
+    XChaCha20Poly1305,
+    /// chacha20-poly1305 with a random 12 byte nonce
+    ChaCha20Poly1305,
+    /// aes-256-gcm-siv with a random 12 byte nonce
+    ///
+    /// nonce misuse resistant and faster on cpus with aes instructions
+    Aes256GcmSiv,
+}
 
-    for i in 0..24 {
-        if let Some(b) = iter.next() {
-            nonce[i] = b;
-        } else {
-            return Err(Error::InvalidEncoding);
+impl Cipher {
+    /// returns the id stored in the header
+    pub(crate) fn id(&self) -> u8 {
+        match self {
+            Cipher::XChaCha20Poly1305 => 0,
+            Cipher::ChaCha20Poly1305 => 1,
+            Cipher::Aes256GcmSiv => 2,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Cipher::XChaCha20Poly1305),
+            1 => Some(Cipher::ChaCha20Poly1305),
+            2 => Some(Cipher::Aes256GcmSiv),
+            _ => None,
         }
     }
 
-    while let Some(b) = iter.next() {
-        encrypted.push(b);
+    /// returns the length of the nonce stored before the encrypted data of a
+    /// file that is encrypted at once
+    pub(crate) fn nonce_len(&self) -> usize {
+        match self {
+            Cipher::XChaCha20Poly1305 => nonce_len::<XChaCha20Poly1305>(),
+            Cipher::ChaCha20Poly1305 => nonce_len::<ChaCha20Poly1305>(),
+            Cipher::Aes256GcmSiv => nonce_len::<Aes256GcmSiv>(),
+        }
     }
+}
 
-    Ok((nonce.into(), encrypted))
+fn nonce_len<C>() -> usize
+where
+    C: AeadCore
+{
+    <C::NonceSize as Unsigned>::USIZE
 }
 
-fn encrypt_data(key: &Key, data: Vec<u8>) -> Result<Vec<u8>, Error> {
-    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
-    let cipher = XChaCha20Poly1305::new(&key);
+/// encrypts the data in the buffer after the space reserved for the nonce
+///
+/// the buffer must start with nonce_len bytes that are replaced by a random
+/// nonce. the data is encrypted in place and the tag is appended so the
+/// buffer is not copied
+fn seal<C>(key: &Key, buffer: &mut Vec<u8>, aad: &[u8], nonces: Option<&NonceCheck>) -> Result<(), Error>
+where
+    C: AeadInPlace + KeyInit
+{
+    let nonce = C::generate_nonce(&mut OsRng);
 
-    let encrypted = cipher.encrypt(&nonce, data.as_slice())
-        .map_err(|_| Error::Crypto)?;
+    nonce::record(nonces, key, &nonce)?;
 
-    Ok(encode_data(nonce, encrypted))
-}
+    let cipher = C::new_from_slice(key.as_slice())
+        .map_err(|_| Error::Crypto)?;
 
-fn decrypt_data(key: &Key, data: Vec<u8>) -> Result<Vec<u8>, Error> {
-    let (nonce, encrypted) = decode_data(data)?;
+    let (prefix, data) = buffer.split_at_mut(nonce.len());
+    prefix.copy_from_slice(&nonce);
 
-    let cipher = XChaCha20Poly1305::new(&key);
-    let decrypted = cipher.decrypt(&nonce, encrypted.as_slice())
+    let tag = cipher.encrypt_in_place_detached(&nonce, aad, data)
         .map_err(|_| Error::Crypto)?;
 
-    Ok(decrypted)
-}
+    buffer.extend_from_slice(&tag);
 
-pub struct Encrypted<T> {
-    inner: T,
-    path: Box<Path>,
-    key: Key,
+    Ok(())
 }
 
-impl<T> Encrypted<T> {
-    /// creates a new Encrypted with the provided data
-    ///
-    /// no checks are made on the path to ensure that the file exists
-    pub fn new<P, K>(inner: T, path: P, key: K) -> Self
-    where
-        P: Into<PathBuf>,
-        K: Into<Key>
-    {
-        Encrypted {
-            inner,
-            path: path.into().into(),
-            key: key.into(),
-        }
+/// splits the nonce and tag from the data and decrypts it in place
+///
+/// returns the part of the data that was decrypted
+fn open<'a, C>(key: &Key, data: &'a mut [u8], aad: &[u8]) -> Result<&'a [u8], Error>
+where
+    C: AeadInPlace + KeyInit
+{
+    let nonce_len = nonce_len::<C>();
+    let tag_len = <C::TagSize as Unsigned>::USIZE;
+
+    if data.len() < nonce_len {
+        return Err(Error::TruncatedHeader);
     }
 
-    #[inline]
-    fn touch_file(path: &Path) -> Result<(), Error> {
-        OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&path)
-            .map_err(|e| Error::Io(e))?;
+    if data.len() < nonce_len + tag_len {
+        return Err(Error::WrongKeyOrTampered);
+    }
 
-        Ok(())
+    let (nonce, rest) = data.split_at_mut(nonce_len);
+    let (encrypted, tag) = rest.split_at_mut(rest.len() - tag_len);
+    let cipher = C::new_from_slice(key.as_slice())
+        .map_err(|_| Error::Crypto)?;
+
+    cipher.decrypt_in_place_detached(
+        Nonce::<C>::from_slice(nonce),
+        aad,
+        encrypted,
+        Tag::<C>::from_slice(tag)
+    ).map_err(|_| Error::WrongKeyOrTampered)?;
+
+    Ok(encrypted)
+}
+
+fn map_bincode(e: bincode::Error) -> Error {
+    match *e {
+        bincode::ErrorKind::Io(io) => Error::Io(io),
+        _ => Error::Bincode(e),
     }
+}
 
-    /// creates a new Encrypted with the provided data and makes the file
-    ///
-    /// will attempt to create a new file and throw an error if a file already
-    /// exists
-    pub fn create<P, K>(inner: T, path: P, key: K) -> Result<Self, Error>
-    where
-        P: Into<PathBuf>,
-        K: Into<Key>
-    {
-        let path: Box<Path> = path.into().into();
-        let key = key.into();
+#[cfg(feature = "json")]
+fn map_json(e: serde_json::Error) -> Error {
+    match e.classify() {
+        serde_json::error::Category::Io => Error::Io(e.into()),
+        _ => Error::Json(e),
+    }
+}
 
-        Self::touch_file(&path)?;
+/// how values are encoded before they are encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Payload {
+    /// bincode with fixed size little endian integers
+    #[default]
+    Bincode,
+    /// json so decrypted files can be read by other tools
+    #[cfg(feature = "json")]
+    Json,
+}
 
-        Ok(Encrypted {
-            inner,
-            path,
-            key
-        })
+impl Payload {
+    /// returns the id stored in the header
+    pub(crate) fn id(&self) -> u8 {
+        match self {
+            Payload::Bincode => 0,
+            #[cfg(feature = "json")]
+            Payload::Json => 1,
+        }
     }
 
-    /// returns the current path for the wrapper
-    pub fn path(&self) -> &Path {
-        &self.path
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Payload::Bincode),
+            #[cfg(feature = "json")]
+            1 => Some(Payload::Json),
+            _ => None,
+        }
     }
 
-    /// updates the current path to the provided value
-    pub fn set_path<P>(&mut self, given: P)
+    fn encode<W, T>(&self, writer: W, value: &T) -> Result<(), Error>
     where
-        P: Into<PathBuf>
+        W: Write,
+        T: Serialize + ?Sized
     {
-        self.path = given.into().into()
-    }
-
-    /// returns the current key for encrypting the file data
-    pub fn key(&self) -> &Key {
-        &self.key
+        match self {
+            Payload::Bincode => bincode::serialize_into(writer, value)
+                .map_err(map_bincode),
+            #[cfg(feature = "json")]
+            Payload::Json => serde_json::to_writer(writer, value)
+                .map_err(map_json),
+        }
     }
 
-    /// updates the current key for encrypting the file data
-    pub fn set_key<K>(&mut self, key: K)
+    fn decode<R, T>(&self, reader: R) -> Result<T, Error>
     where
-        K: Into<Key>
+        R: Read,
+        T: DeserializeOwned
     {
-        self.key = key.into();
+        match self {
+            Payload::Bincode => bincode::deserialize_from(reader)
+                .map_err(map_bincode),
+            #[cfg(feature = "json")]
+            Payload::Json => serde_json::from_reader(reader)
+                .map_err(map_json),
+        }
     }
+}
+
+/// encodes the value compressing it with zstd at the level if one is given
+#[cfg(feature = "zstd")]
+fn encode_value<W, T>(writer: W, payload: Payload, compression: Option<i32>, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: Serialize + ?Sized
+{
+    let Some(level) = compression else {
+        return payload.encode(writer, value);
+    };
+
+    let mut encoder = zstd::Encoder::new(writer, level)?;
+
+    payload.encode(&mut encoder, value)?;
 
-    /// returns the inner value
-    pub fn inner(&self) -> &T {
-        &self.inner
+    encoder.finish()?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn encode_value<W, T>(writer: W, payload: Payload, _compression: Option<i32>, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: Serialize + ?Sized
+{
+    payload.encode(writer, value)
+}
+
+/// decodes the value decompressing it first if it was compressed
+#[cfg(feature = "zstd")]
+fn decode_value<T>(reader: &mut dyn Read, payload: Payload, compressed: bool) -> Result<T, Error>
+where
+    T: DeserializeOwned
+{
+    if compressed {
+        payload.decode(zstd::Decoder::new(reader)?)
+    } else {
+        payload.decode(reader)
     }
+}
+
+/// the header is rejected if it is compressed without the zstd feature
+#[cfg(not(feature = "zstd"))]
+fn decode_value<T>(reader: &mut dyn Read, payload: Payload, _compressed: bool) -> Result<T, Error>
+where
+    T: DeserializeOwned
+{
+    payload.decode(reader)
+}
 
-    /// returns a mutable inner value
-    pub fn inner_mut(&mut self) -> &mut T {
-        &mut self.inner
+/// encrypts the buffer in place with seal
+///
+/// the buffer must start with Cipher::nonce_len bytes for the nonce
+fn encrypt_data(
+    cipher: Cipher,
+    key: &Key,
+    buffer: &mut Vec<u8>,
+    aad: &[u8],
+    nonces: Option<&NonceCheck>,
+) -> Result<(), Error> {
+    match cipher {
+        Cipher::XChaCha20Poly1305 => seal::<XChaCha20Poly1305>(key, buffer, aad, nonces),
+        Cipher::ChaCha20Poly1305 => seal::<ChaCha20Poly1305>(key, buffer, aad, nonces),
+        Cipher::Aes256GcmSiv => seal::<Aes256GcmSiv>(key, buffer, aad, nonces),
     }
+}
 
-    /// consumes the struct returning the inner value
-    pub fn into_inner(self) -> T {
-        self.inner
+/// decrypts the data in place with open
+fn decrypt_data<'a>(cipher: Cipher, key: &Key, data: &'a mut [u8], aad: &[u8]) -> Result<&'a [u8], Error> {
+    match cipher {
+        Cipher::XChaCha20Poly1305 => open::<XChaCha20Poly1305>(key, data, aad),
+        Cipher::ChaCha20Poly1305 => open::<ChaCha20Poly1305>(key, data, aad),
+        Cipher::Aes256GcmSiv => open::<Aes256GcmSiv>(key, data, aad),
     }
 }
 
-impl<T> Encrypted<T>
+/// opens a file for reading through a buffer
+fn open_file(path: &Path) -> Result<BufReader<std::fs::File>, Error> {
+    let file = std::fs::File::open(path)
+        .map_err(FileError::map(Operation::Open, path))?;
+
+    Ok(BufReader::new(file))
+}
+
+/// checks that the file decrypts with the key without deserializing the
+/// value
+///
+/// use EncryptedFormat::verify for files saved with other options
+pub fn verify<P, K>(path: P, key: K) -> Result<(), Error>
 where
-    T: Serialize
+    P: AsRef<Path>,
+    K: Into<Key>
 {
-    /// saves the inner value to the provided file path
-    ///
-    /// data will be encrypted using the key stored and the file will be
-    /// truncated when written to
-    pub fn save(&self) -> Result<(), Error> {
-        let serialize = bincode::serialize(&self.inner)
-            .map_err(|e| match *e {
-                bincode::ErrorKind::Io(io) => Error::Io(io),
-                _ => Error::Bincode(e)
-            })?;
-
-        let encrypted = encrypt_data(&self.key, serialize)?;
+    EncryptedFormat::new(key).verify(path)
+}
 
-        let file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.path)
-            .map_err(|e| Error::Io(e))?;
-        let mut writer = BufWriter::new(file);
+/// reads the header of the file without decrypting it
+///
+/// files saved without a header have a version of 0 and only the length is
+/// known. use EncryptedFormat::inspect to fill in the layout from a format
+pub fn inspect<P>(path: P) -> Result<FileInfo, Error>
+where
+    P: AsRef<Path>
+{
+    inspect_file(path.as_ref(), None)
+}
 
-        writer.write_all(encrypted.as_slice())
-            .map_err(|e| Error::Io(e))?;
+/// reads the header and layout of the file using the format for the layout
+/// of files saved without a header
+fn inspect_file(path: &Path, legacy: Option<&EncryptedFormat>) -> Result<FileInfo, Error> {
+    let mut reader = open_file(path)?;
+    let len = reader.get_ref()
+        .metadata()
+        .map_err(FileError::map(Operation::Read, path))?
+        .len();
+    let (header, prefix) = Header::read(&mut reader)?;
+    let mut reader = Cursor::new(prefix).chain(reader);
+
+    let mut rtn = FileInfo {
+        len,
+        version: header::LEGACY_VERSION,
+        cipher: None,
+        payload: None,
+        kdf: None,
+        envelope: None,
+        chunk_size: None,
+        compressed: false,
+        data_key: None,
+    };
+
+    let (cipher, chunked, envelope) = match (header, legacy) {
+        (Some(header), _) => {
+            rtn.version = header.version;
+            rtn.cipher = Some(header.cipher);
+            rtn.payload = Some(header.payload);
+            rtn.kdf = Some(header.kdf);
+            rtn.compressed = header.compressed;
+            rtn.data_key = header.data_key;
+
+            (header.cipher, header.chunked, header.envelope)
+        }
+        (None, Some(format)) => (format.cipher, format.chunk_size.is_some(), format.envelope.is_some()),
+        (None, None) => return Ok(rtn),
+    };
+
+    if envelope {
+        let count = envelope::count_stanzas(&mut reader)?;
+
+        rtn.envelope = Some(EnvelopeInfo {
+            keys: count.keys,
+            public_keys: count.public_keys,
+            devices: count.devices,
+        });
+    }
 
-        Ok(())
+    if chunked {
+        rtn.chunk_size = Some(chunked::read_chunk_size(&mut reader, cipher, MAX_CHUNK_SIZE)?);
     }
 
-    /// saves the inner value to the provided file path using tokio fs
-    ///
-    /// similar operation as the blocking save
-    #[cfg(feature = "tokio")]
-    pub async fn save_async(&self) -> Result<(), Error> {
-        use tokio::io::AsyncWriteExt;
+    Ok(rtn)
+}
 
-        let serialize = bincode::serialize(&self.inner)
-            .map_err(|e| match *e {
-                bincode::ErrorKind::Io(io) => Error::Io(io),
-                _ => Error::Bincode(e)
-            })?;
+/// the layout of an encrypted file returned by inspect
+///
+/// the cipher, payload and kdf are only known for files with a header
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileInfo {
+    /// the length of the file in bytes
+    pub len: u64,
+    /// the version of the header or 0 if the file does not have one
+    pub version: u8,
+    /// the cipher used to encrypt the file data
+    pub cipher: Option<Cipher>,
+    /// how the value was encoded before it was encrypted
+    pub payload: Option<Payload>,
+    /// how the key of the format that saved the file was derived
+    pub kdf: Option<Kdf>,
+    /// the stanzas of the envelope header if the file uses envelope
+    /// encryption
+    pub envelope: Option<EnvelopeInfo>,
+    /// the segment size stored in a chunked file
+    pub chunk_size: Option<u32>,
+    /// if the value was compressed with zstd before it was encrypted
+    pub compressed: bool,
+    /// how long the data key has been used if the file was saved with a
+    /// rotation policy
+    pub data_key: Option<DataKeyInfo>,
+}
 
-        let encrypted = encrypt_data(&self.key, serialize)?;
+/// the stanzas in the header of a file using envelope encryption
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EnvelopeInfo {
+    /// the number of data keys wrapped by symmetric master keys
+    pub keys: usize,
+    /// the number of data keys wrapped for x25519 public keys
+    pub public_keys: usize,
+    /// the number of data keys wrapped by keys stored in devices
+    pub devices: usize,
+}
 
-        let file = tokio::fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.path)
-            .await
-            .map_err(|e| Error::Io(e))?;
-        let mut writer = tokio::io::BufWriter::new(file);
+/// encodes values with bincode and encrypts them with the stored key
+///
+/// by default the entire file is encrypted at once so it is kept in memory
+/// while saving and loading. a chunked format splits the data into segments
+/// that are encrypted separately with the STREAM construction so only one
+/// segment is kept in memory by the blocking save and load. the tokio save
+/// and load of FileWrapped still keep the entire file in memory since a
+/// Format only encodes to blocking writers. use the blocking save and load
+/// in spawn_blocking to keep the memory bounded. chunked files without a
+/// header are not compatible with files encrypted at once.
+///
+/// files start with a header recording the cipher, payload, layout and how
+/// the key was derived so files load with any of those options set. files
+/// saved before the header was added are loaded with the options of the
+/// format.
+///
+/// with envelope encryption the payload is encrypted with a random data key
+/// that is stored in a header wrapped by the key and every recipient key.
+/// the file can be loaded with any of them and the master keys can be
+/// changed with FileWrapped::rewrap without encrypting the payload again.
+/// the data key can also be wrapped for x25519 public keys so files are
+/// saved by anyone with the public key and only loaded with the secret key.
+/// with the hardware-key feature the data key can be wrapped by a key that
+/// stays in a device such as a tpm or pkcs#11 token.
+///
+/// with the zstd feature the encoded value can be compressed before it is
+/// encrypted since encrypted data does not compress.
+#[derive(Clone)]
+pub struct EncryptedFormat {
+    key: Key,
+    cipher: Cipher,
+    payload: Payload,
+    chunk_size: Option<u32>,
+    envelope: Option<Envelope>,
+    kdf: Kdf,
+    master_key: Option<Key>,
+    compression: Option<i32>,
+    nonce_check: Option<NonceCheck>,
+    rotation: Option<RotationPolicy>,
+    data_keys: DataKeyCache,
+}
 
-        writer.write_all(encrypted.as_slice())
-            .await
-            .map_err(|e| Error::Io(e))?;
-        writer.flush()
-            .await
-            .map_err(|e| Error::Io(e))?;
+/// the master keys used by envelope encryption
+#[derive(Clone)]
+struct Envelope {
+    /// if the data key is wrapped by the key of the format
+    wrap_key: bool,
+    recipients: Vec<Key>,
+    public_keys: Vec<PublicKey>,
+    identity: Option<SecretKey>,
+    #[cfg(feature = "hardware-key")]
+    devices: Vec<Arc<dyn KeyDevice>>,
+}
 
-        Ok(())
+impl Envelope {
+    fn new(wrap_key: bool) -> Self {
+        Envelope {
+            wrap_key,
+            recipients: Vec::new(),
+            public_keys: Vec::new(),
+            identity: None,
+            #[cfg(feature = "hardware-key")]
+            devices: Vec::new(),
+        }
     }
-}
 
-impl<T> Encrypted<T>
-where
-    T: DeserializeOwned
-{
-    fn read_to_buffer(path: &Path) -> Result<Vec<u8>, Error> {
-        let file = OpenOptions::new()
-            .read(true)
-            .open(&path)
-            .map_err(|e| Error::Io(e))?;
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::new();
+    /// returns the number of stanzas written to the header
+    fn count(&self) -> usize {
+        self.wrap_key as usize + self.recipients.len() + self.public_keys.len() + self.devices_len()
+    }
 
-        reader.read_to_end(&mut buffer)
-            .map_err(|e| Error::Io(e))?;
+    #[cfg(feature = "hardware-key")]
+    fn devices_len(&self) -> usize {
+        self.devices.len()
+    }
 
-        Ok(buffer)
+    #[cfg(not(feature = "hardware-key"))]
+    fn devices_len(&self) -> usize {
+        0
     }
+}
 
-    fn decrypt_deserialize(key: &Key, buffer: Vec<u8>) -> Result<T, Error> {
-        let decrypted = decrypt_data(&key, buffer)?;
+impl EncryptedFormat {
+    /// creates a new format with the given key
+    pub fn new<K>(key: K) -> Self
+    where
+        K: Into<Key>
+    {
+        EncryptedFormat {
+            key: key.into(),
+            cipher: Cipher::default(),
+            payload: Payload::default(),
+            chunk_size: None,
+            envelope: None,
+            kdf: Kdf::None,
+            master_key: None,
+            compression: None,
+            nonce_check: None,
+            rotation: None,
+            data_keys: DataKeyCache::default(),
+        }
+    }
 
-        bincode::deserialize(decrypted.as_slice())
-            .map_err(|e| match *e {
-                bincode::ErrorKind::Io(io) => Error::Io(io),
-                _ => Error::Bincode(e),
-            })
+    /// creates a new format with a key derived from the passphrase and salt
+    ///
+    /// the salt is stored in the header of the file and returned by inspect
+    /// so it can be found again when loading. panics if the salt is longer
+    /// than 255 bytes
+    pub fn from_passphrase<P, S>(passphrase: P, salt: S) -> Self
+    where
+        P: AsRef<[u8]>,
+        S: AsRef<[u8]>
+    {
+        let mut rtn = EncryptedFormat::new(key_from_passphrase(passphrase, salt.as_ref()));
+        rtn.kdf = Kdf::passphrase(salt.as_ref());
+        rtn
     }
 
-    /// loads the specified file using the master key provided
+    /// creates a new format with a key derived from the master key and label
     ///
-    /// assumes that the file already exists and is propperly encoded with the
-    /// encrypted data
-    pub fn load<P, K>(given: P, master_key: K) -> Result<Self, Error>
+    /// the label is stored in the header of the file and the master key is
+    /// kept by the format so files saved with any label under the same
+    /// master key load with it. panics if the label is longer than 255
+    /// bytes
+    pub fn from_master_key<M, L>(master: M, label: L) -> Self
     where
-        P: Into<PathBuf>,
-        K: Into<Key>,
+        M: Into<Key>,
+        L: AsRef<[u8]>
     {
-        let path: Box<Path> = given.into().into();
-        let key = master_key.into();
+        let master = master.into();
+        let label = label.as_ref();
 
-        let buffer = Self::read_to_buffer(&path)?;
-        let inner = Self::decrypt_deserialize(&key, buffer)?;
+        assert!(label.len() <= u8::MAX as usize, "label must be at most 255 bytes");
 
-        Ok(Encrypted {
-            inner,
-            path,
-            key
-        })
+        let mut rtn = EncryptedFormat::new(key_from_master(master, label));
+        rtn.kdf = Kdf::HkdfSha256 {
+            label: label.to_vec(),
+        };
+        rtn.master_key = Some(master);
+        rtn
     }
 
-    /// loads or creates the specified file using the master key provided
+    /// sets the cipher used to encrypt the file data
+    pub fn with_cipher(mut self, cipher: Cipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// sets how values are encoded before they are encrypted
     ///
-    /// if the file already exits it will follow the same operation as load
-    /// execept if the buffer is empty then it will return the default
-    /// otherwise it will attempt to create an empty file.
-    pub fn load_create<P, K>(path: P, master_key: K) -> Result<Self, Error>
+    /// the payload is recorded in the header of the file so files load with
+    /// any payload set unless they were saved without a header
+    pub fn with_payload(mut self, payload: Payload) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// encrypts the file data in segments of the given size
+    ///
+    /// the chunk size is stored in the file so files load with any chunk
+    /// size set. only the blocking save and load keep a single segment in
+    /// memory. panics if the chunk size is 0 or larger than MAX_CHUNK_SIZE
+    pub fn with_chunk_size(mut self, chunk_size: u32) -> Self {
+        assert!(
+            chunk_size > 0 && chunk_size <= MAX_CHUNK_SIZE,
+            "chunk size must be between 1 and MAX_CHUNK_SIZE"
+        );
+
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// compresses the encoded value with zstd at the level before it is
+    /// encrypted
+    ///
+    /// the header records if the value was compressed so files load with
+    /// any compression set. a level of 0 uses the zstd default
+    #[cfg(feature = "zstd")]
+    pub fn with_compression(mut self, level: i32) -> Self {
+        self.compression = Some(level);
+        self
+    }
+
+    /// creates a format that encrypts files for the public keys with
+    /// envelope encryption
+    ///
+    /// the data key is not wrapped by the key of the format which is random
+    /// so the files can only be loaded with the secret keys. panics if there
+    /// are more than 255 public keys
+    pub fn for_public_keys<I>(public_keys: I) -> Self
     where
-        T: Default,
-        P: Into<PathBuf>,
-        K: Into<Key>,
+        I: IntoIterator<Item = PublicKey>
     {
-        let path: Box<Path> = path.into().into();
-        let key = master_key.into();
-        let check = path.try_exists()
-            .map_err(|e| Error::Io(e))?;
-
-        if check {
-            let buffer = Self::read_to_buffer(&path)?;
-
-            if buffer.len() == 0 {
-                return Ok(Encrypted {
-                    inner: Default::default(),
-                    path,
-                    key
-                });
-            }
+        let mut rtn = EncryptedFormat::new(envelope::data_key());
+        rtn.envelope = Some(Envelope::new(false));
 
-            let inner = Self::decrypt_deserialize(&key, buffer)?;
+        for public_key in public_keys {
+            rtn = rtn.with_public_key(public_key);
+        }
 
-            Ok(Encrypted {
-                inner,
-                path,
-                key
-            })
-        } else {
-            Self::touch_file(&path)?;
+        rtn
+    }
 
-            Ok(Encrypted {
-                inner: Default::default(),
-                path,
-                key
-            })
-        }
+    /// creates a format that loads files encrypted for the public key of
+    /// the secret key with envelope encryption
+    ///
+    /// files saved by the format are encrypted for the same public key
+    pub fn from_identity(identity: SecretKey) -> Self {
+        EncryptedFormat::for_public_keys([identity.public_key()])
+            .with_identity(identity)
     }
 
-    /// loads the specified file using the master key provided using tokio fs
+    /// encrypts the payload with a random data key wrapped by the key
     ///
-    /// similar to the blocking load
-    #[cfg(feature = "tokio")]
-    pub async fn load_async<P, K>(given: P, master_key: K) -> Result<Self, Error>
+    /// the header records if envelope encryption was used so only files
+    /// saved without a header need the same setting when loading
+    pub fn with_envelope(mut self) -> Self {
+        self.envelope.get_or_insert_with(|| Envelope::new(true));
+        self
+    }
+
+    /// adds another master key that the data key is wrapped by and enables
+    /// envelope encryption
+    ///
+    /// panics if the total number of master keys is more than 255
+    pub fn with_recipient<K>(mut self, key: K) -> Self
     where
-        P: Into<PathBuf>,
-        K: Into<Key>,
+        K: Into<Key>
     {
-        use tokio::io::AsyncReadExt;
+        let envelope = self.envelope.get_or_insert_with(|| Envelope::new(true));
 
-        let path = given.into().into();
-        let key = master_key.into();
+        assert!(envelope.count() < envelope::MAX_KEYS, "too many recipient keys");
 
-        let file = tokio::fs::OpenOptions::new()
-            .read(true)
-            .open(&path)
-            .await
-            .map_err(|e| Error::Io(e))?;
-        let mut reader = tokio::io::BufReader::new(file);
-        let mut buffer = Vec::new();
+        envelope.recipients.push(key.into());
+        self
+    }
 
-        reader.read_to_end(&mut buffer)
-            .await
-            .map_err(|e| Error::Io(e))?;
+    /// adds a public key that the data key is wrapped for and enables
+    /// envelope encryption
+    ///
+    /// panics if the total number of master keys is more than 255
+    pub fn with_public_key(mut self, public_key: PublicKey) -> Self {
+        let envelope = self.envelope.get_or_insert_with(|| Envelope::new(true));
 
-        let decrypted = decrypt_data(&key, buffer)?;
+        assert!(envelope.count() < envelope::MAX_KEYS, "too many recipient keys");
 
-        let inner = bincode::deserialize(decrypted.as_slice())
-            .map_err(|e| match *e {
-                bincode::ErrorKind::Io(io) => Error::Io(io),
-                _ => Error::Bincode(e),
-            })?;
+        envelope.public_keys.push(public_key);
+        self
+    }
 
-        Ok(Encrypted {
-            inner,
-            path,
-            key
-        })
+    /// sets the secret key used to unwrap the data key of files encrypted
+    /// for its public key and enables envelope encryption
+    ///
+    /// the public key is not added to the format so it must also be given
+    /// to with_public_key if saved files should be loadable by the identity
+    pub fn with_identity(mut self, identity: SecretKey) -> Self {
+        self.envelope.get_or_insert_with(|| Envelope::new(true))
+            .identity = Some(identity);
+        self
     }
-}
 
-impl<T> std::fmt::Debug for Encrypted<T>
-where
-    T: std::fmt::Debug
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Encrypted")
-            .field("inner", &self.inner)
-            .field("path", &self.path)
-            .finish_non_exhaustive()
+    /// creates a format that wraps the data key of files with the key stored
+    /// in the device using envelope encryption
+    ///
+    /// the key of the format is random and does not wrap the data key so the
+    /// files can only be loaded with the device
+    #[cfg(feature = "hardware-key")]
+    pub fn from_device(device: Arc<dyn KeyDevice>) -> Self {
+        let mut rtn = EncryptedFormat::new(envelope::data_key());
+        rtn.envelope = Some(Envelope::new(false));
+        rtn.with_device(device)
     }
-}
 
-impl<T> std::convert::AsRef<T> for Encrypted<T> {
-    fn as_ref(&self) -> &T {
-        &self.inner
+    /// adds a device that the data key is wrapped by and enables envelope
+    /// encryption
+    ///
+    /// files are loaded with the device if the key id of a stanza matches.
+    /// panics if the total number of master keys is more than 255
+    #[cfg(feature = "hardware-key")]
+    pub fn with_device(mut self, device: Arc<dyn KeyDevice>) -> Self {
+        let envelope = self.envelope.get_or_insert_with(|| Envelope::new(true));
+
+        assert!(envelope.count() < envelope::MAX_KEYS, "too many recipient keys");
+
+        envelope.devices.push(device);
+        self
     }
-}
 
-impl<T> std::convert::AsMut<T> for Encrypted<T> {
-    fn as_mut(&mut self) -> &mut T {
-        &mut self.inner
+    /// reuses the data key of saves until the policy rotates it and enables
+    /// envelope encryption
+    ///
+    /// the saves and age of the data key are stored in the header. the data
+    /// key is kept by the format after a save or load so a format should
+    /// only be used for one file. clones of the format do not keep it
+    pub fn with_rotation_policy(mut self, policy: RotationPolicy) -> Self {
+        self.envelope.get_or_insert_with(|| Envelope::new(true));
+        self.rotation = Some(policy);
+        self
     }
-}
 
-impl<T> Clone for Encrypted<T>
-where
-    T: Clone
-{
-    fn clone(&self) -> Self {
-        Encrypted {
-            inner: self.inner.clone(),
-            path: self.path.clone(),
-            key: self.key.clone()
-        }
+    /// records the nonces used by the last capacity encryptions with each
+    /// key and fails a save with NonceReuse if one is generated again
+    ///
+    /// nonces are random so a repeat means the random number generator or
+    /// the save loop is broken. the check is shared by clones of the format
+    pub fn with_nonce_check(mut self, capacity: usize) -> Self {
+        self.nonce_check = Some(NonceCheck::new(capacity));
+        self
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::wrapper;
+    /// returns the current key for encrypting the file data
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
 
-    #[test]
-    fn base() {
-        let file_name = "test.encrypted";
-        let inner = usize::MAX;
-        let key = [0; 32];
+    /// returns the other master keys if envelope encryption is enabled
+    pub fn recipients(&self) -> Option<&[Key]> {
+        self.envelope.as_ref()
+            .map(|envelope| envelope.recipients.as_slice())
+    }
 
-        wrapper::test::create_test_file(file_name);
+    /// returns the public keys if envelope encryption is enabled
+    pub fn public_keys(&self) -> Option<&[PublicKey]> {
+        self.envelope.as_ref()
+            .map(|envelope| envelope.public_keys.as_slice())
+    }
 
-        let wrapper = Encrypted::new(inner, file_name, key);
+    /// writes the data key wrapped by every master key, for every public key
+    /// and by every device
+    fn write_envelope<W>(&self, writer: &mut W, envelope: &Envelope, data_key: &Key) -> Result<(), Error>
+    where
+        W: Write
+    {
+        let mut recipients = Vec::with_capacity(envelope.count());
 
-        wrapper.save().expect("failed to save to encrypted file");
+        if envelope.wrap_key {
+            recipients.push(Recipient::Key(&self.key));
+        }
 
-        let and_back: Encrypted<usize> = Encrypted::load(
-            PathBuf::from(file_name),
+        recipients.extend(envelope.recipients.iter().map(Recipient::Key));
+        recipients.extend(envelope.public_keys.iter().map(Recipient::X25519));
+
+        #[cfg(feature = "hardware-key")]
+        recipients.extend(envelope.devices.iter().map(|device| Recipient::Device(&**device)));
+
+        envelope::write_header(writer, data_key, &recipients, self.nonce_check.as_ref())
+    }
+
+    /// returns the key for a file saved with the kdf
+    ///
+    /// files with a label are read with a key derived from the master key if
+    /// the format has one
+    fn file_key(&self, kdf: &Kdf) -> Key {
+        match (kdf, &self.master_key) {
+            (Kdf::HkdfSha256 { label }, Some(master)) => key_from_master(*master, label),
+            _ => self.key,
+        }
+    }
+
+    /// reads the data key unwrapped by the key, the identity or a device
+    fn read_envelope<R>(&self, reader: &mut R, envelope: &Envelope, key: &Key) -> Result<Key, Error>
+    where
+        R: Read
+    {
+        let mut identities = Vec::new();
+
+        if envelope.wrap_key {
+            identities.push(Identity::Key(key));
+        }
+
+        if let Some(identity) = &envelope.identity {
+            identities.push(Identity::X25519(identity));
+        }
+
+        #[cfg(feature = "hardware-key")]
+        identities.extend(envelope.devices.iter().map(|device| Identity::Device(&**device)));
+
+        envelope::read_header(reader, &identities)
+    }
+
+    /// returns how values are encoded before they are encrypted
+    pub fn payload(&self) -> Payload {
+        self.payload
+    }
+
+    /// returns the chunk size if the file data is encrypted in segments
+    pub fn chunk_size(&self) -> Option<u32> {
+        self.chunk_size
+    }
+
+    /// returns the cipher used to encrypt the file data
+    pub fn cipher(&self) -> Cipher {
+        self.cipher
+    }
+
+    /// returns how the key was derived
+    pub fn kdf(&self) -> &Kdf {
+        &self.kdf
+    }
+
+    /// returns the policy for rotating the data key
+    pub fn rotation_policy(&self) -> Option<&RotationPolicy> {
+        self.rotation.as_ref()
+    }
+
+    /// returns the zstd level if the value is compressed before it is
+    /// encrypted
+    pub fn compression(&self) -> Option<i32> {
+        self.compression
+    }
+
+    /// returns the header written to files saved by the format
+    fn header(&self) -> Header {
+        Header {
+            version: header::VERSION,
+            cipher: self.cipher,
+            payload: self.payload,
+            chunked: self.chunk_size.is_some(),
+            envelope: self.envelope.is_some(),
+            compressed: self.compression.is_some(),
+            kdf: self.kdf.clone(),
+            data_key: None,
+        }
+    }
+
+    /// decrypts the file data from the reader and passes it to the callback
+    ///
+    /// the file data is always read to the end so every tag is verified
+    /// even if the callback does not read all of it
+    fn decrypt_reader<R, F, O>(&self, mut reader: R, callback: F) -> Result<O, Error>
+    where
+        R: Read,
+        F: FnOnce(&mut dyn Read, &Header) -> Result<O, Error>
+    {
+        let (header, prefix) = Header::read(&mut reader)?;
+        let mut reader = Cursor::new(prefix).chain(reader);
+
+        // files without a header are read with the options of the format and
+        // do not have any associated data. they were never compressed
+        let (header, aad) = match header {
+            Some(header) => {
+                let aad = header.aad().to_vec();
+
+                (header, aad)
+            }
+            None => {
+                let header = Header {
+                    compressed: false,
+                    ..self.header()
+                };
+
+                (header, Vec::new())
+            }
+        };
+
+        let file_key = self.file_key(&header.kdf);
+        let data_key;
+        let key = if header.envelope {
+            let default = Envelope::new(true);
+
+            data_key = self.read_envelope(
+                &mut reader,
+                self.envelope.as_ref().unwrap_or(&default),
+                &file_key
+            )?;
+
+            // later saves reuse the data key until the policy expires it
+            if let (Some(_), Some(info)) = (&self.rotation, header.data_key) {
+                self.data_keys.loaded(data_key, info);
+            }
+
+            &data_key
+        } else {
+            &file_key
+        };
+
+        if header.chunked {
+            let mut reader = ChunkReader::new(reader, header.cipher, key, &aad, MAX_CHUNK_SIZE)?;
+
+            // read to the end so the last segment is always verified
+            let result = callback(&mut reader, &header)
+                .and_then(|value| {
+                    std::io::copy(&mut reader, &mut std::io::sink())?;
+
+                    Ok(value)
+                });
+
+            return match result {
+                Err(_) if reader.failed() => Err(Error::WrongKeyOrTampered),
+                result => result,
+            };
+        }
+
+        let mut buffer = Vec::new();
+
+        reader.read_to_end(&mut buffer)?;
+
+        let mut decrypted = decrypt_data(header.cipher, key, &mut buffer, &aad)?;
+
+        callback(&mut decrypted, &header)
+    }
+
+    /// checks that the file decrypts with the format without deserializing
+    /// the value
+    ///
+    /// every tag in the file is verified so a file that passes will load if
+    /// the value has the expected type
+    pub fn verify<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>
+    {
+        let reader = open_file(path.as_ref())?;
+
+        self.decrypt_reader(reader, |_, _| Ok(()))
+    }
+
+    /// reads the header and layout of the file without decrypting it
+    ///
+    /// the layout of files saved without a header is read with the options
+    /// of the format
+    pub fn inspect<P>(&self, path: P) -> Result<FileInfo, Error>
+    where
+        P: AsRef<Path>
+    {
+        inspect_file(path.as_ref(), Some(self))
+    }
+}
+
+impl Format for EncryptedFormat {
+    type Error = Error;
+
+    fn to_writer<W, T>(&self, mut writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized
+    {
+        let mut header = self.header();
+        let data_key = self.envelope.as_ref().map(|envelope| {
+            let data_key = match &self.rotation {
+                Some(policy) => {
+                    let (data_key, info) = self.data_keys.next(policy);
+                    header.data_key = Some(info);
+
+                    data_key
+                }
+                None => envelope::data_key(),
+            };
+
+            (envelope, data_key)
+        });
+        let aad = header.aad();
+
+        header.write(&mut writer)?;
+
+        let key = if let Some((envelope, data_key)) = &data_key {
+            self.write_envelope(&mut writer, envelope, data_key)?;
+
+            data_key
+        } else {
+            &self.key
+        };
+
+        if let Some(chunk_size) = self.chunk_size {
+            let mut writer = ChunkWriter::new(
+                writer,
+                self.cipher,
+                key,
+                &aad,
+                chunk_size,
+                self.nonce_check.as_ref()
+            )?;
+
+            encode_value(&mut writer, self.payload, self.compression, value)?;
+
+            writer.finish()?;
+
+            return Ok(());
+        }
+
+        // the value is encoded after the space for the nonce so it can be
+        // encrypted in place
+        let mut buffer = vec![0; self.cipher.nonce_len()];
+
+        encode_value(&mut buffer, self.payload, self.compression, value)?;
+
+        encrypt_data(self.cipher, key, &mut buffer, &aad, self.nonce_check.as_ref())?;
+
+        writer.write_all(&buffer)?;
+
+        Ok(())
+    }
+
+    fn from_reader<R, T>(&self, reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        self.decrypt_reader(reader, |decrypted, header| {
+            decode_value(decrypted, header.payload, header.compressed)
+        })
+    }
+
+    /// encrypted files are only readable by the owner unless the wrapper
+    /// sets other permissions. the permissions are enforced so saving over
+    /// a file with looser permissions restricts them
+    fn permissions(&self) -> Permissions {
+        Permissions::owner_only().with_enforce(true)
+    }
+
+    /// encrypted files flush the contents and metadata before a save returns
+    fn sync_mode(&self) -> SyncMode {
+        SyncMode::All
+    }
+}
+
+impl fmt::Debug for EncryptedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedFormat")
+            .field("cipher", &self.cipher)
+            .field("payload", &self.payload)
+            .field("chunk_size", &self.chunk_size)
+            .field("compression", &self.compression)
+            .field("envelope", &self.envelope.is_some())
+            .field("nonce_check", &self.nonce_check.is_some())
+            .field("rotation", &self.rotation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// a value stored in an encrypted file
+pub type Encrypted<T> = FileWrapped<T, EncryptedFormat>;
+
+impl<T> FileWrapped<T, EncryptedFormat> {
+    /// creates a new Encrypted with the provided data
+    ///
+    /// no checks are made on the path to ensure that the file exists
+    pub fn new<P, K>(inner: T, path: P, key: K) -> Self
+    where
+        P: Into<PathBuf>,
+        K: Into<Key>
+    {
+        Self::with_format(inner, path, EncryptedFormat::new(key))
+    }
+
+    /// creates a new Encrypted with the provided data and makes the file
+    ///
+    /// will attempt to create a new file and throw an error if a file already
+    /// exists
+    pub fn create<P, K>(inner: T, path: P, key: K) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        K: Into<Key>
+    {
+        Self::create_with(inner, path, EncryptedFormat::new(key))
+    }
+
+    /// returns the current key for encrypting the file data
+    pub fn key(&self) -> &Key {
+        self.format().key()
+    }
+
+    /// updates the current key for encrypting the file data
+    ///
+    /// the key is recorded as not derived from a passphrase or master key
+    pub fn set_key<K>(&mut self, key: K)
+    where
+        K: Into<Key>
+    {
+        let format = self.format_mut();
+        format.key = key.into();
+        format.kdf = Kdf::None;
+        format.master_key = None;
+    }
+
+    /// wraps the data key of the file with the master keys of the new
+    /// format and then replaces the current format
+    ///
+    /// only the header is changed so the payload is not decrypted. both
+    /// formats must use envelope encryption and the same cipher, payload and
+    /// chunking. the kdf in the header is replaced by the one of the new
+    /// format. uses the same temp file and rename as save
+    ///
+    /// returns EnvelopeRequired if either format does not use envelope
+    /// encryption and InvalidEncoding if the file does not
+    pub fn rewrap(&mut self, format: EncryptedFormat) -> Result<(), Error> {
+        let (Some(current), Some(next)) = (&self.format().envelope, &format.envelope) else {
+            return Err(Error::EnvelopeRequired);
+        };
+
+        let path = self.path().to_path_buf();
+        let mut file = open_file(&path)?;
+        let (header, prefix) = Header::read(&mut file)?;
+
+        if header.as_ref().is_some_and(|header| !header.envelope) {
+            return Err(Error::InvalidEncoding);
+        }
+
+        let file_key = match &header {
+            Some(header) => self.format().file_key(&header.kdf),
+            None => self.format().key,
+        };
+
+        // files without a header keep the legacy layout since the payload
+        // was not encrypted with a header as associated data
+        let header = header.map(|header| Header {
+            kdf: format.kdf.clone(),
+            ..header
+        });
+        let mut reader = Cursor::new(prefix).chain(file);
+        let data_key = self.format().read_envelope(&mut reader, current, &file_key)?;
+
+        atomic::save(
+            &path,
+            &format.permissions(),
+            format.sync_mode(),
+            |writer| {
+                // moved so the file is closed before the rename
+                let mut reader = reader;
+
+                if let Some(header) = &header {
+                    header.write(writer)?;
+                }
+
+                format.write_envelope(writer, next, &data_key)?;
+
+                std::io::copy(&mut reader, writer)
+                    .map_err(FileError::map(Operation::Write, &path))?;
+
+                Ok::<(), Error>(())
+            }
+        )?;
+
+        *self.format_mut() = format;
+
+        Ok(())
+    }
+}
+
+impl<T> FileWrapped<T, EncryptedFormat>
+where
+    T: Serialize
+{
+    /// saves the inner value encrypted with the new key and then updates the
+    /// current key
+    ///
+    /// uses the same temp file and rename as save so the file is either
+    /// encrypted with the old key or the new one. the current key is left
+    /// unchanged if the save fails
+    pub fn rekey<K>(&mut self, new_key: K) -> Result<(), Error>
+    where
+        K: Into<Key>
+    {
+        self.rekey_kdf(new_key.into(), Kdf::None)
+    }
+
+    /// swaps the key and kdf and saves the file restoring the previous ones
+    /// if the save fails
+    fn rekey_kdf(&mut self, key: Key, kdf: Kdf) -> Result<(), Error> {
+        let format = self.format_mut();
+        let previous_key = std::mem::replace(&mut format.key, key);
+        let previous_kdf = std::mem::replace(&mut format.kdf, kdf);
+        let previous_master = format.master_key.take();
+
+        if let Err(err) = self.save() {
+            let format = self.format_mut();
+            format.key = previous_key;
+            format.kdf = previous_kdf;
+            format.master_key = previous_master;
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// same as rekey with a key derived from the passphrase and salt
+    ///
+    /// see key_from_passphrase for details on the salt. panics if the salt
+    /// is longer than 255 bytes
+    pub fn rekey_with_passphrase<P, S>(&mut self, passphrase: P, salt: S) -> Result<(), Error>
+    where
+        P: AsRef<[u8]>,
+        S: AsRef<[u8]>
+    {
+        let kdf = Kdf::passphrase(salt.as_ref());
+
+        self.rekey_kdf(key_from_passphrase(passphrase, salt), kdf)
+    }
+}
+
+impl<T> FileWrapped<T, EncryptedFormat>
+where
+    T: DeserializeOwned
+{
+    /// loads the specified file using the master key provided
+    ///
+    /// assumes that the file already exists and is propperly encoded with the
+    /// encrypted data
+    pub fn load<P, K>(given: P, master_key: K) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        K: Into<Key>,
+    {
+        Self::load_with(given, EncryptedFormat::new(master_key))
+    }
+
+    /// loads the specified file while holding a shared lock on the path
+    pub fn load_locked<P, K>(given: P, master_key: K) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        K: Into<Key>,
+    {
+        Self::load_locked_with(given, EncryptedFormat::new(master_key))
+    }
+
+    /// loads or creates the specified file using the master key provided
+    ///
+    /// if the file already exits it will follow the same operation as load
+    /// execept if the buffer is empty then it will return the default
+    /// otherwise it will attempt to create an empty file.
+    pub fn load_create<P, K>(path: P, master_key: K) -> Result<Self, Error>
+    where
+        T: Default,
+        P: Into<PathBuf>,
+        K: Into<Key>,
+    {
+        Self::load_or_create_with(path, EncryptedFormat::new(master_key))
+    }
+
+    /// loads the specified file using the master key provided using tokio fs
+    ///
+    /// similar to the blocking load except the entire file is read into
+    /// memory, even for chunked files
+    #[cfg(feature = "tokio")]
+    pub async fn load_async<P, K>(given: P, master_key: K) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+        K: Into<Key>,
+    {
+        Self::load_with_async(given, EncryptedFormat::new(master_key)).await
+    }
+
+    /// loads or creates the specified file using the master key provided
+    /// using tokio fs
+    ///
+    /// similar to the blocking load_create
+    #[cfg(feature = "tokio")]
+    pub async fn load_create_async<P, K>(path: P, master_key: K) -> Result<Self, Error>
+    where
+        T: Default,
+        P: Into<PathBuf>,
+        K: Into<Key>,
+    {
+        Self::load_or_create_with_async(path, EncryptedFormat::new(master_key)).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wrapper;
+
+    #[test]
+    fn base() {
+        let file_name = "test.encrypted";
+        let inner = usize::MAX;
+        let key = [0; 32];
+
+        wrapper::test::create_test_file(file_name);
+
+        let wrapper = Encrypted::new(inner, file_name, key);
+
+        wrapper.save().expect("failed to save to encrypted file");
+
+        let and_back: Encrypted<usize> = Encrypted::load(
+            PathBuf::from(file_name),
             key
         ).expect("failed to load encrypted file");
 
         assert_eq!(wrapper.inner(), and_back.inner());
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let file_name = "test.owner_only.encrypted";
+        let _ = std::fs::remove_file(file_name);
+
+        let wrapper = Encrypted::new(1u64, file_name, [0; 32]);
+        wrapper.save().expect("failed to save to encrypted file");
+
+        let metadata = std::fs::metadata(file_name).expect("failed to read metadata");
+
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        std::fs::set_permissions(file_name, std::fs::Permissions::from_mode(0o644))
+            .expect("failed to set permissions");
+
+        wrapper.save().expect("failed to save to encrypted file");
+
+        let metadata = std::fs::metadata(file_name).expect("failed to read metadata");
+
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn cipher() {
+        let file_name = "test.cipher.encrypted";
+
+        for cipher in [Cipher::ChaCha20Poly1305, Cipher::Aes256GcmSiv] {
+            let format = EncryptedFormat::new([1; 32]).with_cipher(cipher);
+
+            Encrypted::with_format(vec![1u64, 2], file_name, format.clone()).save()
+                .expect("failed to save encrypted file");
+
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, format)
+                .expect("failed to load encrypted file");
+
+            assert_eq!(and_back.inner(), &vec![1, 2]);
+
+            // the cipher is read from the header
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load(file_name, [1; 32])
+                .expect("failed to load encrypted file");
+
+            assert_eq!(and_back.inner(), &vec![1, 2]);
+        }
+    }
+
+    #[test]
+    fn chunked() {
+        let file_name = "test.chunked.encrypted";
+        let data: Vec<u64> = (0..100).collect();
+
+        for cipher in [Cipher::XChaCha20Poly1305, Cipher::ChaCha20Poly1305, Cipher::Aes256GcmSiv] {
+            let format = EncryptedFormat::new([1; 32])
+                .with_cipher(cipher)
+                .with_chunk_size(64);
+
+            Encrypted::with_format(data.clone(), file_name, format.clone()).save()
+                .expect("failed to save chunked file");
+
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, format.clone())
+                .expect("failed to load chunked file");
+
+            assert_eq!(and_back.inner(), &data);
+
+            let bytes = std::fs::read(file_name).expect("failed to read chunked file");
+
+            // drop the last segment, 808 encoded bytes leaves 40 bytes and
+            // the tag in the last segment
+            std::fs::write(file_name, &bytes[..bytes.len() - 56]).expect("failed to write chunked file");
+
+            match Encrypted::<Vec<u64>>::load_with(file_name, format.clone()) {
+                Err(Error::WrongKeyOrTampered) => {}
+                result => panic!("unexpected load result: {:?}", result),
+            }
+
+            let mut tampered = bytes.clone();
+            tampered[100] ^= 0xff;
+            std::fs::write(file_name, &tampered).expect("failed to write chunked file");
+
+            match Encrypted::<Vec<u64>>::load_with(file_name, format) {
+                Err(Error::WrongKeyOrTampered) => {}
+                result => panic!("unexpected load result: {:?}", result),
+            }
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compression() {
+        let file_name = "test.compression.encrypted";
+        let data = vec![7u64; 1000];
+        let plain = EncryptedFormat::new([1; 32]);
+
+        Encrypted::with_format(data.clone(), file_name, plain.clone()).save()
+            .expect("failed to save encrypted file");
+
+        let plain_len = std::fs::metadata(file_name).expect("failed to read metadata").len();
+
+        for chunk_size in [None, Some(64)] {
+            let mut format = EncryptedFormat::new([1; 32]).with_compression(3);
+
+            if let Some(chunk_size) = chunk_size {
+                format = format.with_chunk_size(chunk_size);
+            }
+
+            Encrypted::with_format(data.clone(), file_name, format).save()
+                .expect("failed to save compressed file");
+
+            let info = inspect(file_name).expect("failed to inspect compressed file");
+
+            assert!(info.compressed);
+            assert!(info.len < plain_len / 10);
+
+            // the compression is read from the header
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, plain.clone())
+                .expect("failed to load compressed file");
+
+            assert_eq!(and_back.inner(), &data);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let file_name = "test.json.encrypted";
+        let key = [1; 32];
+        let format = EncryptedFormat::new(key).with_payload(Payload::Json);
+
+        Encrypted::with_format(vec![1u64, 2], file_name, format.clone()).save()
+            .expect("failed to save encrypted file");
+
+        let bytes = std::fs::read(file_name).expect("failed to read encrypted file");
+        // the header is the fixed fields followed by the kdf id
+        let (header, data) = bytes.split_at(header::AAD_LEN + 1);
+        let decrypted = decrypt_data(Cipher::XChaCha20Poly1305, &key.into(), &mut data.to_vec(), &header[..header::AAD_LEN])
+            .map(<[u8]>::to_vec)
+            .expect("failed to decrypt file");
+
+        assert_eq!(decrypted, b"[1,2]");
+
+        // the payload is read from the header
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load(file_name, key)
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &vec![1, 2]);
+    }
+
+    #[test]
+    fn envelope() {
+        let file_name = "test.envelope.encrypted";
+        let data: Vec<u64> = (0..100).collect();
+        let format = EncryptedFormat::new([1; 32]).with_recipient([2; 32]);
+
+        let mut wrapper = Encrypted::with_format(data.clone(), file_name, format);
+        wrapper.save().expect("failed to save encrypted file");
+
+        for key in [[1; 32], [2; 32]] {
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, EncryptedFormat::new(key).with_envelope())
+                .expect("failed to load encrypted file");
+
+            assert_eq!(and_back.inner(), &data);
+        }
+
+        wrapper.rewrap(EncryptedFormat::new([3; 32]).with_envelope())
+            .expect("failed to rewrap encrypted file");
+
+        match Encrypted::<Vec<u64>>::load_with(file_name, EncryptedFormat::new([1; 32]).with_envelope()) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, EncryptedFormat::new([3; 32]).with_envelope())
+            .expect("failed to load rewrapped file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        match wrapper.rewrap(EncryptedFormat::new([4; 32])) {
+            Err(Error::EnvelopeRequired) => {}
+            result => panic!("unexpected rewrap result: {:?}", result),
+        }
+
+        let mut plain = Encrypted::new(data.clone(), "test.envelope_plain.encrypted", [1; 32]);
+
+        match plain.rewrap(EncryptedFormat::new([3; 32]).with_envelope()) {
+            Err(Error::EnvelopeRequired) => {}
+            result => panic!("unexpected rewrap result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn header() {
+        let file_name = "test.header.encrypted";
+        let data: Vec<u64> = (0..100).collect();
+        let format = EncryptedFormat::new([1; 32])
+            .with_cipher(Cipher::ChaCha20Poly1305)
+            .with_chunk_size(64);
+
+        let wrapper = Encrypted::with_format(data.clone(), file_name, format);
+        wrapper.save().expect("failed to save encrypted file");
+
+        let info = inspect(file_name).expect("failed to inspect encrypted file");
+
+        assert_eq!(info.version, header::VERSION);
+        assert_eq!(info.cipher, Some(Cipher::ChaCha20Poly1305));
+        assert_eq!(info.payload, Some(Payload::Bincode));
+        assert_eq!(info.kdf, Some(Kdf::None));
+        assert_eq!(info.chunk_size, Some(64));
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load(file_name, [1; 32])
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        // the fixed fields are authenticated with the file data
+        let mut contents = std::fs::read(file_name).unwrap();
+        contents[header::MAGIC.len() + 1] = Cipher::XChaCha20Poly1305.id();
+        std::fs::write(file_name, contents).unwrap();
+
+        assert!(Encrypted::<Vec<u64>>::load(file_name, [1; 32]).is_err());
+
+        // files saved before the header was added
+        let mut legacy = vec![0; Cipher::XChaCha20Poly1305.nonce_len()];
+        bincode::serialize_into(&mut legacy, &data).unwrap();
+        seal::<XChaCha20Poly1305>(&[1; 32].into(), &mut legacy, &[], None).unwrap();
+        std::fs::write(file_name, legacy).unwrap();
+
+        let info = inspect(file_name).expect("failed to inspect encrypted file");
+
+        assert_eq!(info.version, header::LEGACY_VERSION);
+        assert_eq!(info.cipher, None);
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load(file_name, [1; 32])
+            .expect("failed to load legacy file");
+
+        assert_eq!(and_back.inner(), &data);
+    }
+
+    #[test]
+    fn errors() {
+        let file_name = "test.errors.encrypted";
+
+        Encrypted::new(vec![1u64, 2], file_name, [1; 32]).save()
+            .expect("failed to save encrypted file");
+
+        match Encrypted::<Vec<u64>>::load(file_name, [2; 32]) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        let contents = std::fs::read(file_name).unwrap();
+
+        let mut version = contents.clone();
+        version[header::MAGIC.len()] = header::VERSION + 1;
+        std::fs::write(file_name, version).unwrap();
+
+        match Encrypted::<Vec<u64>>::load(file_name, [1; 32]) {
+            Err(Error::UnsupportedVersion(v)) if v == header::VERSION + 1 => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        for len in [header::MAGIC.len() + 2, header::AAD_LEN + 4] {
+            std::fs::write(file_name, &contents[..len]).unwrap();
+
+            match Encrypted::<Vec<u64>>::load(file_name, [1; 32]) {
+                Err(Error::TruncatedHeader) => {}
+                result => panic!("unexpected load result: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_inspect() {
+        let file_name = "test.verify_inspect.encrypted";
+        let data: Vec<u64> = (0..100).collect();
+        let format = EncryptedFormat::new([1; 32])
+            .with_chunk_size(64)
+            .with_recipient([2; 32])
+            .with_public_key(SecretKey::generate().public_key());
+
+        let wrapper = Encrypted::with_format(data, file_name, format.clone());
+        wrapper.save().expect("failed to save encrypted file");
+
+        format.verify(file_name).expect("failed to verify encrypted file");
+
+        let info = format.inspect(file_name).expect("failed to inspect encrypted file");
+
+        assert_eq!(info.len, std::fs::metadata(file_name).unwrap().len());
+        assert_eq!(info.envelope, Some(EnvelopeInfo { keys: 2, public_keys: 1, devices: 0 }));
+        assert_eq!(info.chunk_size, Some(64));
+
+        verify(file_name, [2; 32]).expect("failed to verify encrypted file");
+
+        match verify(file_name, [3; 32]) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected verify result: {:?}", result),
+        }
+
+        let mut contents = std::fs::read(file_name).unwrap();
+        let last = contents.len() - 1;
+        contents[last] ^= 1;
+        std::fs::write(file_name, contents).unwrap();
+
+        match format.verify(file_name) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected verify result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn public_key() {
+        let file_name = "test.public_key.encrypted";
+        let data: Vec<u64> = (0..100).collect();
+        let alice = SecretKey::generate();
+        let bob = SecretKey::generate();
+        let format = EncryptedFormat::for_public_keys([alice.public_key(), bob.public_key()])
+            .with_recipient([1; 32]);
+
+        let wrapper = Encrypted::with_format(data.clone(), file_name, format);
+        wrapper.save().expect("failed to save encrypted file");
+
+        for identity in [alice, bob] {
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, EncryptedFormat::from_identity(identity))
+                .expect("failed to load encrypted file");
+
+            assert_eq!(and_back.inner(), &data);
+        }
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, EncryptedFormat::new([1; 32]).with_envelope())
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        match Encrypted::<Vec<u64>>::load_with(file_name, EncryptedFormat::from_identity(SecretKey::generate())) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "hardware-key")]
+    struct TestDevice {
+        id: &'static [u8],
+        key: Key,
+        fail: bool,
+    }
+
+    #[cfg(feature = "hardware-key")]
+    impl KeyDevice for TestDevice {
+        fn key_id(&self) -> &[u8] {
+            self.id
+        }
+
+        fn wrap(&self, data_key: &[u8]) -> Result<Vec<u8>, DeviceError> {
+            let mut wrapped = vec![0; Cipher::XChaCha20Poly1305.nonce_len()];
+            wrapped.extend_from_slice(data_key);
+
+            seal::<XChaCha20Poly1305>(&self.key, &mut wrapped, self.id, None)?;
+
+            Ok(wrapped)
+        }
+
+        fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, DeviceError> {
+            if self.fail {
+                return Err("device removed".into());
+            }
+
+            Ok(open::<XChaCha20Poly1305>(&self.key, &mut wrapped.to_vec(), self.id)?.to_vec())
+        }
+    }
+
+    #[cfg(feature = "hardware-key")]
+    #[test]
+    fn device() {
+        let file_name = "test.device.encrypted";
+        let data: Vec<u64> = (0..100).collect();
+        let device = |id, fail| -> Arc<dyn KeyDevice> {
+            Arc::new(TestDevice { id, key: Key::from([3; 32]), fail })
+        };
+        let format = EncryptedFormat::from_device(device(b"slot 1", false))
+            .with_recipient([1; 32]);
+
+        Encrypted::with_format(data.clone(), file_name, format).save()
+            .expect("failed to save encrypted file");
+
+        let info = inspect(file_name).expect("failed to inspect encrypted file");
+
+        assert_eq!(info.envelope, Some(EnvelopeInfo { keys: 1, public_keys: 0, devices: 1 }));
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, EncryptedFormat::from_device(device(b"slot 1", false)))
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        // the stanza of another key id is not passed to the device
+        match Encrypted::<Vec<u64>>::load_with(file_name, EncryptedFormat::from_device(device(b"slot 2", true))) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        match Encrypted::<Vec<u64>>::load_with(file_name, EncryptedFormat::from_device(device(b"slot 1", true))) {
+            Err(err @ Error::Device(_)) => assert_eq!(err.kind(), ErrorKind::Crypto),
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let file_name = "test.rotation.encrypted";
+        let rotated = Arc::new(AtomicU32::new(0));
+        let policy = {
+            let rotated = rotated.clone();
+
+            RotationPolicy::new()
+                .with_max_saves(2)
+                .on_rotate(move |info| {
+                    assert_eq!(info.saves, 2);
+
+                    rotated.fetch_add(1, Ordering::SeqCst);
+                })
+        };
+        let format = EncryptedFormat::new([1; 32]).with_rotation_policy(policy.clone());
+        let wrapper = Encrypted::with_format(vec![1u64, 2], file_name, format);
+        let saves = || {
+            inspect(file_name).expect("failed to inspect encrypted file")
+                .data_key
+                .expect("missing data key info")
+                .saves
+        };
+
+        wrapper.save().expect("failed to save encrypted file");
+        assert_eq!(saves(), 1);
+
+        wrapper.save().expect("failed to save encrypted file");
+        assert_eq!(saves(), 2);
+        assert_eq!(rotated.load(Ordering::SeqCst), 0);
+
+        wrapper.save().expect("failed to save encrypted file");
+        assert_eq!(saves(), 1);
+        assert_eq!(rotated.load(Ordering::SeqCst), 1);
+
+        // the count continues from the header of a loaded file
+        let wrapper: Encrypted<Vec<u64>> = Encrypted::load_with(
+            file_name,
+            EncryptedFormat::new([1; 32]).with_rotation_policy(policy)
+        ).expect("failed to load encrypted file");
+
+        wrapper.save().expect("failed to save encrypted file");
+        assert_eq!(saves(), 2);
+
+        wrapper.save().expect("failed to save encrypted file");
+        assert_eq!(saves(), 1);
+        assert_eq!(rotated.load(Ordering::SeqCst), 2);
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load(file_name, [1; 32])
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &vec![1, 2]);
+    }
+
+    #[test]
+    fn master_key() {
+        let first = "test.master_key.first.encrypted";
+        let second = "test.master_key.second.encrypted";
+
+        assert_ne!(key_from_master([1; 32], "first"), key_from_master([1; 32], "second"));
+
+        Encrypted::with_format(vec![1u64], first, EncryptedFormat::from_master_key([1; 32], "first")).save()
+            .expect("failed to save encrypted file");
+        Encrypted::with_format(vec![2u64], second, EncryptedFormat::from_master_key([1; 32], "second").with_envelope()).save()
+            .expect("failed to save encrypted file");
+
+        let info = inspect(first).expect("failed to inspect encrypted file");
+
+        assert_eq!(info.kdf, Some(Kdf::HkdfSha256 { label: b"first".to_vec() }));
+
+        // the label is read from the header
+        let format = EncryptedFormat::from_master_key([1; 32], "other");
+
+        for (file_name, expected) in [(first, 1), (second, 2)] {
+            let and_back: Encrypted<Vec<u64>> = Encrypted::load_with(file_name, format.clone())
+                .expect("failed to load encrypted file");
+
+            assert_eq!(and_back.inner(), &vec![expected]);
+        }
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load(first, key_from_master([1; 32], "first"))
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &vec![1]);
+
+        match Encrypted::<Vec<u64>>::load_with(first, EncryptedFormat::from_master_key([2; 32], "first")) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rekey() {
+        let file_name = "test.rekey.encrypted";
+        let mut wrapper = Encrypted::new(vec![1u64, 2], file_name, [1; 32]);
+
+        wrapper.save().expect("failed to save encrypted file");
+        wrapper.rekey([2; 32]).expect("failed to rekey encrypted file");
+
+        assert_eq!(wrapper.key(), &Key::from([2; 32]));
+
+        match Encrypted::<Vec<u64>>::load(file_name, [1; 32]) {
+            Err(Error::WrongKeyOrTampered) => {}
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        wrapper.rekey_with_passphrase("passphrase", "test salt").expect("failed to rekey encrypted file");
+
+        let and_back: Encrypted<Vec<u64>> = Encrypted::load(file_name, *wrapper.key())
+            .expect("failed to load encrypted file");
+
+        assert_eq!(and_back.inner(), &vec![1, 2]);
+
+        wrapper.set_path("test.rekey.missing/file.encrypted");
+        wrapper.rekey([3; 32]).expect_err("rekeyed to a missing directory");
+
+        assert_eq!(wrapper.key(), and_back.key());
+    }
+
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn tokio() {
@@ -474,5 +2029,21 @@ mod test {
             .expect("failed to load tokio encrypted file");
 
         assert_eq!(wrapper.inner(), and_back.inner());
+
+        let and_back: Encrypted<usize> = Encrypted::load_create_async(file_name, key)
+            .await
+            .expect("failed to load tokio encrypted file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+
+        let created_name = "test.tokio_create.encrypted";
+        let _ = std::fs::remove_file(created_name);
+
+        let created: Encrypted<usize> = Encrypted::load_create_async(created_name, key)
+            .await
+            .expect("failed to create tokio encrypted file");
+
+        assert_eq!(created.inner(), &0);
+        assert!(std::path::Path::new(created_name).exists());
     }
 }
@@ -0,0 +1,347 @@
+use std::io::{Read, Write};
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Key, XChaCha20Poly1305, XNonce
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+#[cfg(feature = "hardware-key")]
+use super::device::KeyDevice;
+use super::encrypted::Error;
+use super::nonce::{self, NonceCheck};
+use super::x25519::{self, PublicKey, SecretKey};
+
+/// the stanza kind for a data key wrapped by a symmetric master key
+const STANZA_KEY: u8 = 0;
+
+/// the stanza kind for a data key wrapped for an x25519 public key
+const STANZA_X25519: u8 = 1;
+
+/// the stanza kind for a data key wrapped by a key stored in a device
+const STANZA_DEVICE: u8 = 2;
+
+/// the info used to derive the wrapping key from an x25519 shared secret
+const X25519_INFO: &[u8] = b"file-sys x25519 data key";
+
+const NONCE_LEN: usize = 24;
+
+/// the length of a wrapped data key with its tag
+const WRAPPED_LEN: usize = 32 + 16;
+
+/// the max number of master keys a data key can be wrapped by
+pub(crate) const MAX_KEYS: usize = u8::MAX as usize;
+
+/// creates a random data key
+pub(crate) fn data_key() -> Key {
+    XChaCha20Poly1305::generate_key(&mut OsRng)
+}
+
+/// derives the key that wraps the data key for an x25519 recipient
+///
+/// the ephemeral and recipient public keys are used as the salt so the
+/// wrapping key is bound to both
+fn x25519_wrap_key(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> Key {
+    let mut salt = [0; x25519::KEY_LEN * 2];
+    salt[..x25519::KEY_LEN].copy_from_slice(ephemeral.as_bytes());
+    salt[x25519::KEY_LEN..].copy_from_slice(recipient.as_bytes());
+
+    let mut rtn = Key::default();
+
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(X25519_INFO, &mut rtn)
+        .expect("key length is valid for hkdf-sha256");
+
+    rtn
+}
+
+fn wrap(key: &Key, data_key: &Key, nonces: Option<&NonceCheck>) -> Result<(XNonce, Vec<u8>), Error> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    nonce::record(nonces, key, &nonce)?;
+
+    let wrapped = XChaCha20Poly1305::new(key)
+        .encrypt(&nonce, data_key.as_slice())
+        .map_err(|_| Error::Crypto)?;
+
+    Ok((nonce, wrapped))
+}
+
+fn unwrap(key: &Key, nonce: &[u8], wrapped: &[u8]) -> Option<Key> {
+    XChaCha20Poly1305::new(key)
+        .decrypt(XNonce::from_slice(nonce), wrapped)
+        .ok()
+        .map(|data_key| *Key::from_slice(&data_key))
+}
+
+/// a master key that the data key is wrapped by or for
+pub(crate) enum Recipient<'a> {
+    Key(&'a Key),
+    X25519(&'a PublicKey),
+    #[cfg(feature = "hardware-key")]
+    Device(&'a dyn KeyDevice),
+}
+
+/// a master key that can unwrap the data key
+pub(crate) enum Identity<'a> {
+    Key(&'a Key),
+    X25519(&'a SecretKey),
+    #[cfg(feature = "hardware-key")]
+    Device(&'a dyn KeyDevice),
+}
+
+/// writes the data key wrapped by or for every recipient
+///
+/// the header is the number of stanzas followed by each stanza. a key
+/// stanza is the kind, nonce and wrapped key. an x25519 stanza is the kind,
+/// ephemeral public key, nonce and wrapped key. a device stanza is the
+/// kind, key id length, key id, wrapped key length as a u16 and the
+/// wrapped key
+pub(crate) fn write_header<W>(
+    writer: &mut W,
+    data_key: &Key,
+    recipients: &[Recipient<'_>],
+    nonces: Option<&NonceCheck>,
+) -> Result<(), Error>
+where
+    W: Write
+{
+    writer.write_all(&[recipients.len() as u8])?;
+
+    for recipient in recipients {
+        match recipient {
+            Recipient::Key(key) => {
+                let (nonce, wrapped) = wrap(key, data_key, nonces)?;
+
+                writer.write_all(&[STANZA_KEY])?;
+                writer.write_all(&nonce)?;
+                writer.write_all(&wrapped)?;
+            }
+            Recipient::X25519(public_key) => {
+                let ephemeral = SecretKey::generate();
+                let ephemeral_public = ephemeral.public_key();
+                let shared = ephemeral.diffie_hellman(public_key)
+                    .ok_or(Error::Crypto)?;
+                let (nonce, wrapped) = wrap(
+                    &x25519_wrap_key(&shared, &ephemeral_public, public_key),
+                    data_key,
+                    nonces
+                )?;
+
+                writer.write_all(&[STANZA_X25519])?;
+                writer.write_all(ephemeral_public.as_bytes())?;
+                writer.write_all(&nonce)?;
+                writer.write_all(&wrapped)?;
+            }
+            #[cfg(feature = "hardware-key")]
+            Recipient::Device(device) => {
+                let key_id = device.key_id();
+                let wrapped = device.wrap(data_key.as_slice())
+                    .map_err(Error::Device)?;
+
+                if key_id.len() > u8::MAX as usize || wrapped.len() > u16::MAX as usize {
+                    return Err(Error::InvalidEncoding);
+                }
+
+                writer.write_all(&[STANZA_DEVICE, key_id.len() as u8])?;
+                writer.write_all(key_id)?;
+                writer.write_all(&(wrapped.len() as u16).to_le_bytes())?;
+                writer.write_all(&wrapped)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// a stanza read from the header
+enum Stanza<'a> {
+    Key {
+        nonce: &'a [u8],
+        wrapped: &'a [u8],
+    },
+    X25519 {
+        ephemeral: PublicKey,
+        nonce: &'a [u8],
+        wrapped: &'a [u8],
+    },
+    #[cfg_attr(not(feature = "hardware-key"), allow(dead_code))]
+    Device {
+        key_id: &'a [u8],
+        wrapped: &'a [u8],
+    },
+}
+
+/// reads a length prefixed field of a device stanza
+fn read_field<R>(reader: &mut R, len: usize) -> Result<Vec<u8>, Error>
+where
+    R: Read
+{
+    let mut rtn = vec![0; len];
+
+    reader.read_exact(&mut rtn)
+        .map_err(|_| Error::TruncatedHeader)?;
+
+    Ok(rtn)
+}
+
+/// reads every stanza of the header and passes it to the callback
+///
+/// the reader is left at the start of the payload
+fn read_stanzas<R, F>(reader: &mut R, mut callback: F) -> Result<(), Error>
+where
+    R: Read,
+    F: FnMut(Stanza<'_>) -> Result<(), Error>
+{
+    let mut count = [0];
+
+    reader.read_exact(&mut count)
+        .map_err(|_| Error::TruncatedHeader)?;
+
+    for _ in 0..count[0] {
+        let mut kind = [0];
+
+        reader.read_exact(&mut kind)
+            .map_err(|_| Error::TruncatedHeader)?;
+
+        match kind[0] {
+            STANZA_KEY => {
+                let mut stanza = [0; NONCE_LEN + WRAPPED_LEN];
+
+                reader.read_exact(&mut stanza)
+                    .map_err(|_| Error::TruncatedHeader)?;
+
+                let (nonce, wrapped) = stanza.split_at(NONCE_LEN);
+
+                callback(Stanza::Key { nonce, wrapped })?;
+            }
+            STANZA_X25519 => {
+                let mut stanza = [0; x25519::KEY_LEN + NONCE_LEN + WRAPPED_LEN];
+
+                reader.read_exact(&mut stanza)
+                    .map_err(|_| Error::TruncatedHeader)?;
+
+                let (ephemeral, rest) = stanza.split_at(x25519::KEY_LEN);
+                let (nonce, wrapped) = rest.split_at(NONCE_LEN);
+
+                callback(Stanza::X25519 {
+                    ephemeral: PublicKey::from_bytes(ephemeral.try_into().unwrap()),
+                    nonce,
+                    wrapped,
+                })?;
+            }
+            STANZA_DEVICE => {
+                let key_id_len = read_field(reader, 1)?[0] as usize;
+                let key_id = read_field(reader, key_id_len)?;
+                let wrapped_len = read_field(reader, 2)?;
+                let wrapped = read_field(
+                    reader,
+                    u16::from_le_bytes([wrapped_len[0], wrapped_len[1]]) as usize
+                )?;
+
+                callback(Stanza::Device {
+                    key_id: &key_id,
+                    wrapped: &wrapped,
+                })?;
+            }
+            _ => return Err(Error::InvalidEncoding),
+        }
+    }
+
+    Ok(())
+}
+
+/// tries to unwrap the data key of the stanza with the identity
+fn unwrap_stanza(stanza: &Stanza<'_>, identity: &Identity<'_>) -> Result<Option<Key>, Error> {
+    match (stanza, identity) {
+        (Stanza::Key { nonce, wrapped }, Identity::Key(key)) => {
+            Ok(unwrap(key, nonce, wrapped))
+        }
+        (Stanza::X25519 { ephemeral, nonce, wrapped }, Identity::X25519(secret)) => {
+            let Some(shared) = secret.diffie_hellman(ephemeral) else {
+                return Ok(None);
+            };
+
+            Ok(unwrap(
+                &x25519_wrap_key(&shared, ephemeral, &secret.public_key()),
+                nonce,
+                wrapped
+            ))
+        }
+        #[cfg(feature = "hardware-key")]
+        (Stanza::Device { key_id, wrapped }, Identity::Device(device)) => {
+            if *key_id != device.key_id() {
+                return Ok(None);
+            }
+
+            let data_key = device.unwrap(wrapped)
+                .map_err(Error::Device)?;
+
+            if data_key.len() != 32 {
+                return Err(Error::WrongKeyOrTampered);
+            }
+
+            Ok(Some(*Key::from_slice(&data_key)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// reads every stanza of the header returning the data key unwrapped by the
+/// first identity that can
+///
+/// the reader is left at the start of the payload
+pub(crate) fn read_header<R>(reader: &mut R, identities: &[Identity<'_>]) -> Result<Key, Error>
+where
+    R: Read
+{
+    let mut found = None;
+
+    read_stanzas(reader, |stanza| {
+        for identity in identities {
+            if found.is_some() {
+                break;
+            }
+
+            found = unwrap_stanza(&stanza, identity)?;
+        }
+
+        Ok(())
+    })?;
+
+    found.ok_or(Error::WrongKeyOrTampered)
+}
+
+/// the number of each kind of stanza in the header
+pub(crate) struct StanzaCount {
+    pub(crate) keys: usize,
+    pub(crate) public_keys: usize,
+    pub(crate) devices: usize,
+}
+
+/// reads every stanza of the header returning the number of each kind
+///
+/// the reader is left at the start of the payload
+pub(crate) fn count_stanzas<R>(reader: &mut R) -> Result<StanzaCount, Error>
+where
+    R: Read
+{
+    let mut rtn = StanzaCount {
+        keys: 0,
+        public_keys: 0,
+        devices: 0,
+    };
+
+    read_stanzas(reader, |stanza| {
+        match stanza {
+            Stanza::Key { .. } => rtn.keys += 1,
+            Stanza::X25519 { .. } => rtn.public_keys += 1,
+            Stanza::Device { .. } => rtn.devices += 1,
+        }
+
+        Ok(())
+    })?;
+
+    Ok(rtn)
+}
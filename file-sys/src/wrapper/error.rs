@@ -0,0 +1,118 @@
+use std::fmt;
+use std::path::{PathBuf, Path};
+use std::io::Error as IoError;
+
+/// the file system operation that failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Open,
+    Read,
+    Create,
+    Write,
+    Sync,
+    Rename,
+    Lock,
+}
+
+impl Operation {
+    /// returns the name of the operation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Open => "open",
+            Operation::Read => "read",
+            Operation::Create => "create",
+            Operation::Write => "write",
+            Operation::Sync => "sync",
+            Operation::Rename => "rename",
+            Operation::Lock => "lock",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// an io error along with the path and operation that caused it
+pub struct FileError {
+    op: Operation,
+    path: Box<Path>,
+    source: IoError,
+}
+
+impl FileError {
+    /// creates a new FileError
+    pub fn new<P>(op: Operation, path: P, source: IoError) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        FileError {
+            op,
+            path: path.into().into(),
+            source,
+        }
+    }
+
+    /// returns a closure that creates a FileError from an io error
+    #[cfg(feature = "serde")]
+    pub(crate) fn map(op: Operation, path: &Path) -> impl FnOnce(IoError) -> FileError + '_ {
+        move |err| FileError::new(op, path, err)
+    }
+
+    /// returns the operation that failed
+    pub fn op(&self) -> &Operation {
+        &self.op
+    }
+
+    /// returns the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// returns the io error
+    pub fn io(&self) -> &IoError {
+        &self.source
+    }
+
+    /// consumes the error returning the io error
+    pub fn into_io(self) -> IoError {
+        self.source
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to {} \"{}\": {}",
+            self.op,
+            self.path.display(),
+            self.source
+        )
+    }
+}
+
+impl fmt::Debug for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileError")
+            .field("op", &self.op)
+            .field("path", &self.path)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// keeps the kind of the io error with the context in the message
+impl From<FileError> for IoError {
+    fn from(err: FileError) -> Self {
+        IoError::new(err.source.kind(), err)
+    }
+}
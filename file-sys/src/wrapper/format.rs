@@ -0,0 +1,1027 @@
+use std::path::{PathBuf, Path};
+use std::fs::OpenOptions;
+use std::io::{Read, Write, BufReader};
+use std::io::Error as IoError;
+use std::fmt;
+use std::fs::Metadata;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::atomic::{self, SyncMode};
+use super::error::{FileError, Operation};
+use super::limit::check_size;
+use super::lock::FileLock;
+use super::permissions::Permissions;
+
+/// the encoding used by a FileWrapped when saving and loading
+pub trait Format {
+    /// the error returned when encoding or decoding fails
+    ///
+    /// io errors from the writer or reader are converted with From<IoError>
+    /// while errors from opening, creating and renaming files are converted
+    /// with From<FileError> to include the path and operation. every error
+    /// can be converted into the crate Error for code that is generic over
+    /// formats
+    type Error: From<IoError> + From<FileError> + Into<crate::Error>;
+
+    /// encodes the value into the writer
+    fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized;
+
+    /// decodes a value from the reader
+    #[allow(clippy::wrong_self_convention)]
+    fn from_reader<R, T>(&self, reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned;
+
+    /// the permissions of created files when the wrapper does not set any
+    fn permissions(&self) -> Permissions {
+        Permissions::new()
+    }
+
+    /// how saves are flushed to disk when the wrapper does not set a mode
+    fn sync_mode(&self) -> SyncMode {
+        SyncMode::Data
+    }
+
+    /// the largest file in bytes that will be loaded
+    ///
+    /// files larger than this fail to load before any data is read
+    fn max_size(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// creates an empty file with the given permissions failing if it already
+/// exists
+#[inline]
+pub(crate) fn touch_file(path: &Path, permissions: &Permissions) -> Result<Metadata, FileError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    permissions.open_options(&mut options);
+
+    let file = options.open(path)
+        .map_err(FileError::map(Operation::Create, path))?;
+
+    permissions.apply(&file)
+        .map_err(FileError::map(Operation::Create, path))?;
+
+    file.metadata()
+        .map_err(FileError::map(Operation::Create, path))
+}
+
+/// possible errors from FileWrapped::try_modify
+pub enum ModifyError<E, S> {
+    /// the closure failed and nothing was saved
+    Modify(E),
+    /// the closure succeeded but saving failed
+    Save(S),
+}
+
+impl<E, S> fmt::Display for ModifyError<E, S>
+where
+    E: fmt::Display,
+    S: fmt::Display
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModifyError::Modify(e) => fmt::Display::fmt(e, f),
+            ModifyError::Save(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl<E, S> fmt::Debug for ModifyError<E, S>
+where
+    E: fmt::Debug,
+    S: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModifyError::Modify(e) => f.debug_tuple("Modify").field(e).finish(),
+            ModifyError::Save(e) => f.debug_tuple("Save").field(e).finish(),
+        }
+    }
+}
+
+impl<E, S> std::error::Error for ModifyError<E, S>
+where
+    E: std::error::Error + 'static,
+    S: std::error::Error + 'static
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModifyError::Modify(e) => Some(e),
+            ModifyError::Save(e) => Some(e),
+        }
+    }
+}
+
+/// the modified time and length of a file used to check if it has changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Stamp {
+    fn new(metadata: &Metadata) -> Self {
+        Stamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        }
+    }
+}
+
+/// reads and decodes the file at the given path
+///
+/// the stamp is taken from the open file before it is read
+fn read<T, F>(path: &Path, format: &F) -> Result<(T, Stamp), F::Error>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    let file = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .map_err(FileError::map(Operation::Open, path))?;
+    let metadata = file.metadata()
+        .map_err(FileError::map(Operation::Read, path))?;
+
+    check_size(format.max_size(), path, metadata.len())?;
+
+    let reader = BufReader::new(file);
+
+    Ok((format.from_reader(reader)?, Stamp::new(&metadata)))
+}
+
+/// reads the entire file at the given path checking the max size of the
+/// format first
+fn read_bytes<F>(path: &Path, format: &F) -> Result<(Vec<u8>, Stamp), FileError>
+where
+    F: Format
+{
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .map_err(FileError::map(Operation::Open, path))?;
+    let metadata = file.metadata()
+        .map_err(FileError::map(Operation::Read, path))?;
+
+    check_size(format.max_size(), path, metadata.len())?;
+
+    let mut buffer = Vec::with_capacity(metadata.len() as usize);
+
+    file.read_to_end(&mut buffer)
+        .map_err(FileError::map(Operation::Read, path))?;
+
+    Ok((buffer, Stamp::new(&metadata)))
+}
+
+/// reads the entire file at the given path using tokio fs checking the max
+/// size of the format first
+#[cfg(feature = "tokio")]
+async fn read_bytes_async<F>(path: &Path, format: &F) -> Result<(Vec<u8>, Stamp), FileError>
+where
+    F: Format
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(FileError::map(Operation::Open, path))?;
+    let metadata = file.metadata()
+        .await
+        .map_err(FileError::map(Operation::Read, path))?;
+
+    check_size(format.max_size(), path, metadata.len())?;
+
+    let mut buffer = Vec::with_capacity(metadata.len() as usize);
+
+    file.read_to_end(&mut buffer)
+        .await
+        .map_err(FileError::map(Operation::Read, path))?;
+
+    Ok((buffer, Stamp::new(&metadata)))
+}
+
+/// reads the async reader to the end
+#[cfg(feature = "tokio")]
+async fn read_async<R>(mut reader: R) -> Result<Vec<u8>, IoError>
+where
+    R: tokio::io::AsyncRead + Unpin
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut buffer = Vec::new();
+
+    reader.read_to_end(&mut buffer).await?;
+
+    Ok(buffer)
+}
+
+/// a value that is saved to and loaded from a file path using the given
+/// Format
+pub struct FileWrapped<T, F> {
+    inner: T,
+    path: Box<Path>,
+    format: F,
+    permissions: Option<Permissions>,
+    sync: Option<SyncMode>,
+    stamp: Mutex<Option<Stamp>>,
+}
+
+impl<T, F> FileWrapped<T, F> {
+    /// creates a new FileWrapped with the provided data and format
+    ///
+    /// no checks are made on the path to ensure that the file exists
+    pub fn with_format<P>(inner: T, path: P, format: F) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        FileWrapped {
+            inner,
+            path: path.into().into(),
+            format,
+            permissions: None,
+            sync: None,
+            stamp: Mutex::new(None),
+        }
+    }
+
+    /// creates a new FileWrapped with the provided data and format and makes
+    /// the file
+    ///
+    /// will attempt to create a new file and throw an error if a file already
+    /// exists. the file is created with the permissions of the format
+    pub fn create_with<P>(inner: T, path: P, format: F) -> Result<Self, F::Error>
+    where
+        F: Format,
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = path.into().into();
+        let metadata = touch_file(&path, &format.permissions())?;
+
+        Ok(FileWrapped {
+            inner,
+            path,
+            format,
+            permissions: None,
+            sync: None,
+            stamp: Mutex::new(Some(Stamp::new(&metadata))),
+        })
+    }
+
+    /// returns the current path for the wrapper
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// updates the current path to the provided value
+    ///
+    /// the file is considered stale until it is loaded or saved
+    pub fn set_path<P>(&mut self, path: P)
+    where
+        P: Into<PathBuf>
+    {
+        self.path = path.into().into();
+        self.set_stamp(None);
+    }
+
+    /// updates the stamp of the file at the current path
+    fn set_stamp(&self, stamp: Option<Stamp>) {
+        *self.stamp.lock().unwrap_or_else(|err| err.into_inner()) = stamp;
+    }
+
+    /// returns the stamp of the file at the current path
+    fn get_stamp(&self) -> Option<Stamp> {
+        *self.stamp.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// sets the permissions used when saving creates the file
+    ///
+    /// overrides the permissions of the format
+    pub fn with_permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// returns the permissions set for the wrapper
+    pub fn permissions(&self) -> Option<&Permissions> {
+        self.permissions.as_ref()
+    }
+
+    /// updates the permissions used when saving creates the file
+    ///
+    /// None will use the permissions of the format
+    pub fn set_permissions(&mut self, permissions: Option<Permissions>) {
+        self.permissions = permissions;
+    }
+
+    /// sets how saves are flushed to disk
+    ///
+    /// overrides the sync mode of the format
+    pub fn with_sync_mode(mut self, sync: SyncMode) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    /// returns the sync mode set for the wrapper
+    pub fn sync_mode(&self) -> Option<SyncMode> {
+        self.sync
+    }
+
+    /// updates how saves are flushed to disk
+    ///
+    /// None will use the sync mode of the format
+    pub fn set_sync_mode(&mut self, sync: Option<SyncMode>) {
+        self.sync = sync;
+    }
+
+    /// returns the format used for the file
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// returns a mutable format used for the file
+    pub fn format_mut(&mut self) -> &mut F {
+        &mut self.format
+    }
+
+    /// returns the inner value
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// returns a mutable inner value
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// consumes the struct returning the inner value
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    F: Default
+{
+    /// creates a new FileWrapped with the provided data
+    ///
+    /// no checks are made on the path to ensure that the file exists
+    pub fn new<P>(inner: T, path: P) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        Self::with_format(inner, path, F::default())
+    }
+
+    /// creates a new FileWrapped with the provided data and makes the file
+    ///
+    /// see create_with for details
+    pub fn create<P>(inner: T, path: P) -> Result<Self, F::Error>
+    where
+        F: Format,
+        P: Into<PathBuf>
+    {
+        Self::create_with(inner, path, F::default())
+    }
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    F: Format
+{
+    /// returns the permissions of the wrapper or the format if none are set
+    fn resolve_permissions(&self) -> Permissions {
+        self.permissions.unwrap_or_else(|| self.format.permissions())
+    }
+
+    /// returns the sync mode of the wrapper or the format if none is set
+    fn resolve_sync_mode(&self) -> SyncMode {
+        self.sync.unwrap_or_else(|| self.format.sync_mode())
+    }
+
+    /// checks if the file at the current path changed since it was last
+    /// loaded or saved by this wrapper
+    ///
+    /// compares the modified time and length of the file so a change that
+    /// keeps both the same is not seen. a wrapper that has not loaded or
+    /// saved the current path is stale if the file exists
+    pub fn is_stale(&self) -> Result<bool, F::Error> {
+        let current = match std::fs::metadata(&self.path) {
+            Ok(metadata) => Some(Stamp::new(&metadata)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(FileError::new(Operation::Read, self.path.to_path_buf(), err).into()),
+        };
+
+        Ok(current != self.get_stamp())
+    }
+
+    /// blocks until an exclusive lock is acquired for the current path
+    ///
+    /// the lock is advisory so only other processes that also lock the path
+    /// will wait on it
+    pub fn lock_exclusive(&self) -> Result<FileLock, F::Error> {
+        Ok(FileLock::exclusive(&self.path)
+            .map_err(FileError::map(Operation::Lock, &self.path))?)
+    }
+
+    /// blocks until a shared lock is acquired for the current path
+    pub fn lock_shared(&self) -> Result<FileLock, F::Error> {
+        Ok(FileLock::shared(&self.path)
+            .map_err(FileError::map(Operation::Lock, &self.path))?)
+    }
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// saves the inner value to the current path
+    ///
+    /// the data is written to a temp file that is then renamed over the
+    /// destination so a failed save will not destroy the previous contents.
+    /// the file is flushed to disk based on the sync mode before returning
+    pub fn save(&self) -> Result<(), F::Error> {
+        let metadata = self.write_to(&self.path)?;
+
+        self.set_stamp(Some(Stamp::new(&metadata)));
+
+        Ok(())
+    }
+
+    /// writes the inner value to the given path returning the metadata of
+    /// the written file
+    fn write_to(&self, path: &Path) -> Result<Metadata, F::Error> {
+        atomic::save(
+            path,
+            &self.resolve_permissions(),
+            self.resolve_sync_mode(),
+            |writer| self.format.to_writer(writer, &self.inner)
+        )
+    }
+
+    /// writes the inner value to the given path without changing the current
+    /// path
+    ///
+    /// uses the same temp file and rename as save
+    pub fn copy_to<P>(&self, path: P) -> Result<(), F::Error>
+    where
+        P: AsRef<Path>
+    {
+        self.write_to(path.as_ref())?;
+
+        Ok(())
+    }
+
+    /// writes the inner value to the given path and updates the current path
+    /// if successful
+    pub fn save_as<P>(&mut self, path: P) -> Result<(), F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path = path.into();
+        let metadata = self.write_to(&path)?;
+
+        self.set_path(path);
+        self.set_stamp(Some(Stamp::new(&metadata)));
+
+        Ok(())
+    }
+
+    /// applies the closure to the inner value and then saves it
+    ///
+    /// the inner value keeps the changes even if the save fails
+    pub fn modify<Func, R>(&mut self, f: Func) -> Result<R, F::Error>
+    where
+        Func: FnOnce(&mut T) -> R
+    {
+        let rtn = f(&mut self.inner);
+
+        self.save()?;
+
+        Ok(rtn)
+    }
+
+    /// applies the fallible closure to the inner value and then saves it if
+    /// the closure succeeds
+    ///
+    /// the closure is responsible for leaving the inner value in a valid
+    /// state if it fails
+    pub fn try_modify<Func, R, E>(&mut self, f: Func) -> Result<R, ModifyError<E, F::Error>>
+    where
+        Func: FnOnce(&mut T) -> Result<R, E>
+    {
+        let rtn = f(&mut self.inner).map_err(ModifyError::Modify)?;
+
+        self.save().map_err(ModifyError::Save)?;
+
+        Ok(rtn)
+    }
+
+    /// saves the inner value while holding an exclusive lock on the current
+    /// path
+    pub fn save_locked(&self) -> Result<(), F::Error> {
+        let _lock = self.lock_exclusive()?;
+
+        self.save()
+    }
+
+    /// encodes the inner value into the writer using the same encoding as
+    /// save
+    ///
+    /// the current path is not used
+    pub fn save_to_writer<W>(&self, writer: W) -> Result<(), F::Error>
+    where
+        W: Write
+    {
+        self.format.to_writer(writer, &self.inner)
+    }
+
+    /// encodes the inner value into the async writer using the same encoding
+    /// as save
+    ///
+    /// the value is encoded into memory before being written and the writer
+    /// is flushed after
+    #[cfg(feature = "tokio")]
+    pub async fn save_to_writer_async<W>(&self, mut writer: W) -> Result<(), F::Error>
+    where
+        W: tokio::io::AsyncWrite + Unpin
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut buffer = Vec::new();
+
+        self.format.to_writer(&mut buffer, &self.inner)?;
+
+        writer.write_all(buffer.as_slice()).await?;
+        writer.flush().await?;
+
+        Ok(())
+    }
+
+    /// saves the inner value to the current path using tokio fs
+    ///
+    /// the value is encoded into memory before being written. similar
+    /// operation as the blocking save
+    #[cfg(feature = "tokio")]
+    pub async fn save_async(&self) -> Result<(), F::Error> {
+        let metadata = self.write_to_async(&self.path).await?;
+
+        self.set_stamp(Some(Stamp::new(&metadata)));
+
+        Ok(())
+    }
+
+    /// writes the inner value to the given path using tokio fs returning the
+    /// metadata of the written file
+    #[cfg(feature = "tokio")]
+    async fn write_to_async(&self, path: &Path) -> Result<Metadata, F::Error> {
+        let mut buffer = Vec::new();
+
+        self.format.to_writer(&mut buffer, &self.inner)?;
+
+        Ok(atomic::save_async(
+            path,
+            &self.resolve_permissions(),
+            self.resolve_sync_mode(),
+            buffer.as_slice()
+        ).await?)
+    }
+
+    /// writes the inner value to the given path using tokio fs without
+    /// changing the current path
+    ///
+    /// similar operation as the blocking copy_to
+    #[cfg(feature = "tokio")]
+    pub async fn copy_to_async<P>(&self, path: P) -> Result<(), F::Error>
+    where
+        P: AsRef<Path>
+    {
+        self.write_to_async(path.as_ref()).await?;
+
+        Ok(())
+    }
+
+    /// writes the inner value to the given path using tokio fs and updates
+    /// the current path if successful
+    ///
+    /// similar operation as the blocking save_as
+    #[cfg(feature = "tokio")]
+    pub async fn save_as_async<P>(&mut self, path: P) -> Result<(), F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path = path.into();
+        let metadata = self.write_to_async(&path).await?;
+
+        self.set_path(path);
+        self.set_stamp(Some(Stamp::new(&metadata)));
+
+        Ok(())
+    }
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// loads the specified file using the provided format
+    ///
+    /// assumes that the file already exists
+    pub fn load_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+        let (inner, stamp) = read(&path, &format)?;
+
+        Ok(FileWrapped {
+            inner,
+            path,
+            format,
+            permissions: None,
+            sync: None,
+            stamp: Mutex::new(Some(stamp)),
+        })
+    }
+
+    /// re-reads the file at the current path and replaces the inner value
+    ///
+    /// the inner value is left unchanged if an error is returned
+    pub fn reload(&mut self) -> Result<(), F::Error> {
+        let (inner, stamp) = read(&self.path, &self.format)?;
+
+        self.inner = inner;
+        self.set_stamp(Some(stamp));
+
+        Ok(())
+    }
+
+    /// reloads the file if it changed since it was last loaded or saved
+    ///
+    /// returns true if the file was reloaded. see is_stale for details
+    pub fn reload_if_changed(&mut self) -> Result<bool, F::Error> {
+        if self.is_stale()? {
+            self.reload()?;
+
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// decodes a value from the reader and replaces the inner value
+    ///
+    /// the inner value is left unchanged if an error is returned
+    pub fn reload_from_reader<R>(&mut self, reader: R) -> Result<(), F::Error>
+    where
+        R: Read
+    {
+        self.inner = self.format.from_reader(reader)?;
+
+        Ok(())
+    }
+
+    /// decodes a value from the async reader and replaces the inner value
+    ///
+    /// the reader is read to the end before decoding. similar to the
+    /// blocking reload_from_reader
+    #[cfg(feature = "tokio")]
+    pub async fn reload_from_reader_async<R>(&mut self, reader: R) -> Result<(), F::Error>
+    where
+        R: tokio::io::AsyncRead + Unpin
+    {
+        let buffer = read_async(reader).await?;
+
+        self.inner = self.format.from_reader(buffer.as_slice())?;
+
+        Ok(())
+    }
+
+    /// decodes a value from the reader using the provided format
+    ///
+    /// the path is only stored and nothing is read from it
+    pub fn load_from_reader_with<R, P>(reader: R, path: P, format: F) -> Result<Self, F::Error>
+    where
+        R: Read,
+        P: Into<PathBuf>
+    {
+        let inner = format.from_reader(reader)?;
+
+        Ok(Self::with_format(inner, path, format))
+    }
+
+    /// decodes a value from the async reader using the provided format
+    ///
+    /// the reader is read to the end before decoding. similar to the
+    /// blocking load_from_reader_with
+    #[cfg(feature = "tokio")]
+    pub async fn load_from_reader_with_async<R, P>(
+        reader: R,
+        path: P,
+        format: F
+    ) -> Result<Self, F::Error>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        P: Into<PathBuf>
+    {
+        let buffer = read_async(reader).await?;
+        let inner = format.from_reader(buffer.as_slice())?;
+
+        Ok(Self::with_format(inner, path, format))
+    }
+
+    /// re-reads the file at the current path using tokio fs and replaces the
+    /// inner value
+    ///
+    /// similar to the blocking reload
+    #[cfg(feature = "tokio")]
+    pub async fn reload_async(&mut self) -> Result<(), F::Error> {
+        let (buffer, stamp) = read_bytes_async(&self.path, &self.format).await?;
+
+        self.inner = self.format.from_reader(buffer.as_slice())?;
+        self.set_stamp(Some(stamp));
+
+        Ok(())
+    }
+
+    /// loads the specified file using the provided format while holding a
+    /// shared lock on the path
+    pub fn load_locked_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path = given.into();
+        let _lock = FileLock::shared(&path)
+            .map_err(FileError::map(Operation::Lock, &path))?;
+
+        Self::load_with(path, format)
+    }
+
+    /// loads or creates the specified file using the provided format
+    ///
+    /// if the file exists it will be loaded unless it is empty in which case
+    /// the default is returned. if the file does not exist an empty file is
+    /// created and the default is returned.
+    pub fn load_or_create_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        T: Default,
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+
+        let exists = path.try_exists()
+            .map_err(FileError::map(Operation::Open, &path))?;
+
+        if exists {
+            let (buffer, stamp) = read_bytes(&path, &format)?;
+
+            let inner = if buffer.is_empty() {
+                T::default()
+            } else {
+                format.from_reader(buffer.as_slice())?
+            };
+
+            Ok(FileWrapped {
+                inner,
+                path,
+                format,
+                permissions: None,
+                sync: None,
+                stamp: Mutex::new(Some(stamp)),
+            })
+        } else {
+            let metadata = touch_file(&path, &format.permissions())?;
+
+            Ok(FileWrapped {
+                inner: T::default(),
+                path,
+                format,
+                permissions: None,
+                sync: None,
+                stamp: Mutex::new(Some(Stamp::new(&metadata))),
+            })
+        }
+    }
+
+    /// loads the specified file using the provided format and tokio fs
+    ///
+    /// the entire file is read into memory before decoding. similar to the
+    /// blocking load
+    #[cfg(feature = "tokio")]
+    pub async fn load_with_async<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+        let (buffer, stamp) = read_bytes_async(&path, &format).await?;
+
+        let inner = format.from_reader(buffer.as_slice())?;
+
+        Ok(FileWrapped {
+            inner,
+            path,
+            format,
+            permissions: None,
+            sync: None,
+            stamp: Mutex::new(Some(stamp)),
+        })
+    }
+
+    /// loads or creates the specified file using the provided format and
+    /// tokio fs
+    ///
+    /// similar to the blocking load_or_create_with
+    #[cfg(feature = "tokio")]
+    pub async fn load_or_create_with_async<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        T: Default,
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+
+        let exists = tokio::fs::try_exists(&path)
+            .await
+            .map_err(FileError::map(Operation::Open, &path))?;
+
+        if exists {
+            let (buffer, stamp) = read_bytes_async(&path, &format).await?;
+
+            let inner = if buffer.is_empty() {
+                T::default()
+            } else {
+                format.from_reader(buffer.as_slice())?
+            };
+
+            Ok(FileWrapped {
+                inner,
+                path,
+                format,
+                permissions: None,
+                sync: None,
+                stamp: Mutex::new(Some(stamp)),
+            })
+        } else {
+            let permissions = format.permissions();
+            let mut options = tokio::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            permissions.open_options_async(&mut options);
+
+            let file = options.open(&path)
+                .await
+                .map_err(FileError::map(Operation::Create, &path))?;
+
+            permissions.apply_async(&file)
+                .await
+                .map_err(FileError::map(Operation::Create, &path))?;
+
+            let metadata = file.metadata()
+                .await
+                .map_err(FileError::map(Operation::Create, &path))?;
+
+            Ok(FileWrapped {
+                inner: T::default(),
+                path,
+                format,
+                permissions: None,
+                sync: None,
+                stamp: Mutex::new(Some(Stamp::new(&metadata))),
+            })
+        }
+    }
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    T: DeserializeOwned,
+    F: Format + Default
+{
+    /// loads the specified file
+    ///
+    /// assumes that the file already exists
+    pub fn load<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with(given, F::default())
+    }
+
+    /// loads the specified file while holding a shared lock on the path
+    pub fn load_locked<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_locked_with(given, F::default())
+    }
+
+    /// decodes a value from the reader
+    ///
+    /// see load_from_reader_with for details
+    pub fn load_from_reader<R, P>(reader: R, path: P) -> Result<Self, F::Error>
+    where
+        R: Read,
+        P: Into<PathBuf>
+    {
+        Self::load_from_reader_with(reader, path, F::default())
+    }
+
+    /// decodes a value from the async reader
+    ///
+    /// see load_from_reader_with_async for details
+    #[cfg(feature = "tokio")]
+    pub async fn load_from_reader_async<R, P>(reader: R, path: P) -> Result<Self, F::Error>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        P: Into<PathBuf>
+    {
+        Self::load_from_reader_with_async(reader, path, F::default()).await
+    }
+
+    /// loads or creates the specified file
+    ///
+    /// see load_or_create_with for details
+    pub fn load_or_create<P>(given: P) -> Result<Self, F::Error>
+    where
+        T: Default,
+        P: Into<PathBuf>
+    {
+        Self::load_or_create_with(given, F::default())
+    }
+
+    /// loads the specified file using tokio fs
+    ///
+    /// similar to the blocking load
+    #[cfg(feature = "tokio")]
+    pub async fn load_async<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with_async(given, F::default()).await
+    }
+
+    /// loads or creates the specified file using tokio fs
+    ///
+    /// similar to the blocking load_or_create
+    #[cfg(feature = "tokio")]
+    pub async fn load_or_create_async<P>(given: P) -> Result<Self, F::Error>
+    where
+        T: Default,
+        P: Into<PathBuf>
+    {
+        Self::load_or_create_with_async(given, F::default()).await
+    }
+}
+
+/// the format is not shown since it can contain sensitive data
+impl<T, F> std::fmt::Debug for FileWrapped<T, F>
+where
+    T: std::fmt::Debug
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWrapped")
+            .field("inner", &self.inner)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, F> std::convert::AsRef<T> for FileWrapped<T, F> {
+    fn as_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, F> std::convert::AsMut<T> for FileWrapped<T, F> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T, F> Clone for FileWrapped<T, F>
+where
+    T: Clone,
+    F: Clone
+{
+    fn clone(&self) -> Self {
+        FileWrapped {
+            inner: self.inner.clone(),
+            path: self.path.clone(),
+            format: self.format.clone(),
+            permissions: self.permissions,
+            sync: self.sync,
+            stamp: Mutex::new(self.get_stamp()),
+        }
+    }
+}
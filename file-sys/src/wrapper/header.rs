@@ -0,0 +1,239 @@
+use std::io::{Read, Write};
+use std::io::ErrorKind as IoErrorKind;
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::encrypted::{Cipher, Error, Kdf, Payload};
+use super::rotation::DataKeyInfo;
+
+/// the bytes at the start of every file with a header
+///
+/// files saved before the header was added start with a random nonce or
+/// envelope stanza count so the magic is long enough to not be mistaken
+/// for one
+pub(crate) const MAGIC: [u8; 8] = *b"\x89FSENC\r\n";
+
+/// the version of the layout written after the header
+pub(crate) const VERSION: u8 = 1;
+
+/// the version reported for files without a header
+pub(crate) const LEGACY_VERSION: u8 = 0;
+
+/// the length of the fixed fields that are authenticated with the file data
+pub(crate) const AAD_LEN: usize = MAGIC.len() + 4;
+
+const FLAG_CHUNKED: u8 = 0b1;
+const FLAG_ENVELOPE: u8 = 0b10;
+const FLAG_COMPRESSED: u8 = 0b100;
+const FLAG_DATA_KEY: u8 = 0b1000;
+
+/// the flags that can be read with the enabled features
+#[cfg(feature = "zstd")]
+const KNOWN_FLAGS: u8 = FLAG_CHUNKED | FLAG_ENVELOPE | FLAG_COMPRESSED | FLAG_DATA_KEY;
+#[cfg(not(feature = "zstd"))]
+const KNOWN_FLAGS: u8 = FLAG_CHUNKED | FLAG_ENVELOPE | FLAG_DATA_KEY;
+
+const KDF_NONE: u8 = 0;
+const KDF_PBKDF2_SHA256: u8 = 1;
+const KDF_HKDF_SHA256: u8 = 2;
+
+/// the fields stored at the start of an encrypted file
+///
+/// the layout is the magic, version, cipher, payload and flags followed by
+/// the kdf id and its params and then the data key saves and creation time
+/// if a rotation policy is used. the fixed fields are used as associated
+/// data when encrypting so they cannot be changed without failing to
+/// decrypt. the kdf params describe the master key and are not
+/// authenticated so a rewrap can replace them. the data key fields change
+/// with every save and are not authenticated either
+#[derive(Debug, Clone)]
+pub(crate) struct Header {
+    pub(crate) version: u8,
+    pub(crate) cipher: Cipher,
+    pub(crate) payload: Payload,
+    pub(crate) chunked: bool,
+    pub(crate) envelope: bool,
+    /// if the payload was compressed with zstd before it was encrypted
+    pub(crate) compressed: bool,
+    pub(crate) kdf: Kdf,
+    /// how long the data key has been used if a rotation policy is used
+    pub(crate) data_key: Option<DataKeyInfo>,
+}
+
+impl Header {
+    /// returns the fixed fields of the header
+    pub(crate) fn aad(&self) -> [u8; AAD_LEN] {
+        let mut flags = 0;
+
+        if self.chunked {
+            flags |= FLAG_CHUNKED;
+        }
+
+        if self.envelope {
+            flags |= FLAG_ENVELOPE;
+        }
+
+        if self.compressed {
+            flags |= FLAG_COMPRESSED;
+        }
+
+        if self.data_key.is_some() {
+            flags |= FLAG_DATA_KEY;
+        }
+
+        let mut rtn = [0; AAD_LEN];
+        rtn[..MAGIC.len()].copy_from_slice(&MAGIC);
+        rtn[MAGIC.len()..].copy_from_slice(&[
+            self.version,
+            self.cipher.id(),
+            self.payload.id(),
+            flags,
+        ]);
+        rtn
+    }
+
+    pub(crate) fn write<W>(&self, writer: &mut W) -> Result<(), Error>
+    where
+        W: Write
+    {
+        writer.write_all(&self.aad())?;
+
+        match &self.kdf {
+            Kdf::None => writer.write_all(&[KDF_NONE])?,
+            Kdf::Pbkdf2Sha256 { rounds, salt } => {
+                writer.write_all(&[KDF_PBKDF2_SHA256])?;
+                writer.write_all(&rounds.to_le_bytes())?;
+                writer.write_all(&[salt.len() as u8])?;
+                writer.write_all(salt)?;
+            }
+            Kdf::HkdfSha256 { label } => {
+                writer.write_all(&[KDF_HKDF_SHA256, label.len() as u8])?;
+                writer.write_all(label)?;
+            }
+        }
+
+        if let Some(data_key) = &self.data_key {
+            let created = data_key.created.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+
+            writer.write_all(&data_key.saves.to_le_bytes())?;
+            writer.write_all(&created.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// reads the header from the start of the reader
+    ///
+    /// if the reader does not start with the magic then None is returned
+    /// along with the bytes that were read so the legacy layout can be read
+    /// from them
+    pub(crate) fn read<R>(reader: &mut R) -> Result<(Option<Header>, Vec<u8>), Error>
+    where
+        R: Read
+    {
+        let mut prefix = vec![0; MAGIC.len()];
+        let mut read = 0;
+
+        while read < prefix.len() {
+            match reader.read(&mut prefix[read..]) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(err) if err.kind() == IoErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if read < prefix.len() || prefix != MAGIC {
+            prefix.truncate(read);
+
+            return Ok((None, prefix));
+        }
+
+        let mut fields = [0; 5];
+
+        reader.read_exact(&mut fields)
+            .map_err(|_| Error::TruncatedHeader)?;
+
+        let [version, cipher, payload, flags, kdf] = fields;
+
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::InvalidEncoding);
+        }
+
+        let cipher = Cipher::from_id(cipher).ok_or(Error::InvalidEncoding)?;
+        let payload = Payload::from_id(payload).ok_or(Error::InvalidEncoding)?;
+
+        let kdf = match kdf {
+            KDF_NONE => Kdf::None,
+            KDF_PBKDF2_SHA256 => {
+                let mut rounds = [0; 4];
+                let mut salt_len = [0];
+
+                reader.read_exact(&mut rounds)
+                    .and_then(|_| reader.read_exact(&mut salt_len))
+                    .map_err(|_| Error::TruncatedHeader)?;
+
+                let mut salt = vec![0; salt_len[0] as usize];
+
+                reader.read_exact(&mut salt)
+                    .map_err(|_| Error::TruncatedHeader)?;
+
+                Kdf::Pbkdf2Sha256 {
+                    rounds: u32::from_le_bytes(rounds),
+                    salt,
+                }
+            }
+            KDF_HKDF_SHA256 => {
+                let mut label_len = [0];
+
+                reader.read_exact(&mut label_len)
+                    .map_err(|_| Error::TruncatedHeader)?;
+
+                let mut label = vec![0; label_len[0] as usize];
+
+                reader.read_exact(&mut label)
+                    .map_err(|_| Error::TruncatedHeader)?;
+
+                Kdf::HkdfSha256 { label }
+            }
+            _ => return Err(Error::InvalidEncoding),
+        };
+
+        let data_key = if flags & FLAG_DATA_KEY != 0 {
+            let mut saves = [0; 4];
+            let mut created = [0; 8];
+
+            reader.read_exact(&mut saves)
+                .and_then(|_| reader.read_exact(&mut created))
+                .map_err(|_| Error::TruncatedHeader)?;
+
+            let created = UNIX_EPOCH.checked_add(Duration::from_secs(u64::from_le_bytes(created)))
+                .ok_or(Error::InvalidEncoding)?;
+
+            Some(DataKeyInfo {
+                saves: u32::from_le_bytes(saves),
+                created,
+            })
+        } else {
+            None
+        };
+
+        let header = Header {
+            version,
+            cipher,
+            payload,
+            chunked: flags & FLAG_CHUNKED != 0,
+            envelope: flags & FLAG_ENVELOPE != 0,
+            compressed: flags & FLAG_COMPRESSED != 0,
+            kdf,
+            data_key,
+        };
+
+        Ok((Some(header), Vec::new()))
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+use std::path::{PathBuf, Path};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::append_log::{AppendLog, Iter};
+use super::format::{Format, FileWrapped};
+
+/// returns the path of the journal for the given state file
+fn journal_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".journal");
+
+    PathBuf::from(name)
+}
+
+/// a value saved to a state file along with a journal of every change
+///
+/// the state file holds the current version and value. every modify appends
+/// the new version and value to the journal before the state file is saved
+/// so a crash between the two is recovered from the journal when opened.
+/// the journal is stored next to the state file with ".journal" appended to
+/// the name. the journal grows with every modify unless a max number of
+/// entries is set with with_max_entries or it is trimmed with compact.
+pub struct Journaled<T, F> {
+    state: FileWrapped<(u64, T), F>,
+    journal: AppendLog<(u64, T), F>,
+    max_entries: Option<usize>,
+}
+
+impl<T, F> Journaled<T, F>
+where
+    T: Serialize + DeserializeOwned + Default,
+    F: Format + Clone
+{
+    /// opens the state file and journal using the provided format creating
+    /// them if they do not exist
+    ///
+    /// if the journal has a newer version than the state file the state is
+    /// updated to the last journal entry
+    pub fn open_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path = given.into();
+        let journal: AppendLog<(u64, T), F> = AppendLog::open_with(
+            journal_path(&path),
+            format.clone()
+        )?;
+        let mut state: FileWrapped<(u64, T), F> = FileWrapped::load_or_create_with(path, format)?;
+
+        if let Some(entry) = journal.last()? {
+            if entry.0 > state.inner().0 {
+                *state.inner_mut() = entry;
+                state.save()?;
+            }
+        }
+
+        Ok(Journaled {
+            state,
+            journal,
+            max_entries: None,
+        })
+    }
+}
+
+impl<T, F> Journaled<T, F>
+where
+    T: Serialize + DeserializeOwned + Default,
+    F: Format + Clone + Default
+{
+    /// opens the state file and journal creating them if they do not exist
+    ///
+    /// see open_with for details
+    pub fn open<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::open_with(given, F::default())
+    }
+}
+
+impl<T, F> Journaled<T, F> {
+    /// returns the current value
+    pub fn inner(&self) -> &T {
+        &self.state.inner().1
+    }
+
+    /// returns the version of the current value
+    ///
+    /// starts at 0 and increases by one for every modify
+    pub fn version(&self) -> u64 {
+        self.state.inner().0
+    }
+
+    /// returns the path of the state file
+    pub fn path(&self) -> &Path {
+        self.state.path()
+    }
+
+    /// returns the path of the journal
+    pub fn journal_path(&self) -> &Path {
+        self.journal.path()
+    }
+
+    /// returns the max number of entries kept in the journal
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// sets the max number of entries kept in the journal
+    ///
+    /// once a modify grows the journal past the max it is compacted down to
+    /// the newest max entries. the newest entry is always kept so a max of 0
+    /// keeps 1
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// consumes the struct returning the current value
+    pub fn into_inner(self) -> T {
+        self.state.into_inner().1
+    }
+}
+
+impl<T, F> Journaled<T, F>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    F: Format
+{
+    /// applies the closure to a copy of the current value then records it in
+    /// the journal and saves it as the new state
+    ///
+    /// the current value is only replaced if the journal entry is written. if
+    /// saving the state fails the value is still updated since the journal
+    /// will restore it when opened. the same goes for compacting the journal
+    /// when it grows past the max entries
+    pub fn modify<Func, R>(&mut self, f: Func) -> Result<R, F::Error>
+    where
+        Func: FnOnce(&mut T) -> R
+    {
+        let (version, current) = self.state.inner();
+        let mut value = current.clone();
+        let rtn = f(&mut value);
+        let entry = (version + 1, value);
+
+        self.journal.append(&entry)?;
+
+        *self.state.inner_mut() = entry;
+        self.state.save()?;
+
+        if let Some(max_entries) = self.max_entries {
+            if self.journal.len() > max_entries.max(1) {
+                self.compact(max_entries)?;
+            }
+        }
+
+        Ok(rtn)
+    }
+}
+
+impl<T, F> Journaled<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// iterates the version and value of every change in the journal from
+    /// oldest to newest
+    pub fn history(&self) -> Result<Iter<'_, (u64, T), F>, F::Error> {
+        self.journal.iter()
+    }
+
+    /// removes every entry from the journal
+    ///
+    /// the state file is kept so the current value and version are not
+    /// changed
+    pub fn clear_history(&mut self) -> Result<(), F::Error> {
+        self.journal.clear()
+    }
+}
+
+impl<T, F> Journaled<T, F>
+where
+    T: Serialize + DeserializeOwned,
+    F: Format
+{
+    /// removes every entry from the journal except for the newest keep
+    /// entries
+    ///
+    /// the newest entry is always kept so a keep of 0 keeps 1
+    pub fn compact(&mut self, keep: usize) -> Result<(), F::Error> {
+        let start = self.journal.len().saturating_sub(keep.max(1));
+
+        if start == 0 {
+            return Ok(());
+        }
+
+        let entries: Vec<(u64, T)> = self.journal.iter_from(start)?
+            .collect::<Result<_, _>>()?;
+
+        self.journal.compact(&entries)
+    }
+}
+
+impl<T, F> fmt::Debug for Journaled<T, F>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Journaled")
+            .field("state", &self.state)
+            .field("journal", &self.journal)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use crate::wrapper::{Json, JsonFormat};
+
+    #[test]
+    fn base() {
+        let file_name = "test.journaled.json";
+        let _ = std::fs::remove_file(file_name);
+        let _ = std::fs::remove_file(journal_path(Path::new(file_name)));
+
+        let mut journaled: Journaled<Vec<u64>, JsonFormat> = Journaled::open(file_name)
+            .expect("failed to open journaled");
+
+        journaled.modify(|value| value.push(1)).expect("failed to modify");
+        journaled.modify(|value| value.push(2)).expect("failed to modify");
+
+        assert_eq!(journaled.version(), 2);
+
+        let history: Vec<(u64, Vec<u64>)> = journaled.history()
+            .expect("failed to read history")
+            .collect::<Result<_, _>>()
+            .expect("failed to read entry");
+
+        assert_eq!(history, vec![(1, vec![1]), (2, vec![1, 2])]);
+
+        journaled.modify(|value| value.push(3)).expect("failed to modify");
+        drop(journaled);
+
+        // simulate a crash after the journal was written
+        Json::new((2u64, vec![1u64, 2]), file_name).save()
+            .expect("failed to write old state");
+
+        let mut journaled: Journaled<Vec<u64>, JsonFormat> = Journaled::open(file_name)
+            .expect("failed to open journaled");
+
+        assert_eq!(journaled.version(), 3);
+        assert_eq!(journaled.inner(), &vec![1, 2, 3]);
+
+        journaled.clear_history().expect("failed to clear history");
+
+        assert_eq!(journaled.history().expect("failed to read history").count(), 0);
+        assert_eq!(journaled.inner(), &vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn max_entries() {
+        let file_name = "test.journaled_max.json";
+        let _ = std::fs::remove_file(file_name);
+        let _ = std::fs::remove_file(journal_path(Path::new(file_name)));
+
+        let mut journaled: Journaled<u64, JsonFormat> = Journaled::open(file_name)
+            .expect("failed to open journaled")
+            .with_max_entries(3);
+
+        for _ in 0..5 {
+            journaled.modify(|value| *value += 1).expect("failed to modify");
+        }
+
+        let history: Vec<(u64, u64)> = journaled.history()
+            .expect("failed to read history")
+            .collect::<Result<_, _>>()
+            .expect("failed to read entry");
+
+        assert_eq!(history, vec![(3, 3), (4, 4), (5, 5)]);
+
+        journaled.compact(0).expect("failed to compact journal");
+        drop(journaled);
+
+        let journaled: Journaled<u64, JsonFormat> = Journaled::open(file_name)
+            .expect("failed to open journaled");
+
+        assert_eq!(journaled.version(), 5);
+        assert_eq!(journaled.history().expect("failed to read history").count(), 1);
+    }
+}
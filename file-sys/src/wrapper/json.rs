@@ -1,6 +1,4 @@
-use std::path::{PathBuf, Path};
-use std::fs::OpenOptions;
-use std::io::{BufReader, BufWriter};
+use std::io::{Read, Write};
 use std::io::Error as IoError;
 use std::fmt;
 
@@ -8,9 +6,14 @@ use serde::Serialize;
 use serde::de::DeserializeOwned;
 use serde_json::error::Category;
 
+use crate::ErrorKind;
+use super::error::FileError;
+use super::format::{Format, FileWrapped};
+
 #[derive(Debug)]
 pub enum Error {
     Io(IoError),
+    File(FileError),
     Json(serde_json::Error),
 }
 
@@ -18,6 +21,7 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::File(e) => fmt::Display::fmt(e, f),
             Error::Json(e) => fmt::Display::fmt(e, f),
         }
     }
@@ -27,145 +31,189 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::Io(e) => Some(e),
+            Error::File(e) => Some(e),
             Error::Json(e) => Some(e),
         }
     }
 }
 
-pub struct Json<T> {
-    inner: T,
-    path: Box<Path>,
+impl Error {
+    /// returns the general category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) => ErrorKind::Io,
+            Error::File(_) => ErrorKind::File,
+            Error::Json(_) => ErrorKind::Format,
+        }
+    }
 }
 
-impl<T> Json<T> {
-    pub fn new<P>(inner: T, path: P) -> Self
-    where
-        P: Into<PathBuf>
-    {
-        let buf = path.into();
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
+}
 
-        Json {
-            inner,
-            path: buf.into(),
-        }
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::File(err)
     }
+}
 
-    pub fn path(&self) -> &Path {
-        &self.path
+fn map_error(e: serde_json::Error) -> Error {
+    match e.classify() {
+        Category::Io => Error::Io(e.into()),
+        _ => Error::Json(e)
     }
+}
 
-    pub fn set_path<P>(&mut self, path: P)
-    where
-        P: Into<PathBuf>
-    {
-        let buf = path.into();
+/// replaces comments and trailing commas with whitespace
+///
+/// newlines are kept so that error positions still match the input
+#[cfg(feature = "json-relaxed")]
+fn relax(input: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(input.len());
+    let mut index = 0;
+    let mut in_string = false;
 
-        self.path = buf.into();
-    }
+    while index < input.len() {
+        let byte = input[index];
 
-    pub fn inner(&self) -> &T {
-        &self.inner
-    }
+        if in_string {
+            stripped.push(byte);
 
-    pub fn inner_mut(&mut self) -> &mut T {
-        &mut self.inner
-    }
+            if byte == b'\\' {
+                if let Some(next) = input.get(index + 1) {
+                    stripped.push(*next);
+                    index += 1;
+                }
+            } else if byte == b'"' {
+                in_string = false;
+            }
+
+            index += 1;
+            continue;
+        }
+
+        match (byte, input.get(index + 1)) {
+            (b'"', _) => {
+                in_string = true;
+                stripped.push(byte);
+                index += 1;
+            }
+            (b'/', Some(b'/')) => {
+                while index < input.len() && input[index] != b'\n' {
+                    stripped.push(b' ');
+                    index += 1;
+                }
+            }
+            (b'/', Some(b'*')) => {
+                stripped.extend(b"  ");
+                index += 2;
 
-    pub fn into_inner(self) -> T {
-        self.inner
+                while index < input.len() && !input[index..].starts_with(b"*/") {
+                    stripped.push(if input[index] == b'\n' { b'\n' } else { b' ' });
+                    index += 1;
+                }
+
+                if index < input.len() {
+                    stripped.extend(b"  ");
+                    index += 2;
+                }
+            }
+            _ => {
+                stripped.push(byte);
+                index += 1;
+            }
+        }
     }
-}
 
-impl<T> Json<T>
-where
-    T: Serialize
-{
-    pub fn save(&self) -> Result<(), Error> {
-        let file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.path)
-            .map_err(|e| Error::Io(e))?;
-        let writer = BufWriter::new(file);
-
-        serde_json::to_writer(writer, &self.inner)
-            .map_err(|e| match e.classify() {
-                Category::Io => Error::Io(e.into()),
-                _ => Error::Json(e)
-            })?;
-
-        Ok(())
+    in_string = false;
+    let mut escaped = false;
+
+    for index in 0..stripped.len() {
+        let byte = stripped[index];
+
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+
+            continue;
+        }
+
+        if byte == b'"' {
+            in_string = true;
+        } else if byte == b',' {
+            let next = stripped[index + 1..].iter()
+                .find(|byte| !byte.is_ascii_whitespace());
+
+            if matches!(next, Some(b'}') | Some(b']')) {
+                stripped[index] = b' ';
+            }
+        }
     }
+
+    stripped
 }
 
-impl<T> Json<T>
-where
-    T: DeserializeOwned
-{
-    pub fn load<P>(given: P) -> Result<Self, Error>
+/// encodes values as json
+///
+/// with the json-relaxed feature, comments and trailing commas are allowed
+/// when loading. values are always saved as strict json.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonFormat;
+
+impl Format for JsonFormat {
+    type Error = Error;
+
+    fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<(), Self::Error>
     where
-        P: Into<PathBuf>
+        W: Write,
+        T: Serialize + ?Sized
     {
-        let path = given.into().into();
-        let file = OpenOptions::new()
-            .read(true)
-            .open(&path)
-            .map_err(|e| Error::Io(e))?;
-        let reader = BufReader::new(file);
-
-        let inner = serde_json::from_reader(reader)
-            .map_err(|e| match e.classify() {
-                Category::Io => Error::Io(e.into()),
-                _ => Error::Json(e)
-            })?;
-
-        Ok(Json {
-            inner,
-            path
-        })
+        serde_json::to_writer(writer, value)
+            .map_err(map_error)
     }
-}
 
-impl<T> std::fmt::Debug for Json<T>
-where
-    T: std::fmt::Debug
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Json")
-            .field("inner", &self.inner)
-            .field("path", &self.path)
-            .finish()
+    #[cfg(not(feature = "json-relaxed"))]
+    fn from_reader<R, T>(&self, reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        serde_json::from_reader(reader)
+            .map_err(map_error)
     }
-}
 
-impl<T> std::convert::AsRef<T> for Json<T> {
-    fn as_ref(&self) -> &T {
-        &self.inner
-    }
-}
+    #[cfg(feature = "json-relaxed")]
+    fn from_reader<R, T>(&self, mut reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        let mut buffer = Vec::new();
 
-impl<T> std::convert::AsMut<T> for Json<T> {
-    fn as_mut(&mut self) -> &mut T {
-        &mut self.inner
-    }
-}
+        reader.read_to_end(&mut buffer)?;
 
-impl<T> Clone for Json<T>
-where
-    T: Clone
-{
-    fn clone(&self) -> Self {
-        Json {
-            inner: self.inner.clone(),
-            path: self.path.clone()
-        }
+        serde_json::from_slice(&relax(&buffer))
+            .map_err(map_error)
     }
 }
 
+/// a value stored in a json file
+pub type Json<T> = FileWrapped<T, JsonFormat>;
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::wrapper;
+    use std::path::{PathBuf, Path};
+    use crate::wrapper::FileLock;
 
     #[test]
     fn base() {
@@ -183,4 +231,187 @@ mod test {
 
         assert_eq!(wrapper.inner(), and_back.inner());
     }
+
+    #[test]
+    fn writer() {
+        let wrapper = Json::new(vec![1u64, 2], "test.writer.json");
+        let mut buffer = Vec::new();
+
+        wrapper.save_to_writer(&mut buffer).expect("failed to write json");
+
+        assert_eq!(buffer, b"[1,2]");
+
+        let mut and_back: Json<Vec<u64>> = Json::load_from_reader(buffer.as_slice(), "test.writer.json")
+            .expect("failed to read json");
+
+        assert_eq!(and_back.inner(), wrapper.inner());
+        assert!(!and_back.path().exists());
+
+        and_back.reload_from_reader(&b"[3]"[..]).expect("failed to reload json");
+
+        assert_eq!(and_back.inner(), &vec![3]);
+    }
+
+    #[test]
+    fn stale() {
+        let file_name = "test.stale.json";
+        let mut wrapper = Json::new(vec![1u64], file_name);
+
+        assert!(!wrapper.is_stale().expect("failed to check json file"));
+
+        wrapper.save().expect("failed to save json file");
+
+        assert!(!wrapper.is_stale().expect("failed to check json file"));
+        assert!(!wrapper.reload_if_changed().expect("failed to reload json file"));
+
+        std::fs::write(file_name, "[1,2,3]").expect("failed to write json file");
+
+        assert!(wrapper.is_stale().expect("failed to check json file"));
+        assert!(wrapper.reload_if_changed().expect("failed to reload json file"));
+        assert_eq!(wrapper.inner(), &vec![1, 2, 3]);
+        assert!(!wrapper.is_stale().expect("failed to check json file"));
+
+        std::fs::remove_file(file_name).expect("failed to remove json file");
+
+        assert!(wrapper.is_stale().expect("failed to check json file"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn writer_async() {
+        let wrapper = Json::new(vec![1u64, 2], "test.writer_async.json");
+        let mut buffer = Vec::new();
+
+        wrapper.save_to_writer_async(&mut buffer)
+            .await
+            .expect("failed to write json");
+
+        let and_back: Json<Vec<u64>> = Json::load_from_reader_async(buffer.as_slice(), "test.writer_async.json")
+            .await
+            .expect("failed to read json");
+
+        assert_eq!(and_back.inner(), wrapper.inner());
+    }
+
+    #[cfg(feature = "json-relaxed")]
+    #[test]
+    fn relaxed() {
+        let file_name = "test.relaxed.json";
+        let input = r#"{
+            // line comment
+            "a": [1, 2, /* inline */ 3,],
+            "b": "not // a comment, /* or this */,]",
+        }"#;
+
+        std::fs::write(file_name, input).expect("failed to write json file");
+
+        let mut wrapper: Json<std::collections::BTreeMap<String, serde_json::Value>> = Json::load(file_name)
+            .expect("failed to load relaxed json file");
+
+        assert_eq!(wrapper.inner()["a"], serde_json::json!([1, 2, 3]));
+        assert_eq!(wrapper.inner()["b"], "not // a comment, /* or this */,]");
+
+        wrapper.inner_mut().remove("a");
+        wrapper.save().expect("failed to save json file");
+
+        assert_eq!(
+            std::fs::read_to_string(file_name).expect("failed to read json file"),
+            r#"{"b":"not // a comment, /* or this */,]"}"#
+        );
+    }
+
+    #[test]
+    fn locked() {
+        let file_name = "test.locked.json";
+        let wrapper = Json::new(vec![1u64, 2, 3], file_name);
+
+        wrapper.save_locked().expect("failed to save locked json file");
+
+        let and_back: Json<Vec<u64>> = Json::load_locked(file_name)
+            .expect("failed to load locked json file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+
+        let lock = wrapper.lock_shared().expect("failed to lock json file");
+
+        assert!(FileLock::try_exclusive(wrapper.path()).unwrap().is_none());
+
+        let _ = std::fs::remove_file(lock.path());
+    }
+
+    #[test]
+    fn load_or_create() {
+        let file_name = "test.load_or_create.json";
+
+        let _ = std::fs::remove_file(file_name);
+
+        let mut wrapper: Json<Vec<u64>> = Json::load_or_create(file_name)
+            .expect("failed to create json file");
+
+        assert!(wrapper.inner().is_empty());
+        assert!(wrapper.path().exists());
+
+        wrapper.inner_mut().push(1);
+        wrapper.save().expect("failed to save json file");
+
+        let and_back: Json<Vec<u64>> = Json::load_or_create(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(and_back.inner(), &vec![1]);
+    }
+
+    #[test]
+    fn error_context() {
+        let file_name = "test.missing.json";
+
+        let _ = std::fs::remove_file(file_name);
+
+        match Json::<u64>::load(file_name) {
+            Err(Error::File(err)) => {
+                assert_eq!(err.op(), &crate::wrapper::Operation::Open);
+                assert_eq!(err.path(), Path::new(file_name));
+                assert!(err.to_string().starts_with("failed to open \"test.missing.json\""));
+            }
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn save_as() {
+        let mut wrapper = Json::new(1u64, "test.original.json");
+
+        wrapper.copy_to("test.copy.json").expect("failed to copy json file");
+
+        assert_eq!(wrapper.path(), Path::new("test.original.json"));
+
+        wrapper.save_as("test.renamed.json").expect("failed to save json file as");
+
+        assert_eq!(wrapper.path(), Path::new("test.renamed.json"));
+
+        for file_name in ["test.copy.json", "test.renamed.json"] {
+            let and_back: Json<u64> = Json::load(file_name)
+                .expect("failed to load json file");
+
+            assert_eq!(and_back.inner(), &1);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio() {
+        let file_name = "test.tokio.json";
+        let inner = vec![String::from("a"), String::from("b")];
+
+        let wrapper = Json::new(inner, file_name);
+
+        wrapper.save_async()
+            .await
+            .expect("failed to save to tokio json file");
+
+        let and_back: Json<Vec<String>> = Json::load_async(file_name)
+            .await
+            .expect("failed to load tokio json file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
 }
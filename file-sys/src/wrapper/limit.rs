@@ -0,0 +1,189 @@
+use std::io::{Read, Write};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::Path;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::atomic::SyncMode;
+use super::error::{FileError, Operation};
+use super::format::Format;
+use super::permissions::Permissions;
+
+/// the io error returned when data is larger than the max size
+fn too_large(max_size: u64) -> IoError {
+    IoError::new(
+        IoErrorKind::FileTooLarge,
+        format!("data is larger than the max size of {} bytes", max_size)
+    )
+}
+
+/// returns a FileError with the FileTooLarge io error kind if the length is
+/// larger than the max size
+pub(crate) fn check_size(max_size: Option<u64>, path: &Path, len: u64) -> Result<(), FileError> {
+    match max_size {
+        Some(max_size) if len > max_size => Err(FileError::new(
+            Operation::Read,
+            path,
+            too_large(max_size)
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// a reader that fails once more than the max size is read
+struct LimitReader<R> {
+    inner: R,
+    max_size: u64,
+    remaining: u64,
+}
+
+impl<R> Read for LimitReader<R>
+where
+    R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let mut probe = [0; 1];
+
+            return match self.inner.read(&mut probe)? {
+                0 => Ok(0),
+                _ => Err(too_large(self.max_size)),
+            };
+        }
+
+        let len = buf.len().min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..len])?;
+
+        self.remaining -= read as u64;
+
+        Ok(read)
+    }
+}
+
+/// wraps a Format and fails to load data larger than the max size
+///
+/// files are checked before they are read so a large file fails without
+/// reading any of it. data from other readers is counted while decoding.
+/// the error is an io error with the FileTooLarge kind.
+#[derive(Debug, Clone)]
+pub struct Limited<F> {
+    format: F,
+    max_size: u64,
+}
+
+impl<F> Limited<F> {
+    /// creates a new Limited with the given format and max size in bytes
+    pub fn new(format: F, max_size: u64) -> Self {
+        Limited {
+            format,
+            max_size,
+        }
+    }
+
+    /// returns the wrapped format
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// returns a mutable wrapped format
+    pub fn format_mut(&mut self) -> &mut F {
+        &mut self.format
+    }
+
+    /// returns the max size in bytes
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// updates the max size in bytes
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
+    }
+
+    /// consumes the struct returning the wrapped format
+    pub fn into_inner(self) -> F {
+        self.format
+    }
+}
+
+impl<F> Format for Limited<F>
+where
+    F: Format
+{
+    type Error = F::Error;
+
+    fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized
+    {
+        self.format.to_writer(writer, value)
+    }
+
+    fn from_reader<R, T>(&self, reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        self.format.from_reader(LimitReader {
+            inner: reader,
+            max_size: self.max_size,
+            remaining: self.max_size,
+        })
+    }
+
+    fn permissions(&self) -> Permissions {
+        self.format.permissions()
+    }
+
+    fn sync_mode(&self) -> SyncMode {
+        self.format.sync_mode()
+    }
+
+    fn max_size(&self) -> Option<u64> {
+        Some(self.max_size)
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use crate::wrapper::{FileWrapped, Json, JsonFormat};
+
+    #[test]
+    fn max_size() {
+        let file_name = "test.limited.json";
+
+        Json::new(vec![0u64; 64], file_name).save()
+            .expect("failed to save json file");
+
+        let result = FileWrapped::<Vec<u64>, _>::load_with(file_name, Limited::new(JsonFormat, 16));
+
+        match result {
+            Err(crate::wrapper::json::Error::File(err)) => {
+                assert_eq!(err.io().kind(), IoErrorKind::FileTooLarge);
+            }
+            result => panic!("unexpected load result: {:?}", result),
+        }
+
+        let bytes = std::fs::read(file_name).expect("failed to read json file");
+        let result: Result<Vec<u64>, _> = Limited::new(JsonFormat, 16).from_reader(bytes.as_slice());
+
+        match result {
+            Err(crate::wrapper::json::Error::Io(err)) => {
+                assert_eq!(err.kind(), IoErrorKind::FileTooLarge);
+            }
+            result => panic!("unexpected decode result: {:?}", result),
+        }
+
+        let loaded = FileWrapped::<Vec<u64>, _>::load_with(file_name, Limited::new(JsonFormat, 1024))
+            .expect("failed to load json file");
+
+        assert_eq!(loaded.inner().len(), 64);
+    }
+}
@@ -0,0 +1,122 @@
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::Path;
+
+use super::memory::Store;
+
+/// a Store that keeps bytes in the browser local storage
+///
+/// the bytes are hex encoded since local storage only holds strings. every
+/// key starts with the prefix followed by the path. only usable when running
+/// in a browser window.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    prefix: String,
+}
+
+impl LocalStorage {
+    /// creates a new store using the default "file-sys:" prefix
+    pub fn new() -> Self {
+        LocalStorage::with_prefix("file-sys:")
+    }
+
+    /// creates a new store with the given key prefix
+    pub fn with_prefix<P>(prefix: P) -> Self
+    where
+        P: Into<String>
+    {
+        LocalStorage {
+            prefix: prefix.into(),
+        }
+    }
+
+    /// returns the prefix of every key
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn key(&self, path: &Path) -> String {
+        format!("{}{}", self.prefix, path.display())
+    }
+
+    fn storage(&self) -> Result<web_sys::Storage, IoError> {
+        let Some(window) = web_sys::window() else {
+            return Err(IoError::new(IoErrorKind::Unsupported, "no browser window available"));
+        };
+
+        match window.local_storage() {
+            Ok(Some(storage)) => Ok(storage),
+            Ok(None) => Err(IoError::new(IoErrorKind::Unsupported, "local storage is not available")),
+            Err(err) => Err(js_error(err)),
+        }
+    }
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        LocalStorage::new()
+    }
+}
+
+impl Store for LocalStorage {
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, IoError> {
+        let value = self.storage()?
+            .get_item(&self.key(path))
+            .map_err(js_error)?;
+
+        value.map(|hex| decode_hex(&hex)).transpose()
+    }
+
+    fn write(&self, path: &Path, bytes: Vec<u8>) -> Result<(), IoError> {
+        self.storage()?
+            .set_item(&self.key(path), &encode_hex(&bytes))
+            .map_err(js_error)
+    }
+}
+
+fn js_error(err: web_sys::wasm_bindgen::JsValue) -> IoError {
+    IoError::other(format!("{:?}", err))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut rtn = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        rtn.push(DIGITS[(byte >> 4) as usize] as char);
+        rtn.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+
+    rtn
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, IoError> {
+    let invalid = || IoError::new(IoErrorKind::InvalidData, "stored value is not valid hex");
+
+    if !hex.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+
+            u8::from_str_radix(pair, 16).map_err(|_| invalid())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        assert_eq!(decode_hex(&encode_hex(&bytes)).expect("failed to decode hex"), bytes);
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+    }
+}
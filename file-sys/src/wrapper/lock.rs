@@ -0,0 +1,133 @@
+use std::path::{PathBuf, Path};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Error as IoError;
+
+/// creates the path of the lock file for the given path
+///
+/// a sibling lock file is used instead of the file itself since saving
+/// renames a new file over the destination which would leave other processes
+/// holding a lock on the old file
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+
+    if let Some(file_name) = path.file_name() {
+        name.push(file_name);
+    }
+
+    name.push(".lock");
+
+    path.with_file_name(name)
+}
+
+/// an advisory lock shared between processes for a given path
+///
+/// the lock is released when dropped. the lock file is left in place so that
+/// other processes waiting on it are not given a different file.
+pub struct FileLock {
+    file: File,
+    path: Box<Path>,
+}
+
+impl FileLock {
+    fn open(path: &Path) -> Result<(File, Box<Path>), IoError> {
+        let path: Box<Path> = lock_path(path).into();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        Ok((file, path))
+    }
+
+    /// blocks until an exclusive lock is acquired for the given path
+    pub fn exclusive(path: &Path) -> Result<Self, IoError> {
+        let (file, path) = Self::open(path)?;
+
+        file.lock()?;
+
+        Ok(FileLock { file, path })
+    }
+
+    /// blocks until a shared lock is acquired for the given path
+    pub fn shared(path: &Path) -> Result<Self, IoError> {
+        let (file, path) = Self::open(path)?;
+
+        file.lock_shared()?;
+
+        Ok(FileLock { file, path })
+    }
+
+    /// attempts to acquire an exclusive lock for the given path without
+    /// blocking
+    ///
+    /// returns None if the lock is held by someone else
+    pub fn try_exclusive(path: &Path) -> Result<Option<Self>, IoError> {
+        let (file, path) = Self::open(path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(FileLock { file, path })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err),
+        }
+    }
+
+    /// attempts to acquire a shared lock for the given path without blocking
+    ///
+    /// returns None if an exclusive lock is held by someone else
+    pub fn try_shared(path: &Path) -> Result<Option<Self>, IoError> {
+        let (file, path) = Self::open(path)?;
+
+        match file.try_lock_shared() {
+            Ok(()) => Ok(Some(FileLock { file, path })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err),
+        }
+    }
+
+    /// returns the path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+impl std::fmt::Debug for FileLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileLock")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exclusive() {
+        let file_name = Path::new("test.lock");
+
+        let lock = FileLock::exclusive(file_name)
+            .expect("failed to lock test file");
+
+        assert!(FileLock::try_exclusive(file_name).unwrap().is_none());
+        assert!(FileLock::try_shared(file_name).unwrap().is_none());
+
+        drop(lock);
+
+        let shared = FileLock::shared(file_name)
+            .expect("failed to lock test file");
+
+        assert!(FileLock::try_shared(file_name).unwrap().is_some());
+        assert!(FileLock::try_exclusive(file_name).unwrap().is_none());
+
+        let _ = std::fs::remove_file(shared.path());
+    }
+}
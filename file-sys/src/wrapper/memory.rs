@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{PathBuf, Path};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::error::{FileError, Operation};
+use super::format::Format;
+
+type Files = HashMap<Box<Path>, Vec<u8>>;
+
+static GLOBAL: OnceLock<MemoryStore> = OnceLock::new();
+
+/// the storage that a Memory wrapper saves bytes to
+pub trait Store {
+    /// returns the bytes stored for the path if any
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, IoError>;
+
+    /// replaces the bytes stored for the path
+    fn write(&self, path: &Path, bytes: Vec<u8>) -> Result<(), IoError>;
+}
+
+/// a shared map of virtual paths to the bytes saved for them
+///
+/// clones share the same map
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    files: Arc<Mutex<Files>>,
+}
+
+impl MemoryStore {
+    /// creates a new empty store
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// returns the store used by Memory wrappers that are not given one
+    pub fn global() -> Self {
+        GLOBAL.get_or_init(MemoryStore::new).clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Files> {
+        self.files.lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// returns a copy of the bytes stored for the path
+    pub fn get<P>(&self, path: P) -> Option<Vec<u8>>
+    where
+        P: AsRef<Path>
+    {
+        self.lock().get(path.as_ref()).cloned()
+    }
+
+    /// stores the bytes for the path returning the previous bytes
+    pub fn insert<P>(&self, path: P, bytes: Vec<u8>) -> Option<Vec<u8>>
+    where
+        P: Into<PathBuf>
+    {
+        self.lock().insert(path.into().into(), bytes)
+    }
+
+    /// removes the bytes stored for the path
+    pub fn remove<P>(&self, path: P) -> Option<Vec<u8>>
+    where
+        P: AsRef<Path>
+    {
+        self.lock().remove(path.as_ref())
+    }
+
+    /// checks if the path has bytes stored
+    pub fn contains<P>(&self, path: P) -> bool
+    where
+        P: AsRef<Path>
+    {
+        self.lock().contains_key(path.as_ref())
+    }
+
+    /// returns all paths with bytes stored
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.lock().keys().map(|path| path.to_path_buf()).collect()
+    }
+}
+
+impl Store for MemoryStore {
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, IoError> {
+        Ok(self.get(path))
+    }
+
+    fn write(&self, path: &Path, bytes: Vec<u8>) -> Result<(), IoError> {
+        self.insert(path, bytes);
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryStore")
+            .field("paths", &self.paths())
+            .finish()
+    }
+}
+
+/// a value that is saved to and loaded from a Store using the given Format
+///
+/// follows the same api as FileWrapped without touching the file system so
+/// the same encoding can be used in tests or where there is no file system.
+/// the global MemoryStore is used unless another store is given.
+pub struct Memory<T, F, S = MemoryStore> {
+    inner: T,
+    path: Box<Path>,
+    format: F,
+    store: S,
+}
+
+impl<T, F> Memory<T, F> {
+    /// creates a new Memory with the provided data and format
+    ///
+    /// nothing is stored until saved
+    pub fn with_format<P>(inner: T, path: P, format: F) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        Memory {
+            inner,
+            path: path.into().into(),
+            format,
+            store: MemoryStore::global(),
+        }
+    }
+}
+
+impl<T, F, S> Memory<T, F, S> {
+    /// sets the store that the value is saved to
+    pub fn with_store<N>(self, store: N) -> Memory<T, F, N> {
+        Memory {
+            inner: self.inner,
+            path: self.path,
+            format: self.format,
+            store,
+        }
+    }
+
+    /// returns the store that the value is saved to
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// returns the current virtual path for the wrapper
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// updates the current virtual path to the provided value
+    pub fn set_path<P>(&mut self, path: P)
+    where
+        P: Into<PathBuf>
+    {
+        self.path = path.into().into();
+    }
+
+    /// returns the format used for the bytes
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// returns a mutable format used for the bytes
+    pub fn format_mut(&mut self) -> &mut F {
+        &mut self.format
+    }
+
+    /// returns the inner value
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// returns a mutable inner value
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// consumes the struct returning the inner value
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, F> Memory<T, F>
+where
+    F: Default
+{
+    /// creates a new Memory with the provided data
+    ///
+    /// nothing is stored until saved
+    pub fn new<P>(inner: T, path: P) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        Self::with_format(inner, path, F::default())
+    }
+}
+
+/// returns the stored bytes of the path or a not found error
+fn read<F, S>(store: &S, path: &Path) -> Result<Vec<u8>, F::Error>
+where
+    F: Format,
+    S: Store
+{
+    let result = store.read(path)
+        .map_err(FileError::map(Operation::Read, path))?;
+
+    match result {
+        Some(bytes) => Ok(bytes),
+        None => Err(FileError::new(
+            Operation::Open,
+            path,
+            IoError::from(IoErrorKind::NotFound)
+        ).into()),
+    }
+}
+
+impl<T, F, S> Memory<T, F, S>
+where
+    T: Serialize,
+    F: Format,
+    S: Store
+{
+    /// encodes the inner value and stores it under the current path
+    pub fn save(&self) -> Result<(), F::Error> {
+        let mut buffer = Vec::new();
+
+        self.format.to_writer(&mut buffer, &self.inner)?;
+
+        self.store.write(&self.path, buffer)
+            .map_err(FileError::map(Operation::Write, &self.path))?;
+
+        Ok(())
+    }
+}
+
+impl<T, F, S> Memory<T, F, S>
+where
+    T: DeserializeOwned,
+    F: Format,
+    S: Store
+{
+    /// loads the bytes of the path from the store using the provided format
+    ///
+    /// returns a not found error if nothing is stored for the path
+    pub fn load_with_store<P>(given: P, format: F, store: S) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+        let bytes = read::<F, S>(&store, &path)?;
+        let inner = format.from_reader(bytes.as_slice())?;
+
+        Ok(Memory {
+            inner,
+            path,
+            format,
+            store,
+        })
+    }
+
+    /// decodes the bytes stored for the current path and replaces the inner
+    /// value
+    ///
+    /// the inner value is left unchanged if an error is returned
+    pub fn reload(&mut self) -> Result<(), F::Error> {
+        let bytes = read::<F, S>(&self.store, &self.path)?;
+
+        self.inner = self.format.from_reader(bytes.as_slice())?;
+
+        Ok(())
+    }
+}
+
+impl<T, F> Memory<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// loads the bytes of the path from the global store using the provided
+    /// format
+    pub fn load_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with_store(given, format, MemoryStore::global())
+    }
+}
+
+impl<T, F, S> Memory<T, F, S>
+where
+    T: DeserializeOwned,
+    F: Format + Default,
+    S: Store
+{
+    /// loads the bytes of the path from the given store
+    pub fn load_in<P>(given: P, store: S) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with_store(given, F::default(), store)
+    }
+}
+
+impl<T, F> Memory<T, F>
+where
+    T: DeserializeOwned,
+    F: Format + Default
+{
+    /// loads the bytes of the path from the global store
+    pub fn load<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with(given, F::default())
+    }
+}
+
+/// the format is not shown since it can contain sensitive data
+impl<T, F, S> fmt::Debug for Memory<T, F, S>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Memory")
+            .field("inner", &self.inner)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, F, S> AsRef<T> for Memory<T, F, S> {
+    fn as_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, F, S> AsMut<T> for Memory<T, F, S> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T, F, S> Clone for Memory<T, F, S>
+where
+    T: Clone,
+    F: Clone,
+    S: Clone
+{
+    fn clone(&self) -> Self {
+        Memory {
+            inner: self.inner.clone(),
+            path: self.path.clone(),
+            format: self.format.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use crate::wrapper::JsonFormat;
+
+    #[test]
+    fn base() {
+        let store = MemoryStore::new();
+        let wrapper = Memory::<_, JsonFormat>::new(vec![1u64, 2], "virtual.json")
+            .with_store(store.clone());
+
+        wrapper.save().expect("failed to save memory");
+
+        assert_eq!(store.get("virtual.json"), Some(b"[1,2]".to_vec()));
+        assert!(!Path::new("virtual.json").exists());
+
+        let mut and_back: Memory<Vec<u64>, JsonFormat> = Memory::load_in("virtual.json", store.clone())
+            .expect("failed to load memory");
+
+        assert_eq!(and_back.inner(), wrapper.inner());
+
+        store.insert("virtual.json", b"[3]".to_vec());
+        and_back.reload().expect("failed to reload memory");
+
+        assert_eq!(and_back.inner(), &vec![3]);
+
+        match Memory::<Vec<u64>, JsonFormat>::load_in("missing.json", store) {
+            Err(crate::wrapper::json::Error::File(err)) => {
+                assert_eq!(err.io().kind(), IoErrorKind::NotFound);
+            }
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+}
@@ -1,22 +1,162 @@
+pub mod error;
+pub mod lock;
+
+pub use error::{FileError, Operation};
+
+pub use lock::FileLock;
+
+#[cfg(feature = "serde")]
+mod atomic;
+
+#[cfg(feature = "serde")]
+pub use atomic::SyncMode;
+
+#[cfg(feature = "serde")]
+pub mod permissions;
+
+#[cfg(feature = "serde")]
+pub use permissions::Permissions;
+
+#[cfg(feature = "serde")]
+pub mod format;
+
+#[cfg(feature = "serde")]
+pub mod limit;
+
+#[cfg(feature = "serde")]
+pub use limit::Limited;
+
+#[cfg(feature = "serde")]
+pub use format::{Format, FileWrapped, ModifyError};
+
+#[cfg(feature = "serde")]
+pub mod autosave;
+
+#[cfg(feature = "serde")]
+pub use autosave::{AutoSave, WriteGuard};
+
+#[cfg(feature = "serde")]
+pub mod transaction;
+
+#[cfg(feature = "serde")]
+pub use transaction::Transaction;
+
+#[cfg(feature = "serde")]
+pub mod append_log;
+
+#[cfg(feature = "serde")]
+pub use append_log::AppendLog;
+
+#[cfg(feature = "serde")]
+pub mod convert;
+
+#[cfg(feature = "serde")]
+pub use convert::{convert, convert_with, ConvertError};
+
+#[cfg(feature = "serde")]
+pub mod dir_store;
+
+#[cfg(feature = "serde")]
+pub use dir_store::DirStore;
+
+#[cfg(feature = "serde")]
+pub mod journaled;
+
+#[cfg(feature = "serde")]
+pub use journaled::Journaled;
+
+#[cfg(feature = "serde")]
+pub mod sharded;
+
+#[cfg(feature = "serde")]
+pub use sharded::Sharded;
+
+#[cfg(feature = "serde")]
+pub mod memory;
+
+#[cfg(feature = "serde")]
+pub use memory::{Memory, MemoryStore, Store};
+
+#[cfg(feature = "wasm")]
+pub mod local_storage;
+
+#[cfg(feature = "wasm")]
+pub use local_storage::LocalStorage;
+
+#[cfg(all(feature = "notify", feature = "serde"))]
+pub mod watch;
+
+#[cfg(all(feature = "notify", feature = "serde"))]
+pub use watch::{FileWatcher, Change};
+
 #[cfg(all(feature = "binary", feature = "serde"))]
 pub mod binary;
 
 #[cfg(all(feature = "binary", feature = "serde"))]
-pub use binary::Binary;
+pub use binary::{Binary, BinaryFormat, IntEncoding, Endian};
+
+#[cfg(all(feature = "cbor", feature = "serde"))]
+pub mod cbor;
+
+#[cfg(all(feature = "cbor", feature = "serde"))]
+pub use cbor::{Cbor, CborFormat};
 
 #[cfg(all(feature = "json", feature = "serde"))]
 pub mod json;
 
 #[cfg(all(feature = "json", feature = "serde"))]
-pub use json::Json;
+pub use json::{Json, JsonFormat};
+
+#[cfg(all(feature = "json", feature = "serde"))]
+pub mod config_stack;
+
+#[cfg(all(feature = "json", feature = "serde"))]
+pub use config_stack::ConfigStack;
+
+#[cfg(all(feature = "postcard", feature = "serde"))]
+pub mod postcard;
+
+#[cfg(all(feature = "postcard", feature = "serde"))]
+pub use self::postcard::{Postcard, PostcardFormat};
+
+#[cfg(all(feature = "yaml", feature = "serde"))]
+pub mod yaml;
+
+#[cfg(all(feature = "yaml", feature = "serde"))]
+pub use yaml::{Yaml, YamlFormat};
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+mod chunked;
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+mod envelope;
+
+#[cfg(all(feature = "hardware-key", feature = "binary", feature = "serde"))]
+pub mod device;
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+mod header;
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+mod nonce;
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+pub mod rotation;
+
+#[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
+pub mod x25519;
 
 #[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
 pub mod encrypted;
 
 #[cfg(all(feature = "crypto", feature = "binary", feature = "serde"))]
-pub use encrypted::Encrypted;
+pub use encrypted::{Encrypted, EncryptedFormat, Cipher, Payload, Kdf, key_from_master, key_from_passphrase};
 
-#[cfg(test)]
+#[cfg(all(
+    test,
+    feature = "serde",
+    any(feature = "binary", feature = "cbor", feature = "json", feature = "postcard", feature = "yaml")
+))]
 pub(crate) mod test {
     pub fn create_test_file<P>(path: P) -> std::fs::File
     where
@@ -0,0 +1,92 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chacha20poly1305::Key;
+use sha2::{Digest, Sha256};
+
+use super::encrypted::Error;
+
+struct Log {
+    capacity: usize,
+    seen: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+}
+
+/// records the nonces used with each key to detect reuse
+///
+/// only a hash of the key and nonce is kept. once the capacity is reached
+/// the oldest entries are dropped. clones share the same log
+#[derive(Clone)]
+pub(crate) struct NonceCheck {
+    log: Arc<Mutex<Log>>,
+}
+
+impl NonceCheck {
+    pub(crate) fn new(capacity: usize) -> Self {
+        NonceCheck {
+            log: Arc::new(Mutex::new(Log {
+                capacity,
+                seen: HashSet::with_capacity(capacity),
+                order: VecDeque::with_capacity(capacity),
+            })),
+        }
+    }
+
+    /// records the nonce used with the key returning NonceReuse if it was
+    /// already recorded
+    pub(crate) fn record(&self, key: &Key, nonce: &[u8]) -> Result<(), Error> {
+        let entry: [u8; 32] = Sha256::new()
+            .chain_update(key)
+            .chain_update(nonce)
+            .finalize()
+            .into();
+        let mut log = self.log.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if log.capacity == 0 {
+            return Ok(());
+        }
+
+        if !log.seen.insert(entry) {
+            return Err(Error::NonceReuse);
+        }
+
+        if log.order.len() == log.capacity {
+            if let Some(oldest) = log.order.pop_front() {
+                log.seen.remove(&oldest);
+            }
+        }
+
+        log.order.push_back(entry);
+
+        Ok(())
+    }
+}
+
+/// records the nonce if a check is given
+pub(crate) fn record(check: Option<&NonceCheck>, key: &Key, nonce: &[u8]) -> Result<(), Error> {
+    match check {
+        Some(check) => check.record(key, nonce),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuse() {
+        let check = NonceCheck::new(2);
+        let key = Key::from([1; 32]);
+
+        check.record(&key, &[1]).unwrap();
+        check.record(&Key::from([2; 32]), &[1]).unwrap();
+
+        assert!(matches!(check.clone().record(&key, &[1]), Err(Error::NonceReuse)));
+
+        // the first entry was dropped once the capacity was reached
+        check.record(&key, &[2]).unwrap();
+        check.record(&key, &[1]).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::Error as IoError;
+
+/// the permissions given to files created by a wrapper
+///
+/// only applied when a file is created unless enforced. saving over an
+/// existing file keeps the permissions of that file. the mode is only used
+/// on unix and the hidden attribute is only used on windows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Permissions {
+    mode: Option<u32>,
+    readonly: bool,
+    hidden: bool,
+    enforce: bool,
+}
+
+impl Permissions {
+    /// creates permissions that leave the file as the os creates it
+    pub fn new() -> Self {
+        Permissions::default()
+    }
+
+    /// creates permissions that only allow the owner to read and write the
+    /// file
+    pub fn owner_only() -> Self {
+        Permissions::new().with_mode(0o600)
+    }
+
+    /// sets the unix mode of the file
+    ///
+    /// the mode is set exactly and is not affected by the umask
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// sets if the file is read only
+    pub fn with_readonly(mut self, readonly: bool) -> Self {
+        self.readonly = readonly;
+        self
+    }
+
+    /// sets if the file is hidden on windows
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// sets if the permissions are also applied when saving over an
+    /// existing file instead of keeping the permissions of that file
+    pub fn with_enforce(mut self, enforce: bool) -> Self {
+        self.enforce = enforce;
+        self
+    }
+
+    /// returns the unix mode if one was set
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    /// checks if the file is read only
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// checks if the file is hidden on windows
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// checks if the permissions are applied when saving over an existing
+    /// file
+    pub fn enforce(&self) -> bool {
+        self.enforce
+    }
+
+    /// updates the open options so the file is created with the permissions
+    ///
+    /// the unix mode given here is still masked by the umask so apply must be
+    /// called after the file is created
+    pub(crate) fn open_options(&self, options: &mut std::fs::OpenOptions) {
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            std::os::unix::fs::OpenOptionsExt::mode(options, mode);
+        }
+
+        #[cfg(windows)]
+        if self.hidden {
+            std::os::windows::fs::OpenOptionsExt::attributes(options, FILE_ATTRIBUTE_HIDDEN);
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        let _ = options;
+    }
+
+    /// updates the open options so the file is created with the permissions
+    ///
+    /// similar to the blocking open_options
+    #[cfg(feature = "tokio")]
+    pub(crate) fn open_options_async(&self, options: &mut tokio::fs::OpenOptions) {
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            options.mode(mode);
+        }
+
+        #[cfg(windows)]
+        if self.hidden {
+            options.attributes(FILE_ATTRIBUTE_HIDDEN);
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        let _ = options;
+    }
+
+    /// returns the permissions to set on a created file if they differ from
+    /// what the os gave it
+    fn resolve(&self, current: std::fs::Permissions) -> Option<std::fs::Permissions> {
+        #[allow(unused_mut)]
+        let mut rtn = current;
+        #[allow(unused_mut)]
+        let mut changed = false;
+
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            std::os::unix::fs::PermissionsExt::set_mode(&mut rtn, mode);
+            changed = true;
+        }
+
+        if self.readonly {
+            rtn.set_readonly(true);
+            changed = true;
+        }
+
+        if changed {
+            Some(rtn)
+        } else {
+            None
+        }
+    }
+
+    /// sets the permissions on a newly created file
+    pub(crate) fn apply(&self, file: &File) -> Result<(), IoError> {
+        if self.mode.is_none() && !self.readonly {
+            return Ok(());
+        }
+
+        let current = file.metadata()?.permissions();
+
+        if let Some(perms) = self.resolve(current) {
+            file.set_permissions(perms)?;
+        }
+
+        Ok(())
+    }
+
+    /// sets the permissions on a newly created file using tokio fs
+    ///
+    /// similar to the blocking apply
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn apply_async(&self, file: &tokio::fs::File) -> Result<(), IoError> {
+        if self.mode.is_none() && !self.readonly {
+            return Ok(());
+        }
+
+        let current = file.metadata().await?.permissions();
+
+        if let Some(perms) = self.resolve(current) {
+            file.set_permissions(perms).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+#[cfg(all(test, unix, feature = "json"))]
+mod test {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+    use crate::wrapper::Json;
+
+    #[test]
+    fn mode() {
+        let file_name = "test.permissions.json";
+        let _ = std::fs::remove_file(file_name);
+
+        let wrapper = Json::new(1u64, file_name)
+            .with_permissions(Permissions::new().with_mode(0o640));
+
+        wrapper.save().expect("failed to save json file");
+
+        let metadata = std::fs::metadata(file_name).expect("failed to read metadata");
+
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+
+        std::fs::set_permissions(file_name, std::fs::Permissions::from_mode(0o644))
+            .expect("failed to set permissions");
+
+        wrapper.save().expect("failed to save json file");
+
+        let metadata = std::fs::metadata(file_name).expect("failed to read metadata");
+
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o644);
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{Read, Write};
+use std::io::Error as IoError;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::ErrorKind;
+use super::error::FileError;
+use super::format::{Format, FileWrapped};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    File(FileError),
+    Postcard(postcard::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::File(e) => fmt::Display::fmt(e, f),
+            Error::Postcard(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::File(e) => Some(e),
+            Error::Postcard(e) => Some(e),
+        }
+    }
+}
+
+impl Error {
+    /// returns the general category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) => ErrorKind::Io,
+            Error::File(_) => ErrorKind::File,
+            Error::Postcard(_) => ErrorKind::Format,
+        }
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::File(err)
+    }
+}
+
+/// encodes values with postcard
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PostcardFormat;
+
+impl Format for PostcardFormat {
+    type Error = Error;
+
+    fn to_writer<W, T>(&self, mut writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized
+    {
+        let bytes = postcard::to_stdvec(value)
+            .map_err(Error::Postcard)?;
+
+        writer.write_all(bytes.as_slice())?;
+
+        Ok(())
+    }
+
+    fn from_reader<R, T>(&self, mut reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        let mut buffer = Vec::new();
+
+        reader.read_to_end(&mut buffer)?;
+
+        postcard::from_bytes(buffer.as_slice())
+            .map_err(Error::Postcard)
+    }
+}
+
+/// a value stored in a postcard file
+pub type Postcard<T> = FileWrapped<T, PostcardFormat>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wrapper;
+    use std::path::PathBuf;
+
+    #[test]
+    fn base() {
+        let file_name = "test.postcard";
+        let inner = usize::MAX;
+
+        wrapper::test::create_test_file(file_name);
+
+        let wrapper = Postcard::new(inner, file_name);
+
+        wrapper.save().expect("failed to save to postcard file");
+
+        let and_back: Postcard<usize> = Postcard::load(PathBuf::from(file_name))
+            .expect("failed to load postcard file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
+
+    #[test]
+    fn format() {
+        let file_name = "test.format.postcard";
+        let wrapper = Postcard::new((1u8, 300u16), file_name);
+
+        wrapper.save().expect("failed to save to postcard file");
+
+        let bytes = std::fs::read(file_name).expect("failed to read postcard file");
+
+        assert_eq!(bytes, vec![1, 0xac, 0x02], "integers are not varint encoded");
+    }
+}
@@ -0,0 +1,167 @@
+//! policies for rotating the data key of envelope encrypted files
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use chacha20poly1305::Key;
+
+use super::envelope;
+
+type Callback = Arc<dyn Fn(&DataKeyInfo) + Send + Sync>;
+
+/// how long a data key has been used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DataKeyInfo {
+    /// the number of saves that used the data key
+    pub saves: u32,
+    /// when the data key was created
+    ///
+    /// stored in the file with a precision of seconds
+    pub created: SystemTime,
+}
+
+/// when the data key of a file is replaced with a fresh one
+///
+/// without a policy every save uses a fresh data key. with a policy the
+/// data key is reused by saves until it has been used by the max number of
+/// saves or is older than the max age. the next save then uses a fresh data
+/// key and calls the callback with the info of the retired key
+#[derive(Clone, Default)]
+pub struct RotationPolicy {
+    max_saves: Option<u32>,
+    max_age: Option<Duration>,
+    callback: Option<Callback>,
+}
+
+impl RotationPolicy {
+    /// creates a policy that does not rotate the data key
+    pub fn new() -> Self {
+        RotationPolicy::default()
+    }
+
+    /// rotates the data key after it was used by the number of saves
+    ///
+    /// panics if saves is 0
+    pub fn with_max_saves(mut self, saves: u32) -> Self {
+        assert!(saves > 0, "max saves must be greater than 0");
+
+        self.max_saves = Some(saves);
+        self
+    }
+
+    /// rotates the data key once it is older than the age
+    pub fn with_max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// calls the callback with the info of the retired data key when it is
+    /// rotated
+    ///
+    /// only the number of saves and creation time are passed, the retired
+    /// key itself is not. the callback is called during the save that uses
+    /// the fresh key
+    pub fn on_rotate<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&DataKeyInfo) + Send + Sync + 'static
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// returns the number of saves a data key is used by
+    pub fn max_saves(&self) -> Option<u32> {
+        self.max_saves
+    }
+
+    /// returns how long a data key is used for
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// checks if the data key should be replaced
+    fn expired(&self, info: &DataKeyInfo, now: SystemTime) -> bool {
+        if self.max_saves.is_some_and(|max| info.saves >= max) {
+            return true;
+        }
+
+        // a creation time in the future counts as new
+        let age = now.duration_since(info.created).unwrap_or_default();
+
+        self.max_age.is_some_and(|max| age >= max)
+    }
+}
+
+impl fmt::Debug for RotationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RotationPolicy")
+            .field("max_saves", &self.max_saves)
+            .field("max_age", &self.max_age)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+struct Current {
+    key: Key,
+    info: DataKeyInfo,
+}
+
+/// the data key of the last save or load
+///
+/// clones start empty so formats cloned for other files do not reuse the
+/// data key
+#[derive(Default)]
+pub(crate) struct DataKeyCache {
+    current: Mutex<Option<Current>>,
+}
+
+impl DataKeyCache {
+    /// stores the data key read from a file
+    pub(crate) fn loaded(&self, key: Key, info: DataKeyInfo) {
+        *self.current.lock().unwrap_or_else(|err| err.into_inner()) = Some(Current { key, info });
+    }
+
+    /// returns the data key for the next save
+    ///
+    /// the current data key is reused unless the policy says it has expired
+    pub(crate) fn next(&self, policy: &RotationPolicy) -> (Key, DataKeyInfo) {
+        let now = SystemTime::now();
+        let mut current = self.current.lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        let retired = match current.as_mut() {
+            Some(current) if !policy.expired(&current.info, now) => {
+                current.info.saves = current.info.saves.saturating_add(1);
+
+                return (current.key, current.info);
+            }
+            Some(current) => Some(current.info),
+            None => None,
+        };
+
+        let info = DataKeyInfo {
+            saves: 1,
+            created: now,
+        };
+        let key = envelope::data_key();
+
+        *current = Some(Current { key, info });
+
+        drop(current);
+
+        if let (Some(retired), Some(callback)) = (retired, &policy.callback) {
+            callback(&retired);
+        }
+
+        (key, info)
+    }
+}
+
+impl Clone for DataKeyCache {
+    fn clone(&self) -> Self {
+        DataKeyCache::default()
+    }
+}
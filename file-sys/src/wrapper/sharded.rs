@@ -0,0 +1,653 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write, BufReader};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::{PathBuf, Path};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::atomic::{self, SyncMode};
+use super::error::{FileError, Operation};
+use super::format::Format;
+use super::limit::check_size;
+use super::permissions::Permissions;
+
+static SAVE_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// the manifest stores the id of the save and the length of every shard
+type Manifest = (u64, Vec<u64>);
+
+/// returns a new id for a save
+///
+/// the id is never 0 and is unlikely to match the id of a previous save
+fn save_id() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|since| since.as_nanos() as u64)
+        .unwrap_or_default();
+
+    nanos.wrapping_add(SAVE_COUNT.fetch_add(1, Ordering::Relaxed)).max(1)
+}
+
+/// returns the path of the shard at the given index for the save id
+fn shard_path(path: &Path, id: u64, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{:016x}.{:03}", id, index));
+
+    PathBuf::from(name)
+}
+
+/// returns the save id of a shard if the name is one of the shards for the
+/// manifest
+fn shard_id(manifest: &std::ffi::OsStr, name: &std::ffi::OsStr) -> Option<u64> {
+    let rest = name.to_str()?
+        .strip_prefix(manifest.to_str()?)?
+        .strip_prefix('.')?;
+    let (id, index) = rest.split_once('.')?;
+
+    if id.len() != 16 || index.len() < 3 || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    u64::from_str_radix(id, 16).ok()
+}
+
+/// the error returned when a shard does not match the length in the manifest
+fn shard_mismatch(path: &Path) -> FileError {
+    FileError::new(
+        Operation::Read,
+        path,
+        IoError::new(IoErrorKind::InvalidData, "shard does not match the manifest")
+    )
+}
+
+/// returns the total length of the shards listed in the manifest
+///
+/// a manifest with lengths that do not fit in a u64 is treated as invalid
+fn total_len(path: &Path, lens: &[u64]) -> Result<u64, FileError> {
+    lens.iter().try_fold(0u64, |total, len| total.checked_add(*len))
+        .ok_or_else(|| FileError::new(
+            Operation::Read,
+            path,
+            IoError::new(IoErrorKind::InvalidData, "shard lengths overflow")
+        ))
+}
+
+/// removes the shards of every save other than the given one
+///
+/// this includes shards left behind by a save that failed part way. errors
+/// are ignored since a shard left behind is not listed in the manifest so it
+/// is never read
+fn remove_stale(path: &Path, id: u64) {
+    let (Some(manifest), Some(parent)) = (path.file_name(), path.parent()) else {
+        return;
+    };
+    let dir = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        if shard_id(manifest, &entry.file_name()).is_some_and(|found| found != id) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// collects the encoded data into shards saving each one once it is full
+struct ShardWriter<'a> {
+    path: &'a Path,
+    id: u64,
+    shard_size: usize,
+    permissions: Permissions,
+    sync: SyncMode,
+    buffer: Vec<u8>,
+    lens: Vec<u64>,
+    error: Option<FileError>,
+}
+
+impl ShardWriter<'_> {
+    /// saves the buffer as the next shard
+    fn save_shard(&mut self) -> Result<(), FileError> {
+        let path = shard_path(self.path, self.id, self.lens.len());
+
+        atomic::save(&path, &self.permissions, self.sync, |writer| {
+            writer.write_all(&self.buffer)
+                .map_err(FileError::map(Operation::Write, &path))
+        })?;
+
+        self.lens.push(self.buffer.len() as u64);
+        self.buffer.clear();
+
+        Ok(())
+    }
+}
+
+impl Write for ShardWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.shard_size - self.buffer.len());
+
+        self.buffer.extend_from_slice(&buf[..len]);
+
+        if self.buffer.len() == self.shard_size {
+            if let Err(err) = self.save_shard() {
+                let rtn = IoError::new(err.io().kind(), err.to_string());
+
+                self.error = Some(err);
+
+                return Err(rtn);
+            }
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// reads the shards one after the other as a single stream
+struct ShardReader {
+    files: std::vec::IntoIter<File>,
+    current: Option<File>,
+}
+
+impl Read for ShardReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while let Some(file) = &mut self.current {
+            let read = file.read(buf)?;
+
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            self.current = self.files.next();
+        }
+
+        Ok(0)
+    }
+}
+
+/// a value split across multiple files
+///
+/// the encoded value is split into shards of at most shard_size bytes
+/// stored next to the manifest with the id of the save and the index
+/// appended to the name, e.g. "state.bin.0000018c2f3a9b10.000". the manifest
+/// is encoded with the same Format and lists the save id and the length of
+/// every shard. every save writes its shards under a new id and saves the
+/// manifest last so a save that fails part way leaves the manifest pointing
+/// at the complete shards of the previous save. shards of other saves are
+/// removed once the manifest is saved.
+pub struct Sharded<T, F> {
+    inner: T,
+    path: Box<Path>,
+    format: F,
+    shard_size: u64,
+    save_id: AtomicU64,
+}
+
+impl<T, F> Sharded<T, F> {
+    /// creates a new Sharded with the given manifest path, max shard size in
+    /// bytes and format
+    ///
+    /// panics if the shard size is 0
+    pub fn with_format<P>(inner: T, path: P, shard_size: u64, format: F) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        assert!(shard_size > 0, "shard size must be greater than 0");
+
+        Sharded {
+            inner,
+            path: path.into().into(),
+            format,
+            shard_size,
+            save_id: AtomicU64::new(0),
+        }
+    }
+
+    /// returns a reference to the inner value
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// returns a mutable reference to the inner value
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// returns the path of the manifest
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// returns the path of the shard at the given index from the last save
+    /// or load
+    ///
+    /// returns None if the value has not been saved or loaded
+    pub fn shard_path(&self, index: usize) -> Option<PathBuf> {
+        match self.save_id.load(Ordering::Relaxed) {
+            0 => None,
+            id => Some(shard_path(&self.path, id, index)),
+        }
+    }
+
+    /// returns the max size of a shard in bytes
+    pub fn shard_size(&self) -> u64 {
+        self.shard_size
+    }
+
+    /// updates the max size of a shard in bytes
+    ///
+    /// only used by the next save. panics if the shard size is 0
+    pub fn set_shard_size(&mut self, shard_size: u64) {
+        assert!(shard_size > 0, "shard size must be greater than 0");
+
+        self.shard_size = shard_size;
+    }
+
+    /// returns the format used for the manifest and shards
+    pub fn format(&self) -> &F {
+        &self.format
+    }
+
+    /// consumes the struct returning the inner value
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, F> Sharded<T, F>
+where
+    F: Default
+{
+    /// creates a new Sharded with the given manifest path and max shard size
+    /// in bytes
+    ///
+    /// panics if the shard size is 0
+    pub fn new<P>(inner: T, path: P, shard_size: u64) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        Self::with_format(inner, path, shard_size, F::default())
+    }
+}
+
+impl<T, F> Sharded<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// encodes the value into the shards and then saves the manifest
+    ///
+    /// only one shard is kept in memory at a time. the shards of previous
+    /// saves are removed once the manifest is saved
+    pub fn save(&self) -> Result<(), F::Error> {
+        let id = save_id();
+        let mut writer = ShardWriter {
+            path: &self.path,
+            id,
+            shard_size: usize::try_from(self.shard_size).unwrap_or(usize::MAX),
+            permissions: self.format.permissions(),
+            sync: self.format.sync_mode(),
+            buffer: Vec::new(),
+            lens: Vec::new(),
+            error: None,
+        };
+
+        if let Err(err) = self.format.to_writer(&mut writer, &self.inner) {
+            return match writer.error.take() {
+                Some(err) => Err(err.into()),
+                None => Err(err),
+            };
+        }
+
+        if !writer.buffer.is_empty() {
+            writer.save_shard()?;
+        }
+
+        atomic::save(
+            &self.path,
+            &self.format.permissions(),
+            self.format.sync_mode(),
+            |manifest| self.format.to_writer(manifest, &(id, &writer.lens))
+        )?;
+
+        self.save_id.store(id, Ordering::Relaxed);
+
+        remove_stale(&self.path, id);
+
+        Ok(())
+    }
+}
+
+impl<T, F> Sharded<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// reads the manifest at the given path and decodes the value from the
+    /// shards it lists using the provided format
+    ///
+    /// the shard size is set to the size of the first shard
+    pub fn load_with<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+        let (id, lens) = read_manifest(&path, &format)?;
+        let mut files = Vec::with_capacity(lens.len());
+
+        check_size(format.max_size(), &path, total_len(&path, &lens)?)?;
+
+        for (index, len) in lens.iter().enumerate() {
+            let shard = shard_path(&path, id, index);
+            let file = File::open(&shard)
+                .map_err(FileError::map(Operation::Open, &shard))?;
+            let metadata = file.metadata()
+                .map_err(FileError::map(Operation::Read, &shard))?;
+
+            if metadata.len() != *len {
+                return Err(shard_mismatch(&shard).into());
+            }
+
+            files.push(file);
+        }
+
+        let mut files = files.into_iter();
+        let reader = ShardReader {
+            current: files.next(),
+            files,
+        };
+
+        let inner = format.from_reader(BufReader::new(reader))?;
+
+        Ok(Sharded {
+            inner,
+            path,
+            format,
+            shard_size: shard_size(&lens),
+            save_id: AtomicU64::new(id),
+        })
+    }
+}
+
+impl<T, F> Sharded<T, F>
+where
+    T: DeserializeOwned,
+    F: Format + Default
+{
+    /// reads the manifest at the given path and decodes the value from the
+    /// shards it lists
+    ///
+    /// see load_with for details
+    pub fn load<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with(given, F::default())
+    }
+}
+
+/// returns the shard size to use for a loaded value
+fn shard_size(lens: &[u64]) -> u64 {
+    lens.first().copied().unwrap_or(u64::MAX).max(1)
+}
+
+/// reads the save id and the length of every shard from the manifest
+fn read_manifest<F>(path: &Path, format: &F) -> Result<Manifest, F::Error>
+where
+    F: Format
+{
+    let file = File::open(path)
+        .map_err(FileError::map(Operation::Open, path))?;
+
+    format.from_reader(BufReader::new(file))
+}
+
+#[cfg(feature = "tokio")]
+impl<T, F> Sharded<T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// encodes the value and saves the shards in parallel using tokio fs
+    ///
+    /// unlike save the entire encoded value is kept in memory. the manifest
+    /// is saved after every shard
+    pub async fn save_async(&self) -> Result<(), F::Error> {
+        let id = save_id();
+        let mut buffer = Vec::new();
+
+        self.format.to_writer(&mut buffer, &self.inner)?;
+
+        let permissions = self.format.permissions();
+        let sync = self.format.sync_mode();
+        let shard_size = usize::try_from(self.shard_size).unwrap_or(usize::MAX);
+
+        let lens: Vec<u64> = buffer.chunks(shard_size)
+            .map(|chunk| chunk.len() as u64)
+            .collect();
+
+        futures_util::future::try_join_all(
+            buffer.chunks(shard_size)
+                .enumerate()
+                .map(|(index, chunk)| {
+                    let path = shard_path(&self.path, id, index);
+
+                    async move {
+                        atomic::save_async(&path, &permissions, sync, chunk).await
+                    }
+                })
+        ).await?;
+
+        let mut manifest = Vec::new();
+
+        self.format.to_writer(&mut manifest, &(id, &lens))?;
+
+        atomic::save_async(&self.path, &permissions, sync, &manifest).await?;
+
+        self.save_id.store(id, Ordering::Relaxed);
+
+        remove_stale(&self.path, id);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T, F> Sharded<T, F>
+where
+    T: DeserializeOwned,
+    F: Format
+{
+    /// reads the manifest at the given path and the shards in parallel
+    /// using tokio fs with the provided format
+    ///
+    /// see load_with for details
+    pub async fn load_with_async<P>(given: P, format: F) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        let path: Box<Path> = given.into().into();
+        let manifest = tokio::fs::read(&path)
+            .await
+            .map_err(FileError::map(Operation::Read, &path))?;
+        let (id, lens): Manifest = format.from_reader(manifest.as_slice())?;
+
+        check_size(format.max_size(), &path, total_len(&path, &lens)?)?;
+
+        let shards = futures_util::future::try_join_all(
+            lens.iter().enumerate().map(|(index, len)| {
+                let shard = shard_path(&path, id, index);
+
+                async move {
+                    let bytes = tokio::fs::read(&shard)
+                        .await
+                        .map_err(FileError::map(Operation::Read, &shard))?;
+
+                    if bytes.len() as u64 != *len {
+                        return Err(shard_mismatch(&shard));
+                    }
+
+                    Ok(bytes)
+                }
+            })
+        ).await?;
+
+        let inner = format.from_reader(shards.concat().as_slice())?;
+
+        Ok(Sharded {
+            inner,
+            path,
+            format,
+            shard_size: shard_size(&lens),
+            save_id: AtomicU64::new(id),
+        })
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T, F> Sharded<T, F>
+where
+    T: DeserializeOwned,
+    F: Format + Default
+{
+    /// reads the manifest at the given path and the shards in parallel
+    /// using tokio fs
+    ///
+    /// see load_with for details
+    pub async fn load_async<P>(given: P) -> Result<Self, F::Error>
+    where
+        P: Into<PathBuf>
+    {
+        Self::load_with_async(given, F::default()).await
+    }
+}
+
+impl<T, F> fmt::Debug for Sharded<T, F>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sharded")
+            .field("inner", &self.inner)
+            .field("path", &self.path)
+            .field("shard_size", &self.shard_size)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use crate::wrapper::JsonFormat;
+
+    #[test]
+    fn base() {
+        let file_name = "test.sharded.json";
+        let data: Vec<u64> = (0..100).collect();
+
+        let wrapper: Sharded<_, JsonFormat> = Sharded::new(data.clone(), file_name, 64);
+        wrapper.save().expect("failed to save sharded file");
+
+        assert!(wrapper.shard_path(3).unwrap().exists());
+
+        let mut and_back: Sharded<Vec<u64>, JsonFormat> = Sharded::load(file_name)
+            .expect("failed to load sharded file");
+
+        assert_eq!(and_back.inner(), &data);
+        assert_eq!(and_back.shard_size(), 64);
+
+        and_back.inner_mut().truncate(2);
+        and_back.save().expect("failed to save sharded file");
+
+        assert!(!and_back.shard_path(1).unwrap().exists());
+
+        let and_back: Sharded<Vec<u64>, JsonFormat> = Sharded::load(file_name)
+            .expect("failed to load sharded file");
+
+        assert_eq!(and_back.inner(), &vec![0, 1]);
+
+        std::fs::write(and_back.shard_path(0).unwrap(), "[0,1,2]").expect("failed to write shard");
+
+        match Sharded::<Vec<u64>, JsonFormat>::load(file_name) {
+            Err(crate::wrapper::json::Error::File(err)) => {
+                assert_eq!(err.io().kind(), IoErrorKind::InvalidData);
+            }
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let file_name = "test.sharded_overflow.json";
+
+        std::fs::write(file_name, format!("[0,[{},1]]", u64::MAX))
+            .expect("failed to write manifest");
+
+        match Sharded::<Vec<u64>, JsonFormat>::load(file_name) {
+            Err(crate::wrapper::json::Error::File(err)) => {
+                assert_eq!(err.io().kind(), IoErrorKind::InvalidData);
+            }
+            result => panic!("unexpected load result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn torn_save() {
+        let file_name = "test.sharded_torn.json";
+        let data: Vec<u64> = (0..100).collect();
+
+        let wrapper: Sharded<_, JsonFormat> = Sharded::new(data.clone(), file_name, 64);
+        wrapper.save().expect("failed to save sharded file");
+
+        let first = wrapper.shard_path(0).unwrap();
+
+        // a save that stopped before the manifest leaves its shards behind
+        // under its own id
+        let torn = shard_path(Path::new(file_name), 1, 0);
+        std::fs::write(&torn, "[100,101,").expect("failed to write torn shard");
+
+        let and_back: Sharded<Vec<u64>, JsonFormat> = Sharded::load(file_name)
+            .expect("failed to load sharded file");
+
+        assert_eq!(and_back.inner(), &data);
+        assert_eq!(and_back.shard_path(0).unwrap(), first);
+
+        and_back.save().expect("failed to save sharded file");
+
+        assert!(!torn.exists());
+        assert!(!first.exists());
+        assert!(and_back.shard_path(0).unwrap().exists());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio() {
+        let file_name = "test.sharded_async.json";
+        let data: Vec<u64> = (0..100).collect();
+
+        let wrapper: Sharded<_, JsonFormat> = Sharded::new(data.clone(), file_name, 32);
+        wrapper.save_async().await.expect("failed to save sharded file");
+
+        let and_back: Sharded<Vec<u64>, JsonFormat> = Sharded::load_async(file_name)
+            .await
+            .expect("failed to load sharded file");
+
+        assert_eq!(and_back.inner(), &data);
+
+        let and_back: Sharded<Vec<u64>, JsonFormat> = Sharded::load(file_name)
+            .expect("failed to load sharded file");
+
+        assert_eq!(and_back.inner(), &data);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::Serialize;
+
+use super::format::{Format, FileWrapped};
+
+/// a copy of the inner value that replaces it only when committed
+///
+/// created by FileWrapped::transaction. dropping the transaction without
+/// committing discards every change made to the copy.
+pub struct Transaction<'a, T, F> {
+    wrapped: &'a mut FileWrapped<T, F>,
+    shadow: T,
+}
+
+impl<T, F> Transaction<'_, T, F>
+where
+    T: Serialize,
+    F: Format
+{
+    /// saves the changed value and swaps it in as the inner value
+    ///
+    /// if the save fails the inner value and file are left unchanged and the
+    /// changes are discarded
+    pub fn commit(mut self) -> Result<(), F::Error> {
+        std::mem::swap(self.wrapped.inner_mut(), &mut self.shadow);
+
+        if let Err(err) = self.wrapped.save() {
+            std::mem::swap(self.wrapped.inner_mut(), &mut self.shadow);
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// discards every change made to the copy
+    ///
+    /// same as dropping the transaction
+    pub fn rollback(self) {}
+}
+
+impl<T, F> FileWrapped<T, F>
+where
+    T: Serialize + Clone,
+    F: Format
+{
+    /// returns a transaction over a copy of the inner value
+    ///
+    /// the inner value and file are only updated when the transaction is
+    /// committed
+    pub fn transaction(&mut self) -> Transaction<'_, T, F> {
+        let shadow = self.inner().clone();
+
+        Transaction {
+            wrapped: self,
+            shadow,
+        }
+    }
+}
+
+impl<T, F> Deref for Transaction<'_, T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.shadow
+    }
+}
+
+impl<T, F> DerefMut for Transaction<'_, T, F> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.shadow
+    }
+}
+
+impl<T, F> fmt::Debug for Transaction<'_, T, F>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("shadow", &self.shadow)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use crate::wrapper::Json;
+
+    #[test]
+    fn commit_and_rollback() {
+        let file_name = "test.transaction.json";
+        let mut wrapper = Json::new(vec![1u64], file_name);
+
+        wrapper.save().expect("failed to save json file");
+
+        let mut transaction = wrapper.transaction();
+        transaction.push(2);
+        transaction.push(3);
+        transaction.rollback();
+
+        assert_eq!(wrapper.inner(), &vec![1]);
+
+        {
+            let mut transaction = wrapper.transaction();
+            transaction.push(4);
+        }
+
+        assert_eq!(wrapper.inner(), &vec![1]);
+
+        let mut transaction = wrapper.transaction();
+        transaction.push(5);
+        transaction.commit().expect("failed to commit transaction");
+
+        assert_eq!(wrapper.inner(), &vec![1, 5]);
+
+        let and_back: Json<Vec<u64>> = Json::load(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(and_back.inner(), &vec![1, 5]);
+
+        wrapper.set_path("test.transaction.missing/file.json");
+
+        let mut transaction = wrapper.transaction();
+        transaction.push(6);
+        transaction.commit().expect_err("committed to a missing directory");
+
+        assert_eq!(wrapper.inner(), &vec![1, 5]);
+    }
+}
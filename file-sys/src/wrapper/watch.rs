@@ -0,0 +1,170 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, RwLock, mpsc};
+
+use notify::{Watcher, RecommendedWatcher, RecursiveMode, EventKind};
+use notify::event::{ModifyKind, RenameMode};
+use serde::de::DeserializeOwned;
+
+use super::format::{Format, FileWrapped};
+
+pub use notify::Error;
+
+/// the kind of change seen for a watched file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// the file was created, written to or renamed into place
+    Modified,
+    /// the file was removed or renamed away
+    Removed,
+}
+
+/// watches a file for changes until dropped
+///
+/// the parent directory is watched instead of the file itself since saving
+/// renames a new file over the destination
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileWatcher")
+            .finish_non_exhaustive()
+    }
+}
+
+/// checks if the event changes the given file name
+fn classify(event: &notify::Event, file_name: &OsString) -> Option<Change> {
+    let matches = |index: usize| event.paths.get(index)
+        .and_then(|path| path.file_name())
+        .map(|name| name == file_name)
+        .unwrap_or(false);
+
+    match event.kind {
+        EventKind::Create(_) if matches(0) => Some(Change::Modified),
+        EventKind::Remove(_) if matches(0) => Some(Change::Removed),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) if matches(0) => Some(Change::Removed),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => if matches(1) {
+            Some(Change::Modified)
+        } else if matches(0) {
+            Some(Change::Removed)
+        } else {
+            None
+        },
+        EventKind::Modify(_) if matches(0) => Some(Change::Modified),
+        _ => None,
+    }
+}
+
+/// watches the given path calling the callback for every change seen
+///
+/// changes made by this process, including saves, are reported as well
+pub fn watch_path<C>(path: &Path, mut callback: C) -> Result<FileWatcher, Error>
+where
+    C: FnMut(Change) + Send + 'static
+{
+    let Some(file_name) = path.file_name().map(OsString::from) else {
+        return Err(Error::generic("path does not have a file name"));
+    };
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        if let Ok(event) = result {
+            if let Some(change) = classify(&event, &file_name) {
+                callback(change);
+            }
+        }
+    })?;
+
+    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+
+    Ok(FileWatcher {
+        _watcher: watcher,
+    })
+}
+
+impl<T, F> FileWrapped<T, F> {
+    /// watches the current path calling the callback for every change seen
+    ///
+    /// the watcher is not updated if the path changes
+    pub fn watch<C>(&self, callback: C) -> Result<FileWatcher, Error>
+    where
+        C: FnMut(Change) + Send + 'static
+    {
+        watch_path(self.path(), callback)
+    }
+
+    /// watches the current path sending every change seen on the returned
+    /// channel
+    pub fn watch_channel(&self) -> Result<(FileWatcher, mpsc::Receiver<Change>), Error> {
+        let (tx, rx) = mpsc::channel();
+
+        let watcher = self.watch(move |change| {
+            let _ = tx.send(change);
+        })?;
+
+        Ok((watcher, rx))
+    }
+}
+
+/// watches the path of the shared wrapper and reloads it when the file is
+/// modified
+///
+/// the result of every reload is passed to the callback. removals do not
+/// trigger a reload.
+pub fn reload_on_change<T, F, C>(
+    wrapped: Arc<RwLock<FileWrapped<T, F>>>,
+    mut callback: C
+) -> Result<FileWatcher, Error>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    F: Format + Send + Sync + 'static,
+    C: FnMut(Result<(), F::Error>) + Send + 'static
+{
+    let path = {
+        let reader = wrapped.read()
+            .unwrap_or_else(|err| err.into_inner());
+
+        reader.path().to_owned()
+    };
+
+    watch_path(&path, move |change| {
+        if change == Change::Modified {
+            let mut writer = wrapped.write()
+                .unwrap_or_else(|err| err.into_inner());
+
+            callback(writer.reload());
+        }
+    })
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+    use std::time::Duration;
+    use crate::wrapper::Json;
+
+    #[test]
+    fn channel() {
+        let file_name = "test.watch.json";
+        let wrapper = Json::new(1u64, file_name);
+
+        wrapper.save().expect("failed to save json file");
+
+        let (_watcher, rx) = wrapper.watch_channel()
+            .expect("failed to watch json file");
+
+        Json::new(2u64, file_name).save()
+            .expect("failed to save json file");
+
+        let change = rx.recv_timeout(Duration::from_secs(5))
+            .expect("no change seen for json file");
+
+        assert_eq!(change, Change::Modified);
+    }
+}
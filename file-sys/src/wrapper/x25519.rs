@@ -0,0 +1,105 @@
+//! x25519 keys for encrypting files to public key recipients
+
+use std::fmt;
+
+use chacha20poly1305::aead::OsRng;
+use x25519_dalek::StaticSecret;
+
+/// the length of a secret or public key
+pub const KEY_LEN: usize = 32;
+
+/// a secret key that can decrypt files encrypted to its public key
+#[derive(Clone)]
+pub struct SecretKey(StaticSecret);
+
+impl SecretKey {
+    /// creates a new random secret key
+    pub fn generate() -> Self {
+        SecretKey(StaticSecret::random_from_rng(OsRng))
+    }
+
+    /// creates a secret key from its bytes
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        SecretKey(StaticSecret::from(bytes))
+    }
+
+    /// returns the bytes of the secret key
+    pub fn to_bytes(&self) -> [u8; KEY_LEN] {
+        self.0.to_bytes()
+    }
+
+    /// returns the public key for the secret key
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(x25519_dalek::PublicKey::from(&self.0))
+    }
+
+    /// calculates the shared secret with the public key
+    ///
+    /// returns None if the public key is a low order point which would make
+    /// the shared secret all zeros
+    pub(crate) fn diffie_hellman(&self, public: &PublicKey) -> Option<[u8; KEY_LEN]> {
+        let shared = self.0.diffie_hellman(&public.0);
+
+        if shared.was_contributory() {
+            Some(shared.to_bytes())
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKey")
+            .finish_non_exhaustive()
+    }
+}
+
+/// a public key that files can be encrypted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey(x25519_dalek::PublicKey);
+
+impl PublicKey {
+    /// creates a public key from its bytes
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        PublicKey(x25519_dalek::PublicKey::from(bytes))
+    }
+
+    /// returns the bytes of the public key
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        self.0.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from_hex(hex: &str) -> [u8; KEY_LEN] {
+        let mut rtn = [0; KEY_LEN];
+
+        for (index, byte) in rtn.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).unwrap();
+        }
+
+        rtn
+    }
+
+    #[test]
+    fn diffie_hellman() {
+        // rfc 7748 section 6.1
+        let alice = SecretKey::from_bytes(from_hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"));
+        let bob = SecretKey::from_bytes(from_hex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb"));
+
+        assert_eq!(
+            alice.public_key().as_bytes(),
+            &from_hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+
+        let shared = from_hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+
+        assert_eq!(alice.diffie_hellman(&bob.public_key()), Some(shared));
+        assert_eq!(bob.diffie_hellman(&alice.public_key()), Some(shared));
+        assert_eq!(alice.diffie_hellman(&PublicKey::from_bytes([0; KEY_LEN])), None);
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{Read, Write};
+use std::io::Error as IoError;
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::ErrorKind;
+use super::error::FileError;
+use super::format::{Format, FileWrapped};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    File(FileError),
+    Yaml(serde_yaml::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => fmt::Display::fmt(e, f),
+            Error::File(e) => fmt::Display::fmt(e, f),
+            Error::Yaml(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::File(e) => Some(e),
+            Error::Yaml(e) => Some(e),
+        }
+    }
+}
+
+impl Error {
+    /// returns the general category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(_) => ErrorKind::Io,
+            Error::File(_) => ErrorKind::File,
+            Error::Yaml(_) => ErrorKind::Format,
+        }
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<FileError> for Error {
+    fn from(err: FileError) -> Self {
+        Error::File(err)
+    }
+}
+
+/// encodes values as yaml
+#[derive(Debug, Default, Clone, Copy)]
+pub struct YamlFormat;
+
+impl Format for YamlFormat {
+    type Error = Error;
+
+    fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<(), Self::Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized
+    {
+        serde_yaml::to_writer(writer, value)
+            .map_err(Error::Yaml)
+    }
+
+    fn from_reader<R, T>(&self, reader: R) -> Result<T, Self::Error>
+    where
+        R: Read,
+        T: DeserializeOwned
+    {
+        serde_yaml::from_reader(reader)
+            .map_err(Error::Yaml)
+    }
+}
+
+/// a value stored in a yaml file
+pub type Yaml<T> = FileWrapped<T, YamlFormat>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wrapper;
+    use std::path::PathBuf;
+
+    #[test]
+    fn base() {
+        let file_name = "test.yaml";
+        let inner = usize::MAX;
+
+        wrapper::test::create_test_file(file_name);
+
+        let wrapper = Yaml::new(inner, file_name);
+
+        wrapper.save().expect("failed to save to yaml file");
+
+        let and_back: Yaml<usize> = Yaml::load(PathBuf::from(file_name))
+            .expect("failed to load yaml file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
+
+    #[test]
+    fn reload() {
+        let file_name = "test.reload.yaml";
+        let mut wrapper = Yaml::new(1u64, file_name);
+
+        wrapper.save().expect("failed to save yaml file");
+
+        let other = Yaml::new(2u64, file_name);
+
+        other.save().expect("failed to save yaml file");
+
+        let inner = wrapper.inner() as *const u64;
+
+        wrapper.reload().expect("failed to reload yaml file");
+
+        assert_eq!(wrapper.inner(), &2);
+        assert_eq!(wrapper.inner() as *const u64, inner);
+    }
+}
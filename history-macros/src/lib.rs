@@ -0,0 +1,88 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{quote, format_ident};
+use syn::{parse_macro_input, Error, Fields, ItemStruct, Type};
+
+/// wraps struct fields marked with `#[versioned]` in a Versioned
+///
+/// for each marked field `name: T` the field type becomes
+/// `history::versioned::Versioned<T>` and the following methods are
+/// generated:
+///
+/// - `set_name(&mut self, value: T) -> u64` records a new version
+/// - `name_latest(&self) -> Option<&T>` returns the latest version
+///
+/// ```ignore
+/// #[history::versioned]
+/// struct Settings {
+///     #[versioned]
+///     theme: String,
+///     name: String,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn versioned(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(Span::call_site(), "versioned does not accept arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let mut item = parse_macro_input!(item as ItemStruct);
+
+    match expand(&mut item) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(item: &mut ItemStruct) -> Result<proc_macro2::TokenStream, Error> {
+    let Fields::Named(fields) = &mut item.fields else {
+        return Err(Error::new_spanned(&item.ident, "versioned requires a struct with named fields"));
+    };
+
+    let mut methods = Vec::new();
+
+    for field in fields.named.iter_mut() {
+        let before = field.attrs.len();
+
+        field.attrs.retain(|attr| !attr.path().is_ident("versioned"));
+
+        if field.attrs.len() == before {
+            continue;
+        }
+
+        let Some(ident) = field.ident.clone() else {
+            continue;
+        };
+        let vis = field.vis.clone();
+        let inner: Type = field.ty.clone();
+        let set_ident = format_ident!("set_{}", ident);
+        let latest_ident = format_ident!("{}_latest", ident);
+
+        field.ty = syn::parse_quote!(::history::versioned::Versioned<#inner>);
+
+        methods.push(quote! {
+            /// records a new version of the field returning the version number
+            #vis fn #set_ident(&mut self, value: #inner) -> u64 {
+                self.#ident.update(value)
+            }
+
+            /// returns the latest version of the field
+            #vis fn #latest_ident(&self) -> ::core::option::Option<&#inner> {
+                self.#ident.latest()
+            }
+        });
+    }
+
+    let name = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+
+    Ok(quote! {
+        #item
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    })
+}
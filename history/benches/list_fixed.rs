@@ -1,5 +1,5 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BatchSize};
-use rust_containers::list::fixed::Fixed;
+use criterion::{criterion_group, criterion_main, Criterion, BatchSize};
+use history::list::fixed::Fixed;
 
 fn fixed_iter_10(c: &mut Criterion) {
     let size_10: [usize; 10] = std::array::from_fn(|v| v);
@@ -0,0 +1,60 @@
+use std::thread;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use history::versioned::sync::RwVersioned;
+
+const THREADS: u64 = 4;
+const UPDATES: u64 = 1000;
+
+fn rw_versioned_update(c: &mut Criterion) {
+    c.bench_function("rw_versioned update 4x1000 threads", |b| b.iter(|| {
+        let versioned = RwVersioned::new();
+
+        thread::scope(|scope| {
+            for t in 0..THREADS {
+                let versioned = &versioned;
+
+                scope.spawn(move || {
+                    for v in 0..UPDATES {
+                        versioned.update(t * UPDATES + v).unwrap();
+                    }
+                });
+            }
+        });
+
+        versioned
+    }));
+}
+
+fn rw_versioned_update_count(c: &mut Criterion) {
+    c.bench_function("rw_versioned update 2x1000 with 2 count readers", |b| b.iter(|| {
+        let versioned = RwVersioned::new();
+
+        thread::scope(|scope| {
+            for t in 0..THREADS / 2 {
+                let versioned = &versioned;
+
+                scope.spawn(move || {
+                    for v in 0..UPDATES {
+                        versioned.update(t * UPDATES + v).unwrap();
+                    }
+                });
+
+                scope.spawn(move || {
+                    for _ in 0..UPDATES {
+                        black_box(versioned.count());
+                    }
+                });
+            }
+        });
+
+        versioned
+    }));
+}
+
+criterion_group!(
+    benches,
+    rw_versioned_update,
+    rw_versioned_update_count,
+);
+criterion_main!(benches);
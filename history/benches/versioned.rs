@@ -0,0 +1,63 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, BatchSize};
+use history::versioned::Versioned;
+
+fn filled(size: u64) -> Versioned<u64> {
+    let mut versioned = Versioned::new();
+
+    for v in 0..size {
+        versioned.update(v);
+    }
+
+    versioned
+}
+
+fn versioned_update(c: &mut Criterion) {
+    c.bench_function("versioned update 1000", |b| b.iter_batched_ref(
+        Versioned::new,
+        |versioned| {
+            for v in 0..1000u64 {
+                versioned.update(v);
+            }
+        },
+        BatchSize::SmallInput
+    ));
+}
+
+fn versioned_get(c: &mut Criterion) {
+    let versioned = filled(10_000);
+
+    c.bench_function("versioned get 10000", |b| b.iter(|| {
+        for v in 0..10_000u64 {
+            black_box(versioned.get(&v));
+        }
+    }));
+
+    c.bench_function("versioned latest 10000", |b| b.iter(|| {
+        black_box(versioned.latest());
+    }));
+}
+
+fn versioned_iter(c: &mut Criterion) {
+    let versioned = filled(10_000);
+
+    c.bench_function("versioned iter 10000", |b| b.iter(|| {
+        for pair in versioned.iter() {
+            black_box(pair);
+        }
+    }));
+}
+
+fn versioned_remove(c: &mut Criterion) {
+    c.bench_function("versioned remove oldest 1000", |b| b.iter_batched_ref(
+        || filled(1000),
+        |versioned| {
+            for v in 0..1000u64 {
+                versioned.remove(&v);
+            }
+        },
+        BatchSize::SmallInput
+    ));
+}
+
+criterion_group!(benches, versioned_update, versioned_get, versioned_iter, versioned_remove);
+criterion_main!(benches);
@@ -1,3 +1,45 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod list;
 
 pub mod versioned;
+
+#[cfg(feature = "macros")]
+pub use history_macros::versioned;
+
+// allows the macros to refer to ::history from within the crate tests
+#[cfg(all(test, feature = "macros"))]
+extern crate self as history;
+
+#[cfg(all(test, feature = "macros"))]
+mod test {
+    use crate::versioned::Versioned;
+
+    #[crate::versioned]
+    #[derive(Default)]
+    struct Settings {
+        #[versioned]
+        theme: String,
+        name: String,
+    }
+
+    #[test]
+    fn versioned_macro() {
+        let mut settings = Settings {
+            name: String::from("settings"),
+            ..Default::default()
+        };
+
+        settings.set_theme(String::from("light"));
+        let version = settings.set_theme(String::from("dark"));
+
+        let history: &Versioned<String> = &settings.theme;
+
+        assert_eq!(version, 1);
+        assert_eq!(history.len(), 2);
+        assert_eq!(settings.theme_latest().map(String::as_str), Some("dark"));
+        assert_eq!(settings.name, "settings", "unmarked field was changed");
+    }
+}
@@ -19,7 +19,7 @@ impl<T, const N: usize> Fixed<T, N> {
     /// creates empty Fixed
     pub fn new() -> Self {
         Fixed {
-            list: std::array::from_fn(|_| None),
+            list: core::array::from_fn(|_| None),
             next: 0,
             oldest: 0,
             stored: 0,
@@ -154,7 +154,7 @@ impl<T, const N: usize> Fixed<T, N> {
     }
 }
 
-impl<T, const N: usize> std::default::Default for Fixed<T, N> {
+impl<T, const N: usize> core::default::Default for Fixed<T, N> {
     #[inline]
     fn default() -> Self {
         Fixed::new()
@@ -175,11 +175,11 @@ where
     }
 }
 
-impl<T, const N: usize> std::fmt::Debug for Fixed<T, N>
+impl<T, const N: usize> core::fmt::Debug for Fixed<T, N>
 where
-    T: std::fmt::Debug
+    T: core::fmt::Debug
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Fixed")
             .field("list", &self.list)
             .field("next", &self.next)
@@ -256,11 +256,11 @@ impl<'a, T, const N: usize> Clone for FixedIter<'a, T, N> {
     }
 }
 
-impl<'a, T, const N: usize> std::fmt::Debug for FixedIter<'a, T, N>
+impl<'a, T, const N: usize> core::fmt::Debug for FixedIter<'a, T, N>
 where
-    T: std::fmt::Debug
+    T: core::fmt::Debug
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("FixedIter")
             .field("working", &self.working)
             .field("backward", &self.backward)
@@ -271,6 +271,9 @@ where
     }
 }
 
+#[cfg(feature = "serde")]
+use alloc::vec::Vec;
+
 #[cfg(feature = "serde")]
 use serde::{
     ser::{
@@ -334,7 +337,7 @@ where
                 impl<'de> Visitor<'de> for KeyFieldVisitor {
                     type Value = KeyField;
 
-                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                         formatter.write_str("'list' for 'index'")
                     }
 
@@ -357,7 +360,7 @@ where
         }
 
         struct FixedVisitor<T, const N: usize> {
-            _type: std::marker::PhantomData<T>
+            _type: core::marker::PhantomData<T>
         }
 
         impl<'de, T, const N: usize> Visitor<'de> for FixedVisitor<T, N>
@@ -366,7 +369,7 @@ where
         {
             type Value = Fixed<T, N>;
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                 formatter.write_str("struct Fixed")
             }
 
@@ -448,7 +451,7 @@ where
             "Fixed",
             STRUCT_FIELDS,
             FixedVisitor {
-                _type: std::marker::PhantomData
+                _type: core::marker::PhantomData
             }
         )
     }
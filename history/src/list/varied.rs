@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 // this is not as efficient as Fixed and you should probably use that instead
 // if your buffer is a fixed size
 pub struct Varied<T> {
@@ -47,7 +49,7 @@ impl<T> Varied<T> {
 
     pub fn push(&mut self, mut v: T) -> Option<T> {
         if self.list.len() == self.list.capacity() {
-            std::mem::swap(&mut self.list[self.index], &mut v);
+            core::mem::swap(&mut self.list[self.index], &mut v);
 
             self.index = (self.index + 1) % self.list.len();
 
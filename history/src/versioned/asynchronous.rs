@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+
+/// stores changes to a given value and applies a counted number to each
+/// update using the async locks from tokio
+///
+/// mirrors RwVersioned but waiting on a lock will yield to the runtime
+/// instead of blocking the thread. tokio locks cannot be poisoned so none of
+/// the methods return errors
+pub struct AsyncVersioned<T> {
+    store: RwLock<BTreeMap<u64, T>>,
+    count: Mutex<u64>,
+}
+
+impl<T> AsyncVersioned<T> {
+    /// creates an empty versioned struct
+    pub fn new() -> Self {
+        AsyncVersioned {
+            store: RwLock::new(BTreeMap::new()),
+            count: Mutex::new(0),
+        }
+    }
+
+    /// returns the next version number to use
+    pub async fn count(&self) -> u64 {
+        *self.count.lock().await
+    }
+
+    /// returns read guard to current store
+    pub async fn store(&self) -> RwLockReadGuard<'_, BTreeMap<u64, T>> {
+        self.store.read().await
+    }
+
+    /// updates the value returning the version number used
+    ///
+    /// count will be locked first and incremented once the store has been
+    /// updated
+    pub async fn update(&self, value: T) -> u64 {
+        let mut count_lock = self.count.lock().await;
+        let new_version = *count_lock;
+
+        self.store.write().await.insert(new_version, value);
+
+        *count_lock += 1;
+
+        new_version
+    }
+
+    /// drops the desired version returning the value found
+    ///
+    /// only locks the store
+    pub async fn remove(&self, version: &u64) -> Option<T> {
+        self.store.write().await.remove(version)
+    }
+
+    /// returns a reference to the desired version
+    ///
+    /// the returned guard holds the read lock on the store so writers will
+    /// wait until it is dropped
+    pub async fn get(&self, version: &u64) -> Option<RwLockReadGuard<'_, T>> {
+        RwLockReadGuard::try_map(self.store.read().await, |store| store.get(version)).ok()
+    }
+
+    /// returns the latest version of the value
+    pub async fn latest(&self) -> Option<RwLockReadGuard<'_, T>> {
+        RwLockReadGuard::try_map(self.store.read().await, |store| {
+            store.last_key_value().map(|(_, v)| v)
+        }).ok()
+    }
+
+    /// returns the latest version of the value along with the version number
+    pub async fn latest_version(&self) -> Option<(u64, RwLockReadGuard<'_, T>)> {
+        let reader = self.store.read().await;
+        let version = reader.last_key_value().map(|(k, _)| *k)?;
+
+        RwLockReadGuard::try_map(reader, |store| store.get(&version))
+            .ok()
+            .map(|value| (version, value))
+    }
+
+    /// calls the given function with a reference to the desired version
+    ///
+    /// the read lock is only held while the function runs
+    pub async fn with_version<F, R>(&self, version: &u64, f: F) -> Option<R>
+    where
+        F: FnOnce(&T) -> R
+    {
+        self.store.read().await.get(version).map(f)
+    }
+
+    /// calls the given function with the latest version number and value
+    ///
+    /// the read lock is only held while the function runs
+    pub async fn with_latest<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&u64, &T) -> R
+    {
+        self.store.read().await.last_key_value().map(|(k, v)| f(k, v))
+    }
+}
+
+impl<T> std::default::Default for AsyncVersioned<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for AsyncVersioned<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncVersioned")
+            .field("store", &self.store)
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn update_get() {
+        let versioned: AsyncVersioned<u64> = AsyncVersioned::new();
+        versioned.update(1).await;
+        let version = versioned.update(2).await;
+        versioned.update(3).await;
+
+        assert_eq!(versioned.get(&version).await.as_deref(), Some(&2));
+        assert_eq!(versioned.latest().await.as_deref(), Some(&3));
+        assert_eq!(versioned.count().await, 3);
+
+        {
+            let (latest, value) = versioned.latest_version().await
+                .expect("failed to find latest version");
+
+            assert_eq!((latest, *value), (2, 3));
+        }
+
+        assert_eq!(versioned.remove(&version).await, Some(2));
+        assert!(versioned.get(&version).await.is_none());
+        assert_eq!(versioned.with_latest(|k, v| k + v).await, Some(5));
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use sha2::{Sha256, Digest as _};
+
+use super::Versioned;
+
+/// the sha256 hash stored with each version
+pub type Digest = [u8; 32];
+
+/// the hash used as the previous hash of the first version in a chain
+pub const GENESIS: Digest = [0; 32];
+
+/// possible errors from methods in Chained
+#[derive(Debug)]
+pub enum Error {
+    /// the value could not be serialized for hashing
+    Serialize(bincode::Error),
+    /// the stored hash for the given version does not match the calculated
+    /// hash
+    Mismatch(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Serialize(e) => fmt::Display::fmt(e, f),
+            Error::Mismatch(version) => write!(f, "hash mismatch for version {}", version),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Serialize(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+/// calculates the hash for a version from the previous hash, the version
+/// number and the bincode serialized value
+fn hash_version<T>(prev: &Digest, version: u64, value: &T) -> Result<Digest, Error>
+where
+    T: Serialize
+{
+    let serialized = bincode::serialize(value)
+        .map_err(Error::Serialize)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(prev);
+    hasher.update(version.to_le_bytes());
+    hasher.update(serialized);
+
+    Ok(hasher.finalize().into())
+}
+
+/// a Versioned store where each version records a hash of the previous
+/// version's hash and its own serialized value
+///
+/// values cannot be removed once stored since that would break the chain.
+/// any edits made to the persisted data will be detected by verify_chain
+pub struct Chained<T> {
+    versioned: Versioned<(Digest, T)>,
+}
+
+impl<T> Chained<T> {
+    /// creates an empty chained struct
+    pub fn new() -> Self {
+        Chained {
+            versioned: Versioned::new(),
+        }
+    }
+
+    /// returns the underlying versioned store of hashes and values
+    pub fn versioned(&self) -> &Versioned<(Digest, T)> {
+        &self.versioned
+    }
+
+    /// consumes the struct returning the underlying versioned store
+    pub fn into_versioned(self) -> Versioned<(Digest, T)> {
+        self.versioned
+    }
+
+    /// returns next version number to use
+    pub fn count(&self) -> &u64 {
+        self.versioned.count()
+    }
+
+    /// returns total stored values
+    pub fn len(&self) -> usize {
+        self.versioned.len()
+    }
+
+    /// checks if there are no stored values
+    pub fn is_empty(&self) -> bool {
+        self.versioned.is_empty()
+    }
+
+    /// returns a reference to the desired version
+    pub fn get(&self, version: &u64) -> Option<&T> {
+        self.versioned.get(version).map(|(_, v)| v)
+    }
+
+    /// returns the hash recorded for the desired version
+    pub fn hash(&self, version: &u64) -> Option<&Digest> {
+        self.versioned.get(version).map(|(h, _)| h)
+    }
+
+    /// returns the latest version of the value
+    pub fn latest(&self) -> Option<&T> {
+        self.versioned.latest().map(|(_, v)| v)
+    }
+
+    /// returns the hash of the latest version or GENESIS if empty
+    pub fn head(&self) -> &Digest {
+        self.versioned.latest()
+            .map(|(h, _)| h)
+            .unwrap_or(&GENESIS)
+    }
+
+    /// returns an iterator of version numbers, hashes and values
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &Digest, &T)> {
+        self.versioned.iter().map(|(k, (h, v))| (k, h, v))
+    }
+}
+
+impl<T> Chained<T>
+where
+    T: Serialize
+{
+    /// updates the value returning the version number used
+    ///
+    /// the hash is calculated from the current head and the serialized value
+    pub fn update(&mut self, value: T) -> Result<u64, Error> {
+        let hash = hash_version(self.head(), *self.versioned.count(), &value)?;
+
+        Ok(self.versioned.update((hash, value)))
+    }
+
+    /// recalculates every hash in the chain from GENESIS
+    ///
+    /// returns the first version that does not match its recorded hash
+    pub fn verify_chain(&self) -> Result<(), Error> {
+        let mut prev = &GENESIS;
+
+        for (version, (hash, value)) in self.versioned.iter() {
+            let check = hash_version(prev, *version, value)?;
+
+            if check != *hash {
+                return Err(Error::Mismatch(*version));
+            }
+
+            prev = hash;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> std::default::Default for Chained<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Chained<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Chained")
+            .field("versioned", &self.versioned)
+            .finish()
+    }
+}
+
+impl<T> Clone for Chained<T>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        Chained {
+            versioned: self.versioned.clone(),
+        }
+    }
+}
+
+impl<T> Serialize for Chained<T>
+where
+    T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        self.versioned.serialize(serializer)
+    }
+}
+
+/// the chain is not verified when deserializing, call verify_chain after
+/// loading to check for modifications
+impl<'de, T> Deserialize<'de> for Chained<T>
+where
+    T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        Ok(Chained {
+            versioned: Versioned::deserialize(deserializer)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify() {
+        let mut chained: Chained<u64> = Chained::new();
+        chained.update(5).unwrap();
+        chained.update(3).unwrap();
+        chained.update(7).unwrap();
+
+        chained.verify_chain().expect("failed to verify chain");
+
+        let to_json = serde_json::to_string(&chained)
+            .expect("failed to serialize to json string");
+        let and_back: Chained<u64> = serde_json::from_str(&to_json)
+            .expect("failed to deserialize from json string");
+
+        and_back.verify_chain().expect("failed to verify loaded chain");
+    }
+
+    #[test]
+    fn tampered() {
+        let mut chained: Chained<u64> = Chained::new();
+        chained.update(5).unwrap();
+        let edit = chained.update(3).unwrap();
+        chained.update(7).unwrap();
+
+        if let Some((_, value)) = chained.versioned.store.get_mut(&edit) {
+            *value = 4;
+        }
+
+        match chained.verify_chain() {
+            Err(Error::Mismatch(version)) => assert_eq!(version, edit),
+            result => panic!("unexpected verify result: {:?}", result),
+        }
+    }
+}
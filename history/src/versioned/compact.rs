@@ -0,0 +1,161 @@
+//! alternate serde representation for Versioned
+//!
+//! instead of a struct containing the full store map and the count, the
+//! values are written as an ordered sequence of `(version, value)` pairs. the
+//! count is not stored and will be derived from the largest version found
+//! when deserializing.
+//!
+//! intended to be used with `#[serde(with = "history::versioned::compact")]`
+//! or by calling [`serialize`] / [`deserialize`] directly. the
+//! [`human_readable`] module will only use the compact form for human
+//! readable formats (json, yaml, etc) and fall back to the default
+//! representation otherwise.
+//!
+//! since the count is derived, versions that were removed from the end of
+//! the store will be handed out again after a round trip.
+
+use core::fmt;
+
+use serde::ser::{Serializer, SerializeSeq};
+use serde::de::{self, Deserialize, Deserializer, Visitor, SeqAccess};
+
+use super::Versioned;
+
+/// serializes the given Versioned as a sequence of `(version, value)` pairs
+pub fn serialize<T, S>(versioned: &Versioned<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: serde::Serialize,
+    S: Serializer
+{
+    let mut seq = serializer.serialize_seq(Some(versioned.store.len()))?;
+
+    for pair in versioned.store.iter() {
+        seq.serialize_element(&pair)?;
+    }
+
+    seq.end()
+}
+
+/// deserializes a sequence of `(version, value)` pairs into a Versioned
+///
+/// versions must be in ascending order. the count will be set to the largest
+/// version + 1 or 0 if the sequence is empty
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Versioned<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>
+{
+    struct PairsVisitor<T> {
+        _type: core::marker::PhantomData<T>
+    }
+
+    impl<'de, T> Visitor<'de> for PairsVisitor<T>
+    where
+        T: Deserialize<'de>
+    {
+        type Value = Versioned<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of ascending (version, value) pairs")
+        }
+
+        fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+        where
+            V: SeqAccess<'de>
+        {
+            let mut rtn = Versioned::new();
+
+            while let Some((version, value)) = seq.next_element::<(u64, T)>()? {
+                if version < rtn.count {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Unsigned(version),
+                        &"a version larger than the previous version"
+                    ));
+                }
+
+                rtn.count = version.checked_add(1)
+                    .ok_or_else(|| de::Error::invalid_value(
+                        de::Unexpected::Unsigned(version),
+                        &"a version less than u64::MAX"
+                    ))?;
+                rtn.store.insert(version, value);
+            }
+
+            Ok(rtn)
+        }
+    }
+
+    deserializer.deserialize_seq(PairsVisitor {
+        _type: core::marker::PhantomData
+    })
+}
+
+/// selects the compact form only for human readable formats
+///
+/// non human readable formats will use the default struct representation
+pub mod human_readable {
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    use super::Versioned;
+
+    pub fn serialize<T, S>(versioned: &Versioned<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer
+    {
+        if serializer.is_human_readable() {
+            super::serialize(versioned, serializer)
+        } else {
+            versioned.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Versioned<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>
+    {
+        if deserializer.is_human_readable() {
+            super::deserialize(deserializer)
+        } else {
+            Versioned::deserialize(deserializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serde_json() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(5);
+        let drop = versioned.update(3);
+        versioned.update(7);
+
+        versioned.remove(&drop);
+
+        let mut to_json = Vec::new();
+        serialize(&versioned, &mut serde_json::Serializer::new(&mut to_json))
+            .expect("failed to serialize to json string");
+
+        assert_eq!(to_json, b"[[0,5],[2,7]]", "unexpected compact json");
+
+        let and_back: Versioned<u64> = deserialize(
+            &mut serde_json::Deserializer::from_slice(&to_json)
+        ).expect("failed to deserialize from json string");
+
+        assert_eq!(versioned.store, and_back.store, "store values are not equal");
+        assert_eq!(versioned.count, and_back.count, "count values are not equal");
+    }
+
+    #[test]
+    fn out_of_order() {
+        let result: Result<Versioned<u64>, _> = deserialize(
+            &mut serde_json::Deserializer::from_str("[[2,5],[1,7]]")
+        );
+
+        assert!(result.is_err(), "out of order versions were accepted");
+    }
+}
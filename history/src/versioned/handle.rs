@@ -0,0 +1,223 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Weak};
+
+use super::sync::RwVersioned;
+
+/// a shared handle to an RwVersioned
+///
+/// cloning the handle only increments the reference count. use downgrade to
+/// get a WeakVersionedHandle that will not keep the history alive.
+pub struct VersionedHandle<T> {
+    inner: Arc<RwVersioned<T>>,
+}
+
+impl<T> VersionedHandle<T> {
+    /// creates a handle to a new empty RwVersioned
+    pub fn new() -> Self {
+        VersionedHandle {
+            inner: Arc::new(RwVersioned::new()),
+        }
+    }
+
+    /// creates a weak handle to the same RwVersioned
+    pub fn downgrade(&self) -> WeakVersionedHandle<T> {
+        WeakVersionedHandle {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
+    /// returns total strong handles to the RwVersioned
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+
+    /// returns total weak handles to the RwVersioned
+    pub fn weak_count(&self) -> usize {
+        Arc::weak_count(&self.inner)
+    }
+
+    /// checks if both handles point to the same RwVersioned
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// returns a reference to the underlying Arc
+    pub fn as_arc(&self) -> &Arc<RwVersioned<T>> {
+        &self.inner
+    }
+
+    /// returns the underlying Arc
+    pub fn into_arc(self) -> Arc<RwVersioned<T>> {
+        self.inner
+    }
+
+    /// returns the RwVersioned if this is the only strong handle
+    ///
+    /// the handle is returned if there are other strong handles
+    pub fn try_unwrap(self) -> Result<RwVersioned<T>, Self> {
+        Arc::try_unwrap(self.inner)
+            .map_err(|inner| VersionedHandle { inner })
+    }
+}
+
+impl<T> Deref for VersionedHandle<T> {
+    type Target = RwVersioned<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> AsRef<RwVersioned<T>> for VersionedHandle<T> {
+    #[inline]
+    fn as_ref(&self) -> &RwVersioned<T> {
+        &self.inner
+    }
+}
+
+impl<T> From<RwVersioned<T>> for VersionedHandle<T> {
+    fn from(versioned: RwVersioned<T>) -> Self {
+        VersionedHandle {
+            inner: Arc::new(versioned),
+        }
+    }
+}
+
+impl<T> From<Arc<RwVersioned<T>>> for VersionedHandle<T> {
+    fn from(inner: Arc<RwVersioned<T>>) -> Self {
+        VersionedHandle { inner }
+    }
+}
+
+impl<T> Default for VersionedHandle<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for VersionedHandle<T> {
+    fn clone(&self) -> Self {
+        VersionedHandle {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> fmt::Debug for VersionedHandle<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.inner, f)
+    }
+}
+
+/// a weak handle to an RwVersioned
+///
+/// does not keep the RwVersioned alive. use upgrade to get a VersionedHandle
+/// if it still exists.
+pub struct WeakVersionedHandle<T> {
+    inner: Weak<RwVersioned<T>>,
+}
+
+impl<T> WeakVersionedHandle<T> {
+    /// creates a weak handle that will never upgrade
+    pub fn new() -> Self {
+        WeakVersionedHandle {
+            inner: Weak::new(),
+        }
+    }
+
+    /// attempts to get a strong handle to the RwVersioned
+    pub fn upgrade(&self) -> Option<VersionedHandle<T>> {
+        self.inner.upgrade()
+            .map(|inner| VersionedHandle { inner })
+    }
+
+    /// checks if the RwVersioned still exists
+    pub fn is_alive(&self) -> bool {
+        self.inner.strong_count() > 0
+    }
+
+    /// returns total strong handles to the RwVersioned
+    pub fn strong_count(&self) -> usize {
+        self.inner.strong_count()
+    }
+
+    /// checks if both handles point to the same RwVersioned
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.inner.ptr_eq(&other.inner)
+    }
+
+    /// calls the given function with the RwVersioned if it still exists
+    pub fn with<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&RwVersioned<T>) -> R
+    {
+        let inner = self.inner.upgrade()?;
+
+        Some(f(&inner))
+    }
+}
+
+impl<T> Default for WeakVersionedHandle<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for WeakVersionedHandle<T> {
+    fn clone(&self) -> Self {
+        WeakVersionedHandle {
+            inner: Weak::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> fmt::Debug for WeakVersionedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("(WeakVersionedHandle)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strong_and_weak() {
+        let handle: VersionedHandle<u64> = VersionedHandle::new();
+        let weak = handle.downgrade();
+        let other = handle.clone();
+
+        other.update(1).unwrap();
+
+        assert!(handle.ptr_eq(&other));
+        assert_eq!(handle.strong_count(), 2);
+        assert_eq!(handle.weak_count(), 1);
+        assert_eq!(handle.latest_cloned().unwrap(), Some(1));
+        assert_eq!(
+            weak.with(|versioned| versioned.count()),
+            Some(1)
+        );
+
+        let handle = match handle.try_unwrap() {
+            Ok(_) => panic!("strong handle should block unwrap"),
+            Err(handle) => handle,
+        };
+
+        drop(other);
+
+        let versioned = handle.try_unwrap()
+            .expect("weak handle should not block unwrap");
+
+        assert_eq!(versioned.count(), 1);
+
+        assert!(!weak.is_alive());
+        assert!(weak.upgrade().is_none());
+    }
+}
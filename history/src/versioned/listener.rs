@@ -0,0 +1,85 @@
+use core::fmt;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// callback invoked with the version number and value that changed
+pub type Callback<T> = Box<dyn FnMut(u64, &T) + Send + Sync>;
+
+/// identifies a registered listener so that it can be removed later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+/// registry of update and remove listeners
+pub(crate) struct Listeners<T> {
+    next_id: u64,
+    on_update: Vec<(ListenerId, Callback<T>)>,
+    on_remove: Vec<(ListenerId, Callback<T>)>,
+}
+
+impl<T> Listeners<T> {
+    pub(crate) fn new() -> Self {
+        Listeners {
+            next_id: 0,
+            on_update: Vec::new(),
+            on_remove: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn next_id(&mut self) -> ListenerId {
+        let id = ListenerId(self.next_id);
+        self.next_id += 1;
+
+        id
+    }
+
+    pub(crate) fn add_update(&mut self, cb: Callback<T>) -> ListenerId {
+        let id = self.next_id();
+
+        self.on_update.push((id, cb));
+
+        id
+    }
+
+    pub(crate) fn add_remove(&mut self, cb: Callback<T>) -> ListenerId {
+        let id = self.next_id();
+
+        self.on_remove.push((id, cb));
+
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: ListenerId) -> bool {
+        let total = self.on_update.len() + self.on_remove.len();
+
+        self.on_update.retain(|(check, _)| *check != id);
+        self.on_remove.retain(|(check, _)| *check != id);
+
+        total != self.on_update.len() + self.on_remove.len()
+    }
+
+    pub(crate) fn has_remove(&self) -> bool {
+        !self.on_remove.is_empty()
+    }
+
+    pub(crate) fn updated(&mut self, version: u64, value: &T) {
+        for (_, cb) in self.on_update.iter_mut() {
+            cb(version, value);
+        }
+    }
+
+    pub(crate) fn removed(&mut self, version: u64, value: &T) {
+        for (_, cb) in self.on_remove.iter_mut() {
+            cb(version, value);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Listeners<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Listeners")
+            .field("on_update", &self.on_update.len())
+            .field("on_remove", &self.on_remove.len())
+            .finish()
+    }
+}
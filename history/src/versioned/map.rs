@@ -0,0 +1,313 @@
+use alloc::collections::BTreeMap;
+use alloc::collections::btree_map;
+use core::borrow::Borrow;
+use core::fmt;
+use alloc::vec::Vec;
+
+use super::Versioned;
+
+/// stores a separate version history for each key
+///
+/// each key is given its own Versioned so version numbers are counted per
+/// key
+pub struct VersionedMap<K, T> {
+    map: BTreeMap<K, Versioned<T>>,
+}
+
+impl<K, T> VersionedMap<K, T> {
+    /// creates an empty map
+    pub fn new() -> Self {
+        VersionedMap {
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// returns total keys in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// checks if there are no keys in the map
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// returns an iterator of the keys in the map
+    pub fn keys(&self) -> btree_map::Keys<'_, K, Versioned<T>> {
+        self.map.keys()
+    }
+
+    /// returns an iterator of the keys and their histories
+    pub fn iter(&self) -> btree_map::Iter<'_, K, Versioned<T>> {
+        self.map.iter()
+    }
+}
+
+impl<K, T> VersionedMap<K, T>
+where
+    K: Ord
+{
+    /// updates the value for the given key returning the version number used
+    ///
+    /// a new history is created if the key does not exist
+    pub fn update(&mut self, key: K, value: T) -> u64 {
+        self.map.entry(key)
+            .or_default()
+            .update(value)
+    }
+
+    /// checks if the key has a history
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.contains_key(key)
+    }
+
+    /// returns the history for the given key
+    pub fn history<Q>(&self, key: &Q) -> Option<&Versioned<T>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get(key)
+    }
+
+    /// returns a mutable history for the given key
+    pub fn history_mut<Q>(&mut self, key: &Q) -> Option<&mut Versioned<T>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get_mut(key)
+    }
+
+    /// returns a reference to the desired version of the given key
+    pub fn get<Q>(&self, key: &Q, version: &u64) -> Option<&T>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get(key)?.get(version)
+    }
+
+    /// returns the latest version of the value for the given key
+    pub fn latest<Q>(&self, key: &Q) -> Option<&T>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get(key)?.latest()
+    }
+
+    /// returns the latest version of the value for the given key along with
+    /// the version number
+    pub fn latest_version<Q>(&self, key: &Q) -> Option<(&u64, &T)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get(key)?.latest_version()
+    }
+
+    /// drops the desired version of the given key returning the value found
+    ///
+    /// the key is kept even if its history is now empty so that version
+    /// numbers are not reused
+    pub fn remove<Q>(&mut self, key: &Q, version: &u64) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.get_mut(key)?.remove(version)
+    }
+
+    /// drops the entire history of the given key
+    pub fn remove_key<Q>(&mut self, key: &Q) -> Option<Versioned<T>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        self.map.remove(key)
+    }
+
+    /// keeps only the latest n versions for the given key returning the
+    /// removed versions in ascending order
+    pub fn retain_latest<Q>(&mut self, key: &Q, n: usize) -> Vec<(u64, T)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized
+    {
+        let Some(versioned) = self.map.get_mut(key) else {
+            return Vec::new();
+        };
+
+        retain_latest(versioned, n)
+    }
+
+    /// keeps only the latest n versions for every key
+    pub fn retain_latest_all(&mut self, n: usize) {
+        for versioned in self.map.values_mut() {
+            retain_latest(versioned, n);
+        }
+    }
+}
+
+/// removes all but the latest n versions returning the removed versions in
+/// ascending order
+fn retain_latest<T>(versioned: &mut Versioned<T>, n: usize) -> Vec<(u64, T)> {
+    let versions: Vec<u64> = versioned.iter()
+        .take(versioned.len().saturating_sub(n))
+        .map(|(k, _)| *k)
+        .collect();
+    let mut rtn = Vec::with_capacity(versions.len());
+
+    for version in versions {
+        if let Some(value) = versioned.remove(&version) {
+            rtn.push((version, value));
+        }
+    }
+
+    rtn
+}
+
+impl<K, T> core::default::Default for VersionedMap<K, T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> fmt::Debug for VersionedMap<K, T>
+where
+    K: fmt::Debug,
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VersionedMap")
+            .field("map", &self.map)
+            .finish()
+    }
+}
+
+impl<K, T> Clone for VersionedMap<K, T>
+where
+    K: Clone,
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        VersionedMap {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K, T> PartialEq for VersionedMap<K, T>
+where
+    K: PartialEq,
+    T: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K, T> Eq for VersionedMap<K, T>
+where
+    K: Eq,
+    T: Eq
+{}
+
+#[cfg(feature = "serde")]
+use serde::{
+    ser::{
+        Serialize,
+        Serializer,
+    },
+    de::{
+        Deserialize,
+        Deserializer,
+    }
+};
+
+/// serialized as a map of keys to their Versioned history
+#[cfg(feature = "serde")]
+impl<K, T> Serialize for VersionedMap<K, T>
+where
+    K: Serialize,
+    T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        self.map.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, T> Deserialize<'de> for VersionedMap<K, T>
+where
+    K: Deserialize<'de> + Ord,
+    T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        Ok(VersionedMap {
+            map: BTreeMap::deserialize(deserializer)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn per_key() {
+        let mut map: VersionedMap<String, u64> = VersionedMap::new();
+        map.update(String::from("a"), 1);
+        map.update(String::from("a"), 2);
+        let version = map.update(String::from("b"), 3);
+
+        assert_eq!(version, 0, "versions are not counted per key");
+        assert_eq!(map.latest("a"), Some(&2));
+        assert_eq!(map.latest_version("b"), Some((&0, &3)));
+        assert_eq!(map.get("a", &0), Some(&1));
+        assert_eq!(map.latest("c"), None);
+    }
+
+    #[test]
+    fn retain_latest() {
+        let mut map: VersionedMap<&str, u64> = VersionedMap::new();
+
+        for v in 0..5 {
+            map.update("a", v);
+        }
+
+        let removed = map.retain_latest("a", 2);
+
+        assert_eq!(removed, vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(map.history("a").map(Versioned::len), Some(2));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json() {
+        let mut map: VersionedMap<String, u64> = VersionedMap::new();
+        map.update(String::from("a"), 1);
+        map.update(String::from("b"), 2);
+
+        let to_json = serde_json::to_string(&map)
+            .expect("failed to serialize to json string");
+
+        let and_back: VersionedMap<String, u64> = serde_json::from_str(&to_json)
+            .expect("failed to deserialize from json string");
+
+        assert_eq!(map, and_back);
+    }
+}
@@ -1,33 +1,178 @@
-use std::collections::BTreeMap;
-use std::collections::btree_map::Iter;
-use std::fmt;
+use core::fmt;
+use core::ops::{Range, RangeBounds};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
-//pub mod sync;
+#[cfg(feature = "std")]
+pub mod sync;
+
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+#[cfg(feature = "serde")]
+pub mod compact;
+#[cfg(feature = "chain")]
+pub mod chained;
+#[cfg(feature = "parking_lot")]
+pub mod parking;
+#[cfg(feature = "std")]
+pub mod handle;
+#[cfg(feature = "persist")]
+pub mod persist;
+mod listener;
+pub mod map;
+pub mod patch;
+pub mod store;
+mod size;
+pub mod text;
+#[cfg(feature = "std")]
+pub mod timestamp;
+
+#[cfg(feature = "tokio")]
+pub use asynchronous::AsyncVersioned;
+#[cfg(feature = "std")]
+pub use handle::{VersionedHandle, WeakVersionedHandle};
+pub use listener::{Callback, ListenerId};
+pub use size::HeapSize;
+pub use text::VersionedString;
+#[cfg(feature = "std")]
+pub use timestamp::Timestamped;
+pub use map::VersionedMap;
+pub use patch::Patch;
+pub use store::{Store, Iter};
+use listener::Listeners;
+
+/// possible errors from methods in Versioned
+pub enum Error {
+    /// the given version was not larger than the version before it
+    NotAscending(u64),
+    /// there are no more version numbers available
+    VersionExhausted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotAscending(version) => write!(f, "NotAscending({})", version),
+            Error::VersionExhausted => f.write_str("VersionExhausted"),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotAscending(version) => write!(f, "NotAscending({})", version),
+            Error::VersionExhausted => f.write_str("VersionExhausted"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// marks a point in the history of a Versioned that can be restored to
+///
+/// created by Versioned::savepoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Savepoint {
+    count: u64,
+}
+
+impl Savepoint {
+    /// the count of the Versioned when the savepoint was created
+    pub fn count(&self) -> &u64 {
+        &self.count
+    }
+}
+
+/// the result of squashing a range of versions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Squashed {
+    version: u64,
+    replaced: Vec<u64>,
+}
+
+impl Squashed {
+    /// the version that holds the squashed value
+    pub fn version(&self) -> &u64 {
+        &self.version
+    }
+
+    /// the versions that were removed by the squash in ascending order
+    pub fn replaced(&self) -> &[u64] {
+        &self.replaced
+    }
+}
 
 /// stores changes to a given value and applies a counted number to each update
 ///
-/// values are stored in a BTreeMap and the counted version is a u64
+/// values are stored in a Store indexed by version and the counted version
+/// is a u64.
+/// registered listeners are not cloned, compared or serialized with the rest
+/// of the struct
 pub struct Versioned<T> {
-    store: BTreeMap<u64, T>,
-    count: u64
+    store: Store<T>,
+    count: u64,
+    listeners: Listeners<T>,
 }
 
 impl<T> Versioned<T> {
     /// creates an empty versioned struct
     pub fn new() -> Self {
         Versioned {
-            store: BTreeMap::new(),
-            count: 0
+            store: Store::new(),
+            count: 0,
+            listeners: Listeners::new(),
+        }
+    }
+
+    /// creates an empty versioned struct that will start numbering versions
+    /// from the given count
+    pub fn with_count(count: u64) -> Self {
+        Versioned {
+            store: Store::new(),
+            count,
+            listeners: Listeners::new(),
         }
     }
 
+    /// creates a Versioned from a list of version and value pairs
+    ///
+    /// versions must be in ascending order and the count will be set to the
+    /// largest version + 1. gaps between versions are not stored so memory
+    /// only grows with the number of pairs. this is intended as the
+    /// interchange format with other systems and is the inverse of into_pairs
+    pub fn from_pairs<I>(pairs: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (u64, T)>
+    {
+        let mut rtn = Versioned::new();
+
+        for (version, value) in pairs {
+            if version < rtn.count {
+                return Err(Error::NotAscending(version));
+            }
+
+            rtn.count = version.checked_add(1)
+                .ok_or(Error::VersionExhausted)?;
+            rtn.store.insert(version, value);
+        }
+
+        Ok(rtn)
+    }
+
+    /// consumes the struct returning the stored versions and values in
+    /// ascending order
+    pub fn into_pairs(self) -> Vec<(u64, T)> {
+        self.store.into_iter().collect()
+    }
+
     /// returns next version number to use
     pub fn count(&self) -> &u64 {
         &self.count
     }
 
     /// returns reference to current store
-    pub fn store(&self) -> &BTreeMap<u64, T> {
+    pub fn store(&self) -> &Store<T> {
         &self.store
     }
 
@@ -36,19 +181,105 @@ impl<T> Versioned<T> {
         self.store.len()
     }
 
+    /// checks if there are no values in the store
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// checks if the desired version is in the store
+    pub fn contains_version(&self, version: &u64) -> bool {
+        self.store.contains_key(version)
+    }
+
     /// updates the value returning the version number used
+    ///
+    /// panics if the version numbers have been exhausted, use try_update to
+    /// handle that case
     pub fn update(&mut self, value: T) -> u64 {
+        match self.try_update(value) {
+            Ok(version) => version,
+            Err(_) => panic!("Versioned count exhausted"),
+        }
+    }
+
+    /// updates the value returning the version number used
+    ///
+    /// the last version that can be used is u64::MAX - 1. once the count
+    /// reaches u64::MAX a VersionExhausted error will be returned and the
+    /// value will not be stored
+    pub fn try_update(&mut self, value: T) -> Result<u64, Error> {
+        if self.count == u64::MAX {
+            return Err(Error::VersionExhausted);
+        }
+
         let version = self.count;
         self.count += 1;
 
         self.store.insert(version, value);
 
-        version
+        if let Some(value) = self.store.get(&version) {
+            self.listeners.updated(version, value);
+        }
+
+        Ok(version)
+    }
+
+    /// stores each value as a consecutive version returning the range of
+    /// versions used
+    ///
+    /// panics if there are not enough version numbers left for every value,
+    /// use try_update_many to handle that case
+    pub fn update_many<I>(&mut self, values: I) -> Range<u64>
+    where
+        I: IntoIterator<Item = T>
+    {
+        match self.try_update_many(values) {
+            Ok(range) => range,
+            Err(_) => panic!("Versioned count exhausted"),
+        }
+    }
+
+    /// stores each value as a consecutive version returning the range of
+    /// versions used
+    ///
+    /// update listeners are only invoked once all values have been stored.
+    /// a VersionExhausted error is returned without storing any value if
+    /// the values do not fit in the remaining version numbers
+    pub fn try_update_many<I>(&mut self, values: I) -> Result<Range<u64>, Error>
+    where
+        I: IntoIterator<Item = T>
+    {
+        let values: Vec<T> = values.into_iter().collect();
+
+        if u64::MAX - self.count < values.len() as u64 {
+            return Err(Error::VersionExhausted);
+        }
+
+        let start = self.count;
+
+        for value in values {
+            self.store.insert(self.count, value);
+            self.count += 1;
+        }
+
+        for version in start..self.count {
+            if let Some(value) = self.store.get(&version) {
+                self.listeners.updated(version, value);
+            }
+        }
+
+        Ok(start..self.count)
     }
 
     /// drops the desired version returning the value found
     pub fn remove(&mut self, version: &u64) -> Option<T> {
-        self.store.remove(version)
+        let rtn = self.store.remove(version);
+
+        if let Some(value) = &rtn {
+            self.listeners.removed(*version, value);
+        }
+
+        rtn
     }
 
     /// returns a reference to the desired version
@@ -66,19 +297,285 @@ impl<T> Versioned<T> {
         self.store.last_key_value()
     }
 
-    /// returns a BTreeMap Iter
-    pub fn iter(&self) -> Iter<'_, u64, T> {
+    /// returns an iterator from the oldest to the newest version
+    pub fn iter(&self) -> Iter<'_, T> {
         self.store.iter()
     }
+
+    /// returns an iterator from the newest to the oldest version
+    pub fn iter_desc(&self) -> core::iter::Rev<Iter<'_, T>> {
+        self.store.iter().rev()
+    }
+
+    /// returns an iterator over the n most recent versions from newest to
+    /// oldest
+    pub fn latest_n(&self, n: usize) -> core::iter::Take<core::iter::Rev<Iter<'_, T>>> {
+        self.iter_desc().take(n)
+    }
+
+    /// removes all values from the store
+    ///
+    /// the count is kept as is so that version numbers handed out after
+    /// clearing will not collide with previously used versions
+    pub fn clear(&mut self) {
+        self.clear_store();
+    }
+
+    /// removes all values from the store and sets the count back to 0
+    pub fn reset(&mut self) {
+        self.clear_store();
+        self.count = 0;
+    }
+
+    #[inline]
+    fn clear_store(&mut self) {
+        if self.listeners.has_remove() {
+            for (version, value) in core::mem::take(&mut self.store) {
+                self.listeners.removed(version, &value);
+            }
+        } else {
+            self.store.clear();
+        }
+    }
+
+    /// estimates the bytes used by the struct and its stored values
+    ///
+    /// the provided sizer should return the heap bytes owned by a value, the
+    /// inline size of the value is already included.
+    pub fn approx_memory_usage_with<F>(&self, sizer: F) -> usize
+    where
+        F: Fn(&T) -> usize
+    {
+        core::mem::size_of::<Self>()
+            + self.store.allocated_bytes()
+            + self.store.values().map(sizer).sum::<usize>()
+    }
+
+    /// estimates the bytes used by the struct and its stored values using
+    /// the HeapSize implementation of the values
+    pub fn approx_memory_usage(&self) -> usize
+    where
+        T: HeapSize
+    {
+        self.approx_memory_usage_with(HeapSize::heap_size)
+    }
+
+    /// creates a new Versioned containing the history up to and including
+    /// the given version
+    ///
+    /// the count of the fork will continue from the given version so new
+    /// updates branch off from that point. None is returned if the version is
+    /// not stored. listeners are not carried over to the fork.
+    pub fn fork_at(&self, version: &u64) -> Option<Self>
+    where
+        T: Clone
+    {
+        if !self.store.contains_key(version) {
+            return None;
+        }
+
+        let store = self.store.iter()
+            .take_while(|(k, _)| *k <= version)
+            .map(|(k, v)| (*k, v.clone()))
+            .collect();
+
+        Some(Versioned {
+            store,
+            count: *version + 1,
+            listeners: Listeners::new(),
+        })
+    }
+
+    /// collapses the versions in the given range into the newest version of
+    /// the range
+    ///
+    /// the newest value is kept and all other versions in the range are
+    /// removed. None is returned if no versions are stored in the range.
+    ///
+    /// the replaced versions are not recorded by the Versioned so get
+    /// returns None for them afterwards. use the returned Squashed to map a
+    /// replaced version to the version that now holds its value
+    pub fn squash<R>(&mut self, range: R) -> Option<Squashed>
+    where
+        R: RangeBounds<u64>
+    {
+        let (version, replaced) = self.squash_versions(range)?;
+
+        for replace in replaced.iter() {
+            self.remove(replace);
+        }
+
+        Some(Squashed { version, replaced })
+    }
+
+    /// collapses the versions in the given range into the newest version of
+    /// the range using the provided merge function
+    ///
+    /// all versions in the range are removed and passed to the merge function
+    /// in ascending order and the returned value is stored under the newest
+    /// version. None is returned if no versions are stored in the range.
+    /// lookups of the replaced versions return None the same as squash
+    pub fn squash_with<R, F>(&mut self, range: R, merge: F) -> Option<Squashed>
+    where
+        R: RangeBounds<u64>,
+        F: FnOnce(Vec<(u64, T)>) -> T
+    {
+        let (version, replaced) = self.squash_versions(range)?;
+        let mut values = Vec::with_capacity(replaced.len() + 1);
+
+        for replace in replaced.iter() {
+            if let Some(value) = self.remove(replace) {
+                values.push((*replace, value));
+            }
+        }
+
+        if let Some(value) = self.store.remove(&version) {
+            values.push((version, value));
+        }
+
+        self.store.insert(version, merge(values));
+
+        if let Some(value) = self.store.get(&version) {
+            self.listeners.updated(version, value);
+        }
+
+        Some(Squashed { version, replaced })
+    }
+
+    /// finds the newest version in the range and the versions to replace
+    fn squash_versions<R>(&self, range: R) -> Option<(u64, Vec<u64>)>
+    where
+        R: RangeBounds<u64>
+    {
+        let mut replaced: Vec<u64> = self.store.range(range)
+            .map(|(k, _)| *k)
+            .collect();
+
+        let version = replaced.pop()?;
+
+        Some((version, replaced))
+    }
+
+    /// creates a savepoint for the current state of the store
+    pub fn savepoint(&self) -> Savepoint {
+        Savepoint {
+            count: self.count
+        }
+    }
+
+    /// reverts the store to the given savepoint
+    ///
+    /// all versions created after the savepoint are removed and returned in
+    /// ascending order and the count is set back to what it was. versions
+    /// that were removed after the savepoint was created are not brought
+    /// back. if the count is less than the savepoint (the store was reset
+    /// after creating it) then nothing is changed and None is returned.
+    pub fn restore(&mut self, savepoint: Savepoint) -> Option<Vec<(u64, T)>> {
+        if savepoint.count > self.count {
+            return None;
+        }
+
+        let discarded = self.store.split_off(&savepoint.count);
+        self.count = savepoint.count;
+
+        for (version, value) in discarded.iter() {
+            self.listeners.removed(*version, value);
+        }
+
+        Some(discarded.into_iter().collect())
+    }
+
+    /// registers a callback that is invoked after a new version is stored
+    ///
+    /// the callback receives the version number and a reference to the value
+    pub fn on_update<F>(&mut self, cb: F) -> ListenerId
+    where
+        F: FnMut(u64, &T) + Send + Sync + 'static
+    {
+        self.listeners.add_update(Box::new(cb))
+    }
+
+    /// registers a callback that is invoked when a version is removed
+    ///
+    /// the callback receives the version number and a reference to the value
+    /// before it is dropped or returned to the caller
+    pub fn on_remove<F>(&mut self, cb: F) -> ListenerId
+    where
+        F: FnMut(u64, &T) + Send + Sync + 'static
+    {
+        self.listeners.add_remove(Box::new(cb))
+    }
+
+    /// unregisters the desired listener returning true if it was found
+    pub fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.listeners.remove(id)
+    }
+
+    /// returns a watch receiver that is sent the newest version and value
+    /// after every update
+    ///
+    /// the receiver starts with the current latest version. removing
+    /// versions does not publish a new value. the sender is registered as an
+    /// update listener for the lifetime of the struct.
+    #[cfg(feature = "tokio")]
+    pub fn watch(&mut self) -> tokio::sync::watch::Receiver<Option<(u64, T)>>
+    where
+        T: Clone + Send + Sync + 'static
+    {
+        let initial = self.store.last_key_value()
+            .map(|(k, v)| (*k, v.clone()));
+        let (sender, receiver) = tokio::sync::watch::channel(initial);
+
+        self.listeners.add_update(Box::new(move |version, value| {
+            sender.send_replace(Some((version, value.clone())));
+        }));
+
+        receiver
+    }
 }
 
-impl<T> std::default::Default for Versioned<T> {
+impl<T> Versioned<T>
+where
+    T: Clone
+{
+    /// returns a copy of the desired version
+    pub fn get_cloned(&self, version: &u64) -> Option<T> {
+        self.store.get(version).cloned()
+    }
+
+    /// returns a copy of the latest version of the value
+    pub fn latest_cloned(&self) -> Option<T> {
+        self.latest().cloned()
+    }
+
+    /// returns a copy of the latest version of the value along with the
+    /// version number
+    pub fn latest_version_cloned(&self) -> Option<(u64, T)> {
+        self.latest_version().map(|(k, v)| (*k, v.clone()))
+    }
+}
+
+impl<T> core::default::Default for Versioned<T> {
     #[inline]
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// returns a reference to the desired version
+///
+/// panics if the version is not stored, use get for a checked lookup
+impl<T> core::ops::Index<u64> for Versioned<T> {
+    type Output = T;
+
+    fn index(&self, version: u64) -> &Self::Output {
+        match self.store.get(&version) {
+            Some(value) => value,
+            None => panic!("version {} is not stored", version),
+        }
+    }
+}
+
 impl<T> fmt::Debug for Versioned<T>
 where
     T: fmt::Debug
@@ -99,10 +596,26 @@ where
         Versioned {
             store: self.store.clone(),
             count: self.count.clone(),
+            listeners: Listeners::new(),
         }
     }
 }
 
+/// compares the stored values and count, listeners are ignored
+impl<T> PartialEq for Versioned<T>
+where
+    T: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count && self.store == other.store
+    }
+}
+
+impl<T> Eq for Versioned<T>
+where
+    T: Eq
+{}
+
 #[cfg(feature = "serde")]
 use serde::{
     ser::{
@@ -182,8 +695,23 @@ where
             }
         }
 
+        /// calculates the count from the largest version in the store
+        ///
+        /// used when loading data that was created without the count field
+        fn derive_count<T, E>(store: &Store<T>) -> Result<u64, E>
+        where
+            E: de::Error
+        {
+            let Some((version, _)) = store.last_key_value() else {
+                return Ok(0);
+            };
+
+            version.checked_add(1)
+                .ok_or_else(|| de::Error::custom("cannot derive count from version u64::MAX"))
+        }
+
         struct VersionedVisitor<T> {
-            _type: std::marker::PhantomData<T>
+            _type: core::marker::PhantomData<T>
         }
 
         impl<'de, T> Visitor<'de> for VersionedVisitor<T>
@@ -192,7 +720,7 @@ where
         {
             type Value = Versioned<T>;
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                 formatter.write_str("struct Versioned")
             }
 
@@ -200,12 +728,18 @@ where
             where
                 V: SeqAccess<'de>
             {
-                let store = seq.next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                let count = seq.next_element()?
+                let store: Store<T> = seq.next_element()?
                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let count = match seq.next_element()? {
+                    Some(count) => count,
+                    None => derive_count(&store)?,
+                };
 
-                Ok(Versioned { store, count })
+                Ok(Versioned {
+                    store,
+                    count,
+                    listeners: Listeners::new(),
+                })
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
@@ -235,9 +769,16 @@ where
                 }
 
                 let store = store.ok_or_else(|| de::Error::missing_field("store"))?;
-                let count = count.ok_or_else(|| de::Error::missing_field("count"))?;
+                let count = match count {
+                    Some(count) => count,
+                    None => derive_count(&store)?,
+                };
 
-                Ok(Versioned { store, count })
+                Ok(Versioned {
+                    store,
+                    count,
+                    listeners: Listeners::new(),
+                })
             }
         }
 
@@ -245,7 +786,7 @@ where
             "Versioned",
             STRUCT_FIELDS,
             VersionedVisitor {
-                _type: std::marker::PhantomData
+                _type: core::marker::PhantomData
             }
         )
     }
@@ -256,6 +797,329 @@ mod test {
     #[allow(unused_imports)]
     use super::*;
 
+    #[test]
+    fn clear_and_reset() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(1);
+        versioned.update(2);
+
+        versioned.clear();
+
+        assert_eq!(versioned.len(), 0, "store was not cleared");
+        assert_eq!(versioned.update(3), 2, "count was not kept after clear");
+
+        versioned.reset();
+
+        assert_eq!(versioned.len(), 0, "store was not cleared");
+        assert_eq!(versioned.update(4), 0, "count was not reset");
+    }
+
+    #[test]
+    fn savepoint() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(1);
+
+        let savepoint = versioned.savepoint();
+
+        versioned.update(2);
+        versioned.update(3);
+
+        let discarded = versioned.restore(savepoint)
+            .expect("savepoint was not restored");
+
+        assert_eq!(discarded, vec![(1, 2), (2, 3)]);
+        assert_eq!(versioned.count, 1, "count was not restored");
+        assert_eq!(versioned.latest(), Some(&1));
+
+        versioned.reset();
+
+        assert!(versioned.restore(savepoint).is_none(), "stale savepoint was restored");
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut versioned: Versioned<String> = Versioned::new();
+        let empty = versioned.approx_memory_usage();
+
+        versioned.update(String::with_capacity(100));
+        versioned.update(String::with_capacity(50));
+
+        let filled = versioned.approx_memory_usage();
+
+        assert!(filled >= empty + 150, "heap sizes were not included");
+        assert_eq!(
+            versioned.approx_memory_usage_with(|_| 0) + 150,
+            filled,
+            "sizer was not used"
+        );
+    }
+
+    #[test]
+    fn index() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(5);
+        let version = versioned.update(3);
+
+        assert_eq!(versioned[version], 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_missing() {
+        let versioned: Versioned<u64> = Versioned::new();
+
+        let _ = versioned[0];
+    }
+
+    #[test]
+    fn eq() {
+        let mut a: Versioned<u64> = Versioned::new();
+        a.update(1);
+        a.update(2);
+
+        let mut b = a.clone();
+
+        assert_eq!(a, b);
+
+        b.remove(&1);
+        b.update(2);
+
+        assert_ne!(a, b, "store values should not be equal");
+
+        a.remove(&1);
+
+        assert_ne!(a, b, "count values should not be equal");
+    }
+
+    #[test]
+    fn iter_desc() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update_many(0..5);
+        versioned.remove(&2);
+
+        assert!(
+            versioned.iter_desc().map(|(k, _)| *k).eq([4, 3, 1, 0]),
+            "unexpected descending order"
+        );
+    }
+
+    #[test]
+    fn latest_n() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+
+        for v in 0..10 {
+            versioned.update(v);
+        }
+
+        versioned.remove(&8);
+
+        assert!(
+            versioned.latest_n(3).map(|(k, _)| *k).eq([9, 7, 6]),
+            "unexpected latest versions"
+        );
+        assert_eq!(versioned.latest_n(20).count(), 9);
+    }
+
+    #[test]
+    fn fork_at() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(1);
+        let fork_version = versioned.update(2);
+        versioned.update(3);
+
+        let mut fork = versioned.fork_at(&fork_version)
+            .expect("version was not found");
+
+        assert_eq!(fork.len(), 2);
+        assert_eq!(fork.update(4), 2, "fork did not continue from version");
+        assert_eq!(versioned.get(&2), Some(&3), "original was modified");
+
+        assert!(versioned.fork_at(&10).is_none(), "missing version was forked");
+    }
+
+    #[test]
+    fn squash() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+
+        for v in 0..6 {
+            versioned.update(v);
+        }
+
+        let squashed = versioned.squash(1..4)
+            .expect("no versions were squashed");
+
+        assert_eq!(*squashed.version(), 3);
+        assert_eq!(squashed.replaced(), &[1, 2]);
+        assert!(versioned.iter().map(|(k, _)| *k).eq([0, 3, 4, 5]));
+        assert_eq!(versioned.get(&1), None, "squashed version was found");
+
+        let squashed = versioned.squash_with(..=4, |values| {
+            values.into_iter().map(|(_, v)| v).sum()
+        }).expect("no versions were squashed");
+
+        assert_eq!(*squashed.version(), 4);
+        assert_eq!(squashed.replaced(), &[0, 3]);
+        assert_eq!(versioned.get(&4), Some(&7));
+
+        assert!(versioned.squash(10..).is_none(), "empty range was squashed");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn watch() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(1);
+
+        let mut receiver = versioned.watch();
+
+        assert_eq!(*receiver.borrow_and_update(), Some((0, 1)));
+
+        versioned.update(2);
+
+        assert!(receiver.has_changed().unwrap(), "update was not published");
+        assert_eq!(*receiver.borrow_and_update(), Some((1, 2)));
+    }
+
+    #[test]
+    fn cloned() {
+        let mut versioned: Versioned<String> = Versioned::new();
+        let version = versioned.update(String::from("first"));
+        versioned.update(String::from("second"));
+
+        assert_eq!(versioned.get_cloned(&version), Some(String::from("first")));
+        assert_eq!(versioned.latest_cloned(), Some(String::from("second")));
+        assert_eq!(versioned.latest_version_cloned(), Some((1, String::from("second"))));
+    }
+
+    #[test]
+    fn pairs() {
+        let versioned = Versioned::from_pairs([(1, 'a'), (4, 'b'), (5, 'c')])
+            .expect("failed to create from pairs");
+
+        assert_eq!(versioned.count, 6, "count was not set from pairs");
+        assert_eq!(versioned.into_pairs(), vec![(1, 'a'), (4, 'b'), (5, 'c')]);
+
+        assert!(
+            matches!(Versioned::from_pairs([(3, 'a'), (3, 'b')]), Err(Error::NotAscending(3))),
+            "duplicate version was accepted"
+        );
+        assert!(
+            matches!(Versioned::from_pairs([(u64::MAX, 'a')]), Err(Error::VersionExhausted)),
+            "max version was accepted"
+        );
+
+        // far apart versions do not allocate for the gap between them
+        let sparse = Versioned::from_pairs([(0, 'a'), (1 << 40, 'b'), (u64::MAX - 1, 'c')])
+            .expect("failed to create from sparse pairs");
+
+        assert_eq!(sparse.len(), 3);
+        assert_eq!(sparse.count, u64::MAX);
+    }
+
+    #[test]
+    fn update_many() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+        versioned.update(0);
+
+        let range = versioned.update_many([1, 2, 3]);
+
+        assert_eq!(range, 1..4);
+        assert_eq!(versioned.count, 4);
+        assert!(versioned.iter().map(|(k, v)| (*k, *v)).eq([(0, 0), (1, 1), (2, 2), (3, 3)]));
+    }
+
+    #[test]
+    fn with_count() {
+        let mut versioned: Versioned<u64> = Versioned::with_count(100);
+
+        assert_eq!(versioned.len(), 0);
+        assert_eq!(versioned.update(1), 100);
+        assert_eq!(versioned.count, 101);
+    }
+
+    #[test]
+    fn exhausted() {
+        let mut versioned: Versioned<u64> = Versioned::with_count(u64::MAX - 1);
+
+        assert_eq!(versioned.try_update(1).unwrap(), u64::MAX - 1);
+        assert!(
+            matches!(versioned.try_update(2), Err(Error::VersionExhausted)),
+            "exhausted count was not reported"
+        );
+        assert_eq!(versioned.len(), 1, "value was stored after exhaustion");
+    }
+
+    #[test]
+    fn exhausted_many() {
+        let mut versioned: Versioned<u64> = Versioned::with_count(u64::MAX - 2);
+
+        assert!(
+            matches!(versioned.try_update_many([1, 2, 3]), Err(Error::VersionExhausted)),
+            "exhausted count was not reported"
+        );
+        assert!(versioned.is_empty(), "values were stored after exhaustion");
+        assert_eq!(versioned.try_update_many([1, 2]).unwrap(), (u64::MAX - 2)..u64::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn exhausted_update() {
+        let mut versioned: Versioned<u64> = Versioned::with_count(u64::MAX);
+
+        versioned.update(1);
+    }
+
+    #[test]
+    fn is_empty_contains() {
+        let mut versioned: Versioned<u64> = Versioned::new();
+
+        assert!(versioned.is_empty());
+
+        let version = versioned.update(1);
+
+        assert!(!versioned.is_empty());
+        assert!(versioned.contains_version(&version));
+        assert!(!versioned.contains_version(&(version + 1)));
+    }
+
+    #[test]
+    fn listeners() {
+        use std::sync::{Arc, Mutex};
+
+        let updated = Arc::new(Mutex::new(Vec::new()));
+        let removed = Arc::new(Mutex::new(Vec::new()));
+
+        let mut versioned: Versioned<u64> = Versioned::new();
+
+        let id = {
+            let updated = updated.clone();
+
+            versioned.on_update(move |version, value| {
+                updated.lock().unwrap().push((version, *value));
+            })
+        };
+
+        {
+            let removed = removed.clone();
+
+            versioned.on_remove(move |version, value| {
+                removed.lock().unwrap().push((version, *value));
+            });
+        }
+
+        versioned.update(5);
+        let drop = versioned.update(3);
+        versioned.remove(&drop);
+
+        assert!(versioned.remove_listener(id), "listener was not found");
+
+        versioned.update(7);
+        versioned.clear();
+
+        assert_eq!(*updated.lock().unwrap(), vec![(0, 5), (1, 3)]);
+        assert_eq!(*removed.lock().unwrap(), vec![(1, 3), (0, 5), (2, 7)]);
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn serde_json() {
@@ -278,6 +1142,31 @@ mod test {
         assert_eq!(versioned.count, and_back.count, "count values are not equal");
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_missing_count() {
+        let json = r#"{"store":{"0":5,"3":7}}"#;
+
+        let versioned: Versioned<u64> = serde_json::from_str(json)
+            .expect("failed to deserialize from json string");
+
+        assert_eq!(versioned.count, 4, "count was not derived from the store");
+        assert_eq!(versioned.len(), 2, "unexpected store length");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_sparse() {
+        let json = r#"{"store":{"0":1,"18446744073709551000":2},"count":18446744073709551001}"#;
+
+        let versioned: Versioned<u64> = serde_json::from_str(json)
+            .expect("failed to deserialize from json string");
+
+        assert_eq!(versioned.len(), 2, "unexpected store length");
+        assert_eq!(versioned.get(&18446744073709551000), Some(&2));
+        assert_eq!(versioned.latest(), Some(&2));
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn serde_bincode() {
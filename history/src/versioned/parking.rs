@@ -0,0 +1,511 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Range, RangeBounds};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::{RwLock, RwLockReadGuard, MappedRwLockReadGuard};
+
+use super::Versioned;
+use super::listener::Listeners;
+
+/// stores changes to a given value and applies a counted number to each update
+///
+/// same as the RwVersioned in the sync module but backed by the locks from
+/// parking_lot. the locks cannot be poisoned so none of the methods return
+/// errors. the count is an AtomicU64 that is only changed while the store is
+/// locked for writing
+pub struct RwVersioned<T> {
+    store: RwLock<BTreeMap<u64, T>>,
+    count: AtomicU64,
+}
+
+impl<T> RwVersioned<T> {
+    /// creates an empty versioned struct
+    pub fn new() -> Self {
+        RwVersioned {
+            store: RwLock::new(BTreeMap::new()),
+            count: AtomicU64::new(0)
+        }
+    }
+
+    /// returns the next version number to use
+    ///
+    /// does not lock the store
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// returns read guard to current store
+    pub fn store(&self) -> RwLockReadGuard<'_, BTreeMap<u64, T>> {
+        self.store.read()
+    }
+
+    /// returns total stored values
+    pub fn len(&self) -> usize {
+        self.store.read().len()
+    }
+
+    /// checks if there are no stored values
+    pub fn is_empty(&self) -> bool {
+        self.store.read().is_empty()
+    }
+
+    /// checks if the given version is stored
+    pub fn contains(&self, version: &u64) -> bool {
+        self.store.read().contains_key(version)
+    }
+
+    /// updates the value returning the version number used
+    ///
+    /// the version number is taken from the count while the store is locked
+    /// for writing. panics if the version numbers have been exhausted
+    pub fn update(&self, value: T) -> u64 {
+        let mut store_writer = self.store.write();
+        let new_version = self.reserve(1);
+
+        store_writer.insert(new_version, value);
+
+        new_version
+    }
+
+    /// stores each value as a consecutive version returning the range of
+    /// versions used
+    ///
+    /// the store is locked once for the whole batch so readers will see
+    /// either none or all of the values. panics without storing any value if
+    /// the batch does not fit in the remaining version numbers
+    pub fn update_many<I>(&self, values: I) -> Range<u64>
+    where
+        I: IntoIterator<Item = T>
+    {
+        let values: Vec<T> = values.into_iter().collect();
+        let mut store_writer = self.store.write();
+        let start = self.reserve(values.len() as u64);
+        let end = start + values.len() as u64;
+
+        store_writer.extend((start..end).zip(values));
+
+        start..end
+    }
+
+    /// takes the given number of versions from the count returning the
+    /// first one
+    ///
+    /// must only be called while the store is locked for writing
+    fn reserve(&self, len: u64) -> u64 {
+        let start = self.count.load(Ordering::Acquire);
+
+        if u64::MAX - start < len {
+            panic!("RwVersioned count exhausted");
+        }
+
+        self.count.store(start + len, Ordering::Release);
+
+        start
+    }
+
+    /// drops the desired version returning the value found
+    ///
+    /// only locks the store
+    pub fn drop(&self, version: &u64) -> Option<T> {
+        self.store.write().remove(version)
+    }
+
+    /// removes all but the latest n versions returning the removed versions
+    /// in ascending order
+    ///
+    /// performed under a single write lock of the store
+    pub fn retain_latest(&self, n: usize) -> Vec<(u64, T)> {
+        let mut store_writer = self.store.write();
+
+        let removed = if n == 0 {
+            std::mem::take(&mut *store_writer)
+        } else if let Some(version) = store_writer.keys().nth_back(n - 1).copied() {
+            split_before(&mut store_writer, &version)
+        } else {
+            return Vec::new();
+        };
+
+        removed.into_iter().collect()
+    }
+
+    /// removes all versions older than the given version returning the
+    /// removed versions in ascending order
+    ///
+    /// performed under a single write lock of the store
+    pub fn remove_older_than(&self, version: &u64) -> Vec<(u64, T)> {
+        let mut store_writer = self.store.write();
+
+        let removed = split_before(&mut store_writer, version);
+
+        removed.into_iter().collect()
+    }
+
+    /// removes all versions newer than the given version and resets the
+    /// count to continue from it returning the removed versions in
+    /// ascending order
+    ///
+    /// performed under a single write lock of the store so no updates can
+    /// happen in between. nothing is changed if the version is not less than
+    /// the count
+    pub fn rollback_to(&self, version: &u64) -> Vec<(u64, T)> {
+        let mut store_writer = self.store.write();
+        let next = version.saturating_add(1);
+
+        if next >= self.count.load(Ordering::Acquire) {
+            return Vec::new();
+        }
+
+        let removed = store_writer.split_off(&next);
+        self.count.store(next, Ordering::Release);
+
+        removed.into_iter().collect()
+    }
+
+    /// returns a reference to the desired version
+    ///
+    /// the returned guard holds the read lock on the store so writers will be
+    /// blocked until it is dropped
+    pub fn get(&self, version: &u64) -> Option<MappedRwLockReadGuard<'_, T>> {
+        RwLockReadGuard::try_map(self.store.read(), |store| store.get(version)).ok()
+    }
+
+    /// returns the latest version of the value
+    pub fn latest(&self) -> Option<MappedRwLockReadGuard<'_, T>> {
+        RwLockReadGuard::try_map(self.store.read(), |store| {
+            store.last_key_value().map(|(_, v)| v)
+        }).ok()
+    }
+
+    /// returns the latest version of the value along with the version number
+    pub fn latest_version(&self) -> Option<(u64, MappedRwLockReadGuard<'_, T>)> {
+        let reader = self.store.read();
+        let version = reader.last_key_value().map(|(k, _)| *k)?;
+
+        RwLockReadGuard::try_map(reader, |store| store.get(&version))
+            .ok()
+            .map(|value| (version, value))
+    }
+
+    /// calls the given function with a reference to the desired version
+    ///
+    /// the read lock is only held while the function runs
+    pub fn with_version<F, R>(&self, version: &u64, f: F) -> Option<R>
+    where
+        F: FnOnce(&T) -> R
+    {
+        self.store.read().get(version).map(f)
+    }
+
+    /// calls the given function with the latest version number and value
+    ///
+    /// the read lock is only held while the function runs
+    pub fn with_latest<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&u64, &T) -> R
+    {
+        self.store.read().last_key_value().map(|(k, v)| f(k, v))
+    }
+}
+
+impl<T> RwVersioned<T>
+where
+    T: Clone
+{
+    /// copies the store and count into an owned Versioned
+    ///
+    /// the count is read while the store is locked for reading so the
+    /// snapshot will not contain a partially applied update
+    pub fn snapshot(&self) -> Versioned<T> {
+        let store_reader = self.store.read();
+        let count = self.count.load(Ordering::Acquire);
+
+        Versioned {
+            store: store_reader.iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            count,
+            listeners: Listeners::new(),
+        }
+    }
+
+    /// returns a clone of the desired version
+    pub fn get_cloned(&self, version: &u64) -> Option<T> {
+        self.store.read().get(version).cloned()
+    }
+
+    /// returns a clone of the latest version of the value
+    pub fn latest_cloned(&self) -> Option<T> {
+        self.store.read().last_key_value().map(|(_, v)| v.clone())
+    }
+
+    /// returns a clone of the latest version of the value along with the
+    /// version number
+    pub fn latest_version_cloned(&self) -> Option<(u64, T)> {
+        self.store.read().last_key_value().map(|(k, v)| (*k, v.clone()))
+    }
+
+    /// returns an owning iterator of clones of all stored versions in
+    /// ascending order
+    ///
+    /// the versions are copied under a single read lock that is released
+    /// before returning
+    pub fn iter_cloned(&self) -> std::vec::IntoIter<(u64, T)> {
+        self.range_cloned(..).into_iter()
+    }
+
+    /// returns clones of the stored versions in the given range in ascending
+    /// order
+    ///
+    /// the versions are copied under a single read lock that is released
+    /// before returning
+    pub fn range_cloned<R>(&self, range: R) -> Vec<(u64, T)>
+    where
+        R: RangeBounds<u64>
+    {
+        self.store.read().range(range)
+            .map(|(k, v)| (*k, v.clone()))
+            .collect()
+    }
+}
+
+/// removes all values before the given version from the store returning
+/// them
+fn split_before<T>(store: &mut BTreeMap<u64, T>, version: &u64) -> BTreeMap<u64, T> {
+    let kept = store.split_off(version);
+
+    std::mem::replace(store, kept)
+}
+
+impl<T> std::default::Default for RwVersioned<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for RwVersioned<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RwVersioned")
+            .field("store", &self.store)
+            .field("count", &self.count.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+/// creates a snapshot of the store and count
+///
+/// the count is read while the store is locked for reading so the clone will
+/// not contain a partially applied update
+impl<T> Clone for RwVersioned<T>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        let store_reader = self.store.read();
+
+        RwVersioned {
+            store: RwLock::new(store_reader.clone()),
+            count: AtomicU64::new(self.count.load(Ordering::Acquire)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+use serde::{
+    ser::{
+        Serialize,
+        Serializer,
+        SerializeStruct,
+    },
+    de::{
+        self,
+        Deserialize,
+        Deserializer,
+        Visitor,
+        MapAccess,
+        SeqAccess,
+    }
+};
+
+/// serialized the same as the RwVersioned in the sync module
+#[cfg(feature = "serde")]
+impl<T> Serialize for RwVersioned<T>
+where
+    T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        let store_reader = self.store.read();
+
+        let mut state = serializer.serialize_struct("RwVersioned", 2)?;
+        state.serialize_field("store", &*store_reader)?;
+        state.serialize_field("count", &self.count.load(Ordering::Acquire))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for RwVersioned<T>
+where
+    T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        const STRUCT_FIELDS: &[&str] = &["store", "count"];
+
+        enum StructField {
+            Store,
+            Count,
+        }
+
+        impl<'de> Deserialize<'de> for StructField {
+            fn deserialize<D>(deserializer: D) -> Result<StructField, D::Error>
+            where
+                D: Deserializer<'de>
+            {
+                struct StructFieldVisitor;
+
+                impl<'de> Visitor<'de> for StructFieldVisitor {
+                    type Value = StructField;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("'store' or 'count'")
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+                    where
+                        E: de::Error
+                    {
+                        match value {
+                            "store" => Ok(StructField::Store),
+                            "count" => Ok(StructField::Count),
+                            _ => Err(de::Error::unknown_field(value, STRUCT_FIELDS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(StructFieldVisitor)
+            }
+        }
+
+        struct RwVersionedVisitor<T> {
+            _type: std::marker::PhantomData<T>
+        }
+
+        impl<'de, T> Visitor<'de> for RwVersionedVisitor<T>
+        where
+            T: Deserialize<'de>
+        {
+            type Value = RwVersioned<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct RwVersioned")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: SeqAccess<'de>
+            {
+                let store = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let count = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                Ok(RwVersioned {
+                    store: RwLock::new(store),
+                    count: AtomicU64::new(count),
+                })
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>
+            {
+                let mut store = None;
+                let mut count = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        StructField::Store => {
+                            if store.is_some() {
+                                return Err(de::Error::duplicate_field("store"));
+                            }
+
+                            store = Some(map.next_value()?);
+                        }
+                        StructField::Count => {
+                            if count.is_some() {
+                                return Err(de::Error::duplicate_field("count"));
+                            }
+
+                            count = Some(map.next_value()?);
+                        }
+                    }
+                }
+
+                let store = store.ok_or_else(|| de::Error::missing_field("store"))?;
+                let count = count.ok_or_else(|| de::Error::missing_field("count"))?;
+
+                Ok(RwVersioned {
+                    store: RwLock::new(store),
+                    count: AtomicU64::new(count),
+                })
+            }
+        }
+
+        deserializer.deserialize_struct(
+            "RwVersioned",
+            STRUCT_FIELDS,
+            RwVersionedVisitor {
+                _type: std::marker::PhantomData
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn get() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1);
+        let version = store.update(2);
+        store.update(3);
+
+        assert_eq!(store.get(&version).as_deref(), Some(&2));
+        assert_eq!(store.latest().as_deref(), Some(&3));
+        assert_eq!(store.drop(&version), Some(2));
+        assert!(store.get(&version).is_none());
+        assert_eq!(store.count(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json() {
+        let versioned: RwVersioned<u64> = RwVersioned::new();
+        versioned.update(5);
+        let drop = versioned.update(3);
+        versioned.update(7);
+
+        versioned.drop(&drop);
+
+        let to_json = serde_json::to_string(&versioned)
+            .expect("failed to serialize to json string");
+
+        let and_back: RwVersioned<u64> = serde_json::from_str(&to_json)
+            .expect("failed to deserialize from json string");
+
+        assert_eq!(*versioned.store(), *and_back.store());
+        assert_eq!(versioned.count(), and_back.count());
+    }
+}
@@ -0,0 +1,537 @@
+use core::fmt;
+use core::ops::Range;
+use alloc::vec::Vec;
+
+use super::Versioned;
+
+/// possible errors when applying a Patch
+pub enum Error {
+    /// both stores have created versions starting from the given version
+    /// number so the values under the same version numbers can differ
+    Conflict(u64),
+    /// the patch starts after the local count so the versions from the
+    /// local count up to the start of the patch would be missing
+    Missing(u64),
+    /// the patch contains a version or removed range outside of since and
+    /// count or out of order
+    OutOfRange(u64),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Conflict(version) => write!(f, "Conflict({})", version),
+            Error::Missing(version) => write!(f, "Missing({})", version),
+            Error::OutOfRange(version) => write!(f, "OutOfRange({})", version),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Conflict(version) => write!(f, "Conflict({})", version),
+            Error::Missing(version) => write!(f, "Missing({})", version),
+            Error::OutOfRange(version) => write!(f, "OutOfRange({})", version),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// the changes made to a Versioned since a given version
+///
+/// created by Versioned::changes_since and applied with
+/// Versioned::apply_patch. removals are sent as the ranges of version numbers
+/// below the count that are not stored so a receiver will drop them no
+/// matter when they were removed.
+pub struct Patch<T> {
+    since: u64,
+    count: u64,
+    removed: Vec<Range<u64>>,
+    versions: Vec<(u64, T)>,
+}
+
+impl<T> Patch<T> {
+    /// returns the version the patch starts from
+    pub fn since(&self) -> &u64 {
+        &self.since
+    }
+
+    /// returns the count of the Versioned the patch was created from
+    pub fn count(&self) -> &u64 {
+        &self.count
+    }
+
+    /// returns the ranges of version numbers that are not stored
+    pub fn removed(&self) -> &[Range<u64>] {
+        &self.removed
+    }
+
+    /// returns the versions stored at or after the start of the patch
+    pub fn versions(&self) -> &[(u64, T)] {
+        &self.versions
+    }
+
+    /// checks if the patch contains new versions
+    pub fn has_versions(&self) -> bool {
+        self.count > self.since
+    }
+
+    /// checks that the versions are ascending and between since and count
+    /// and that the removed ranges are ascending and below count
+    ///
+    /// returns the first version that is out of place
+    fn check_bounds(&self) -> Result<(), u64> {
+        let mut expected = self.since;
+
+        for (version, _) in &self.versions {
+            if *version < expected || *version >= self.count {
+                return Err(*version);
+            }
+
+            expected = *version + 1;
+        }
+
+        let mut expected = 0;
+
+        for range in &self.removed {
+            if range.start < expected || range.start > range.end || range.end > self.count {
+                return Err(range.start);
+            }
+
+            expected = range.end;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Versioned<T>
+where
+    T: Clone
+{
+    /// creates a patch containing all versions at or after the given version
+    /// along with the version numbers that have been removed
+    ///
+    /// pass the count of the receiving Versioned so that it only gets the
+    /// versions it has not seen
+    pub fn changes_since(&self, version: u64) -> Patch<T> {
+        let since = version.min(self.count);
+        let mut removed = Vec::new();
+        let mut versions = Vec::new();
+        let mut expected = 0;
+
+        for (key, value) in self.store.iter() {
+            if *key != expected {
+                removed.push(expected..*key);
+            }
+
+            if *key >= since {
+                versions.push((*key, value.clone()));
+            }
+
+            expected = *key + 1;
+        }
+
+        if expected < self.count {
+            removed.push(expected..self.count);
+        }
+
+        Patch {
+            since,
+            count: self.count,
+            removed,
+            versions,
+        }
+    }
+}
+
+impl<T> Versioned<T> {
+    /// applies a patch created from another Versioned
+    ///
+    /// the removed versions are dropped, the new versions are stored and the
+    /// count is moved up to the count of the patch. listeners are invoked for
+    /// every removed and stored version. patches with versions or removed
+    /// ranges outside of the patch are rejected with OutOfRange. nothing is
+    /// changed if an error is returned.
+    pub fn apply_patch(&mut self, patch: Patch<T>) -> Result<(), Error> {
+        patch.check_bounds().map_err(Error::OutOfRange)?;
+
+        if self.count < patch.since {
+            return Err(Error::Missing(self.count));
+        }
+
+        if self.count > patch.since && patch.has_versions() {
+            return Err(Error::Conflict(patch.since));
+        }
+
+        for range in patch.removed {
+            let versions: Vec<u64> = self.store.range(range)
+                .map(|(k, _)| *k)
+                .collect();
+
+            for version in versions {
+                self.remove(&version);
+            }
+        }
+
+        for (version, value) in patch.versions {
+            self.store.insert(version, value);
+
+            if let Some(value) = self.store.get(&version) {
+                self.listeners.updated(version, value);
+            }
+        }
+
+        self.count = self.count.max(patch.count);
+
+        Ok(())
+    }
+}
+
+impl<T> fmt::Debug for Patch<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Patch")
+            .field("since", &self.since)
+            .field("count", &self.count)
+            .field("removed", &self.removed)
+            .field("versions", &self.versions)
+            .finish()
+    }
+}
+
+impl<T> Clone for Patch<T>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        Patch {
+            since: self.since,
+            count: self.count,
+            removed: self.removed.clone(),
+            versions: self.versions.clone(),
+        }
+    }
+}
+
+impl<T> PartialEq for Patch<T>
+where
+    T: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.since == other.since &&
+            self.count == other.count &&
+            self.removed == other.removed &&
+            self.versions == other.versions
+    }
+}
+
+#[cfg(feature = "serde")]
+use serde::{
+    ser::{
+        Serialize,
+        Serializer,
+        SerializeStruct,
+    },
+    de::{
+        self,
+        Deserialize,
+        Deserializer,
+        Visitor,
+        MapAccess,
+        SeqAccess,
+    }
+};
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for Patch<T>
+where
+    T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        let mut state = serializer.serialize_struct("Patch", 4)?;
+        state.serialize_field("since", &self.since)?;
+        state.serialize_field("count", &self.count)?;
+        state.serialize_field("removed", &self.removed)?;
+        state.serialize_field("versions", &self.versions)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Patch<T>
+where
+    T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        const STRUCT_FIELDS: &[&str] = &["since", "count", "removed", "versions"];
+
+        enum StructField {
+            Since,
+            Count,
+            Removed,
+            Versions,
+        }
+
+        impl<'de> Deserialize<'de> for StructField {
+            fn deserialize<D>(deserializer: D) -> Result<StructField, D::Error>
+            where
+                D: Deserializer<'de>
+            {
+                struct StructFieldVisitor;
+
+                impl<'de> Visitor<'de> for StructFieldVisitor {
+                    type Value = StructField;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("'since', 'count', 'removed' or 'versions'")
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+                    where
+                        E: de::Error
+                    {
+                        match value {
+                            "since" => Ok(StructField::Since),
+                            "count" => Ok(StructField::Count),
+                            "removed" => Ok(StructField::Removed),
+                            "versions" => Ok(StructField::Versions),
+                            _ => Err(de::Error::unknown_field(value, STRUCT_FIELDS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(StructFieldVisitor)
+            }
+        }
+
+        /// checks that the versions and removed ranges are within the patch
+        fn validate<T, E>(patch: Patch<T>) -> Result<Patch<T>, E>
+        where
+            E: de::Error
+        {
+            match patch.check_bounds() {
+                Ok(()) => Ok(patch),
+                Err(version) => Err(de::Error::invalid_value(
+                    de::Unexpected::Unsigned(version),
+                    &"ascending versions and removed ranges between since and count"
+                )),
+            }
+        }
+
+        struct PatchVisitor<T> {
+            _type: core::marker::PhantomData<T>
+        }
+
+        impl<'de, T> Visitor<'de> for PatchVisitor<T>
+        where
+            T: Deserialize<'de>
+        {
+            type Value = Patch<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct Patch")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: SeqAccess<'de>
+            {
+                let since = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let count = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let removed = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let versions = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+
+                validate(Patch { since, count, removed, versions })
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>
+            {
+                let mut since = None;
+                let mut count = None;
+                let mut removed = None;
+                let mut versions = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        StructField::Since => {
+                            if since.is_some() {
+                                return Err(de::Error::duplicate_field("since"));
+                            }
+
+                            since = Some(map.next_value()?);
+                        }
+                        StructField::Count => {
+                            if count.is_some() {
+                                return Err(de::Error::duplicate_field("count"));
+                            }
+
+                            count = Some(map.next_value()?);
+                        }
+                        StructField::Removed => {
+                            if removed.is_some() {
+                                return Err(de::Error::duplicate_field("removed"));
+                            }
+
+                            removed = Some(map.next_value()?);
+                        }
+                        StructField::Versions => {
+                            if versions.is_some() {
+                                return Err(de::Error::duplicate_field("versions"));
+                            }
+
+                            versions = Some(map.next_value()?);
+                        }
+                    }
+                }
+
+                let since = since.ok_or_else(|| de::Error::missing_field("since"))?;
+                let count = count.ok_or_else(|| de::Error::missing_field("count"))?;
+                let removed = removed.ok_or_else(|| de::Error::missing_field("removed"))?;
+                let versions = versions.ok_or_else(|| de::Error::missing_field("versions"))?;
+
+                validate(Patch { since, count, removed, versions })
+            }
+        }
+
+        deserializer.deserialize_struct(
+            "Patch",
+            STRUCT_FIELDS,
+            PatchVisitor {
+                _type: core::marker::PhantomData
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sync() {
+        let mut remote: Versioned<u64> = Versioned::new();
+        let mut local: Versioned<u64> = Versioned::new();
+
+        remote.update_many([1, 2, 3]);
+        local.apply_patch(remote.changes_since(*local.count()))
+            .expect("failed to apply first patch");
+
+        assert_eq!(local, remote);
+
+        remote.remove(&1);
+        remote.update_many([4, 5]);
+        remote.remove(&4);
+
+        let patch = remote.changes_since(*local.count());
+
+        assert_eq!(patch.removed(), &[1..2, 4..5]);
+        assert_eq!(patch.versions(), &[(3, 4)]);
+
+        local.apply_patch(patch)
+            .expect("failed to apply second patch");
+
+        assert_eq!(local, remote);
+    }
+
+    #[test]
+    fn conflict() {
+        let mut remote: Versioned<u64> = Versioned::new();
+        let mut local: Versioned<u64> = Versioned::new();
+
+        remote.update(1);
+        local.apply_patch(remote.changes_since(0))
+            .expect("failed to apply patch");
+
+        let since = *local.count();
+        remote.update(2);
+        local.update(3);
+
+        match local.apply_patch(remote.changes_since(since)) {
+            Err(Error::Conflict(version)) => assert_eq!(version, since),
+            result => panic!("unexpected apply result: {:?}", result),
+        }
+
+        let mut behind: Versioned<u64> = Versioned::new();
+
+        match behind.apply_patch(remote.changes_since(since)) {
+            Err(Error::Missing(version)) => assert_eq!(version, 0),
+            result => panic!("unexpected apply result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut local: Versioned<u64> = Versioned::new();
+
+        // far off versions inside the patch are stored without filling the
+        // gap before them
+        let far = Patch {
+            since: 0,
+            count: (1 << 40) + 1,
+            removed: vec![0..1, 2..(1 << 40)],
+            versions: vec![(1 << 40, 1)],
+        };
+
+        local.apply_patch(far).expect("failed to apply far off patch");
+
+        assert_eq!(local.len(), 1);
+        assert_eq!(*local.count(), (1 << 40) + 1);
+
+        let mut local: Versioned<u64> = Versioned::new();
+        let outside = Patch {
+            since: 0,
+            count: 2,
+            removed: Vec::new(),
+            versions: vec![(0, 1), (u64::MAX - 1, 2)],
+        };
+
+        match local.apply_patch(outside) {
+            Err(Error::OutOfRange(version)) => assert_eq!(version, u64::MAX - 1),
+            result => panic!("unexpected apply result: {:?}", result),
+        }
+
+        let removed = Patch::<u64> {
+            since: 0,
+            count: 2,
+            removed: vec![0..1, 1..u64::MAX],
+            versions: Vec::new(),
+        };
+
+        assert!(matches!(local.apply_patch(removed), Err(Error::OutOfRange(1))));
+        assert!(local.is_empty() && *local.count() == 0, "local was changed");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json() {
+        let mut remote: Versioned<u64> = Versioned::new();
+        remote.update_many([1, 2, 3]);
+        remote.remove(&0);
+
+        let patch = remote.changes_since(1);
+
+        let to_json = serde_json::to_string(&patch)
+            .expect("failed to serialize to json string");
+        let and_back: Patch<u64> = serde_json::from_str(&to_json)
+            .expect("failed to deserialize from json string");
+
+        assert_eq!(patch, and_back);
+    }
+}
@@ -0,0 +1,276 @@
+use std::time::{Duration, Instant};
+
+use super::Versioned;
+
+/// a file-sys wrapper that holds a Versioned and can save it
+///
+/// implemented for the file-sys wrappers that are enabled with the
+/// persist-json, persist-binary and persist-encrypted features
+pub trait Persist<T> {
+    /// the error returned when saving fails
+    type Error;
+
+    /// returns a reference to the wrapped Versioned
+    fn versioned(&self) -> &Versioned<T>;
+
+    /// returns a mutable reference to the wrapped Versioned
+    fn versioned_mut(&mut self) -> &mut Versioned<T>;
+
+    /// saves the wrapped Versioned to the file
+    fn save(&self) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "persist-json")]
+impl<T> Persist<T> for file_sys::wrapper::Json<Versioned<T>>
+where
+    T: serde::Serialize
+{
+    type Error = file_sys::wrapper::json::Error;
+
+    fn versioned(&self) -> &Versioned<T> {
+        self.inner()
+    }
+
+    fn versioned_mut(&mut self) -> &mut Versioned<T> {
+        self.inner_mut()
+    }
+
+    fn save(&self) -> Result<(), Self::Error> {
+        file_sys::wrapper::Json::save(self)
+    }
+}
+
+#[cfg(feature = "persist-binary")]
+impl<T> Persist<T> for file_sys::wrapper::Binary<Versioned<T>>
+where
+    T: serde::Serialize
+{
+    type Error = file_sys::wrapper::binary::Error;
+
+    fn versioned(&self) -> &Versioned<T> {
+        self.inner()
+    }
+
+    fn versioned_mut(&mut self) -> &mut Versioned<T> {
+        self.inner_mut()
+    }
+
+    fn save(&self) -> Result<(), Self::Error> {
+        file_sys::wrapper::Binary::save(self)
+    }
+}
+
+#[cfg(feature = "persist-encrypted")]
+impl<T> Persist<T> for file_sys::wrapper::Encrypted<Versioned<T>>
+where
+    T: serde::Serialize
+{
+    type Error = file_sys::wrapper::encrypted::Error;
+
+    fn versioned(&self) -> &Versioned<T> {
+        self.inner()
+    }
+
+    fn versioned_mut(&mut self) -> &mut Versioned<T> {
+        self.inner_mut()
+    }
+
+    fn save(&self) -> Result<(), Self::Error> {
+        file_sys::wrapper::Encrypted::save(self)
+    }
+}
+
+/// a Versioned that is saved to a file after every change
+///
+/// if a debounce is set then saves will only happen once the debounce has
+/// passed since the last save. changes made during the debounce are saved on
+/// the next change after it has passed, when calling flush, or when dropped.
+/// errors from saving on drop are ignored so call flush before dropping if
+/// they need to be handled.
+pub struct Persisted<T, W>
+where
+    W: Persist<T>
+{
+    // only None once into_wrapper has taken it
+    wrapper: Option<W>,
+    debounce: Option<Duration>,
+    last_save: Option<Instant>,
+    dirty: bool,
+    _type: std::marker::PhantomData<T>,
+}
+
+impl<T, W> Persisted<T, W>
+where
+    W: Persist<T>
+{
+    /// creates a new Persisted that saves after every change
+    pub fn new(wrapper: W) -> Self {
+        Persisted {
+            wrapper: Some(wrapper),
+            debounce: None,
+            last_save: None,
+            dirty: false,
+            _type: std::marker::PhantomData,
+        }
+    }
+
+    /// creates a new Persisted that will wait the given debounce between
+    /// saves
+    pub fn with_debounce(wrapper: W, debounce: Duration) -> Self {
+        Persisted {
+            wrapper: Some(wrapper),
+            debounce: Some(debounce),
+            last_save: None,
+            dirty: false,
+            _type: std::marker::PhantomData,
+        }
+    }
+
+    /// returns the current debounce
+    pub fn debounce(&self) -> Option<&Duration> {
+        self.debounce.as_ref()
+    }
+
+    /// updates the current debounce
+    pub fn set_debounce(&mut self, debounce: Option<Duration>) {
+        self.debounce = debounce;
+    }
+
+    /// returns a reference to the wrapper
+    pub fn wrapper(&self) -> &W {
+        self.wrapper.as_ref().expect("wrapper taken before drop")
+    }
+
+    /// returns a reference to the Versioned
+    pub fn versioned(&self) -> &Versioned<T> {
+        self.wrapper().versioned()
+    }
+
+    /// checks if there are changes that have not been saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// updates the value returning the version number used
+    ///
+    /// the value is stored even if the save fails
+    pub fn update(&mut self, value: T) -> Result<u64, W::Error> {
+        let version = self.wrapper_mut().versioned_mut().update(value);
+
+        self.changed()?;
+
+        Ok(version)
+    }
+
+    /// drops the desired version returning the value found
+    ///
+    /// the file is only saved if the version was found
+    pub fn remove(&mut self, version: &u64) -> Result<Option<T>, W::Error> {
+        let rtn = self.wrapper_mut().versioned_mut().remove(version);
+
+        if rtn.is_some() {
+            self.changed()?;
+        }
+
+        Ok(rtn)
+    }
+
+    /// saves the Versioned if there are unsaved changes
+    pub fn flush(&mut self) -> Result<(), W::Error> {
+        if self.dirty {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// consumes the struct returning the wrapper
+    ///
+    /// unsaved changes are flushed before returning
+    pub fn into_wrapper(mut self) -> Result<W, W::Error> {
+        self.flush()?;
+
+        Ok(self.wrapper.take().expect("wrapper taken before drop"))
+    }
+
+    fn wrapper_mut(&mut self) -> &mut W {
+        self.wrapper.as_mut().expect("wrapper taken before drop")
+    }
+
+    fn changed(&mut self) -> Result<(), W::Error> {
+        self.dirty = true;
+
+        if let (Some(debounce), Some(last_save)) = (self.debounce, self.last_save) {
+            if last_save.elapsed() < debounce {
+                return Ok(());
+            }
+        }
+
+        self.save()
+    }
+
+    fn save(&mut self) -> Result<(), W::Error> {
+        self.wrapper().save()?;
+        self.last_save = Some(Instant::now());
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+impl<T, W> Drop for Persisted<T, W>
+where
+    W: Persist<T>
+{
+    fn drop(&mut self) {
+        if self.wrapper.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+impl<T> Versioned<T> {
+    /// wraps the Versioned with the given file-sys wrapper so that it will be
+    /// saved after every update or remove
+    ///
+    /// ```ignore
+    /// let persisted = versioned.persist_to(|v| Json::new(v, "history.json"));
+    /// ```
+    pub fn persist_to<W, F>(self, wrap: F) -> Persisted<T, W>
+    where
+        W: Persist<T>,
+        F: FnOnce(Self) -> W
+    {
+        Persisted::new(wrap(self))
+    }
+}
+
+#[cfg(all(test, feature = "persist-json"))]
+mod test {
+    use super::*;
+    use file_sys::wrapper::Json;
+
+    #[test]
+    fn json() {
+        let file_name = "test.persist.json";
+
+        std::fs::File::create(file_name).expect("failed to create test file");
+
+        let mut persisted = Versioned::new()
+            .persist_to(|v| Json::new(v, file_name));
+        persisted.set_debounce(Some(Duration::from_secs(60)));
+
+        persisted.update(1u64).expect("failed to save update");
+        persisted.update(2u64).expect("failed to save update");
+
+        assert!(persisted.is_dirty(), "debounced update was saved");
+
+        let wrapper = persisted.into_wrapper()
+            .expect("failed to flush changes");
+
+        let and_back: Json<Versioned<u64>> = Json::load(file_name)
+            .expect("failed to load json file");
+
+        assert_eq!(wrapper.inner(), and_back.inner());
+    }
+}
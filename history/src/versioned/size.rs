@@ -0,0 +1,77 @@
+use core::mem::size_of;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// reports the number of bytes a value owns on the heap
+///
+/// used by Versioned::approx_memory_usage to estimate the memory used by
+/// stored values. the size of the value itself is already accounted for so
+/// only additional allocations should be reported.
+pub trait HeapSize {
+    /// returns the approximate bytes allocated by this value
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! no_heap {
+    ($($t:ty),*) => {
+        $(
+            impl HeapSize for $t {
+                #[inline]
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+no_heap!(
+    (), bool, char,
+    u8, u16, u32, u64, u128, usize,
+    i8, i16, i32, i64, i128, isize,
+    f32, f64
+);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T> HeapSize for Vec<T>
+where
+    T: HeapSize
+{
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T> HeapSize for Box<T>
+where
+    T: HeapSize
+{
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + self.as_ref().heap_size()
+    }
+}
+
+impl<T> HeapSize for Option<T>
+where
+    T: HeapSize
+{
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<A, B> HeapSize for (A, B)
+where
+    A: HeapSize,
+    B: HeapSize
+{
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
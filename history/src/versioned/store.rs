@@ -0,0 +1,851 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::collections::{btree_map, vec_deque};
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+/// the number of empty slots a dense store can hold beyond twice the number
+/// of stored values
+const DENSE_SLACK: u64 = 64;
+
+/// checks if a dense store spanning the given number of versions is small
+/// enough for the number of stored values
+#[inline]
+fn fits_dense(span: u64, len: usize) -> bool {
+    span <= (len as u64).saturating_mul(2).saturating_add(DENSE_SLACK)
+}
+
+/// checks if a sparse store spanning the given number of versions should
+/// switch back to dense
+///
+/// lower than fits_dense so a store near the limit does not switch back and
+/// forth on every change
+#[inline]
+fn prefers_dense(span: u64, len: usize) -> bool {
+    span <= (len as u64).saturating_add(len as u64 / 2)
+}
+
+/// returns the number of versions from first to last
+#[inline]
+fn span(first: u64, last: u64) -> u64 {
+    (last - first).saturating_add(1)
+}
+
+/// converts the bounds of a range to an inclusive start and end
+///
+/// returns None if the range is empty
+fn inclusive<R>(range: &R) -> Option<(u64, u64)>
+where
+    R: RangeBounds<u64>
+{
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(start) => start.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => *end,
+        Bound::Excluded(end) => end.checked_sub(1)?,
+        Bound::Unbounded => u64::MAX,
+    };
+
+    if start > end {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+enum Inner<T> {
+    /// slots for each version starting at base. the first and last slots
+    /// are always occupied unless the list is empty
+    Dense {
+        base: u64,
+        slots: VecDeque<Option<(u64, T)>>,
+        len: usize,
+    },
+    Sparse(BTreeMap<u64, T>),
+}
+
+/// storage for the versions of a Versioned
+///
+/// versions are handed out sequentially so values are kept in a VecDeque
+/// indexed by `version - base` where base is the oldest stored version,
+/// making get and latest O(1). removed versions leave an empty slot behind
+/// that is trimmed once it reaches either end of the list.
+///
+/// once the span between the oldest and newest version grows well past the
+/// number of stored values, from removals or from sparse version numbers,
+/// the values move to a BTreeMap so memory stays proportional to the number
+/// of values. they move back once the versions are close together again.
+pub struct Store<T> {
+    inner: Inner<T>,
+}
+
+impl<T> Store<T> {
+    /// creates an empty store
+    pub fn new() -> Self {
+        Store {
+            inner: Inner::Dense {
+                base: 0,
+                slots: VecDeque::new(),
+                len: 0,
+            }
+        }
+    }
+
+    /// returns total stored values
+    pub fn len(&self) -> usize {
+        match &self.inner {
+            Inner::Dense { len, .. } => *len,
+            Inner::Sparse(map) => map.len(),
+        }
+    }
+
+    /// checks if there are no stored values
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// checks if the values are kept in a BTreeMap instead of being indexed
+    /// by version
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.inner, Inner::Sparse(_))
+    }
+
+    /// checks if the given version is stored
+    pub fn contains_key(&self, version: &u64) -> bool {
+        self.get(version).is_some()
+    }
+
+    /// returns a reference to the desired version
+    pub fn get(&self, version: &u64) -> Option<&T> {
+        match &self.inner {
+            Inner::Dense { base, slots, .. } => {
+                let index = dense_index(*base, slots.len(), version)?;
+
+                slots[index].as_ref().map(|(_, v)| v)
+            }
+            Inner::Sparse(map) => map.get(version),
+        }
+    }
+
+    /// returns a mutable reference to the desired version
+    pub fn get_mut(&mut self, version: &u64) -> Option<&mut T> {
+        match &mut self.inner {
+            Inner::Dense { base, slots, .. } => {
+                let index = dense_index(*base, slots.len(), version)?;
+
+                slots[index].as_mut().map(|(_, v)| v)
+            }
+            Inner::Sparse(map) => map.get_mut(version),
+        }
+    }
+
+    /// returns the oldest version and value
+    pub fn first_key_value(&self) -> Option<(&u64, &T)> {
+        match &self.inner {
+            Inner::Dense { slots, .. } => slots.front()
+                .and_then(|slot| slot.as_ref())
+                .map(|(k, v)| (k, v)),
+            Inner::Sparse(map) => map.first_key_value(),
+        }
+    }
+
+    /// returns the newest version and value
+    pub fn last_key_value(&self) -> Option<(&u64, &T)> {
+        match &self.inner {
+            Inner::Dense { slots, .. } => slots.back()
+                .and_then(|slot| slot.as_ref())
+                .map(|(k, v)| (k, v)),
+            Inner::Sparse(map) => map.last_key_value(),
+        }
+    }
+
+    /// inserts the value at the given version returning the previous value
+    /// if one was present
+    pub fn insert(&mut self, version: u64, value: T) -> Option<T> {
+        let Inner::Dense { base, slots, len } = &mut self.inner else {
+            let Inner::Sparse(map) = &mut self.inner else {
+                unreachable!();
+            };
+
+            let rtn = map.insert(version, value);
+            self.rebalance();
+
+            return rtn;
+        };
+
+        if slots.is_empty() {
+            *base = version;
+            slots.push_back(Some((version, value)));
+            *len = 1;
+
+            return None;
+        }
+
+        let last = *base + (slots.len() as u64 - 1);
+
+        if version < *base {
+            if !fits_dense(span(version, last), *len + 1) {
+                self.make_sparse();
+
+                return self.insert(version, value);
+            }
+
+            for _ in (version + 1)..*base {
+                slots.push_front(None);
+            }
+
+            slots.push_front(Some((version, value)));
+            *base = version;
+            *len += 1;
+
+            return None;
+        }
+
+        if version > last {
+            if !fits_dense(span(*base, version), *len + 1) {
+                self.make_sparse();
+
+                return self.insert(version, value);
+            }
+
+            slots.resize_with((version - *base) as usize, || None);
+            slots.push_back(Some((version, value)));
+            *len += 1;
+
+            return None;
+        }
+
+        let rtn = slots[(version - *base) as usize].replace((version, value));
+
+        if rtn.is_none() {
+            *len += 1;
+        }
+
+        rtn.map(|(_, v)| v)
+    }
+
+    /// removes the desired version returning the value found
+    pub fn remove(&mut self, version: &u64) -> Option<T> {
+        let rtn = match &mut self.inner {
+            Inner::Dense { base, slots, len } => {
+                let index = dense_index(*base, slots.len(), version)?;
+                let (_, rtn) = slots[index].take()?;
+
+                *len -= 1;
+
+                rtn
+            }
+            Inner::Sparse(map) => map.remove(version)?,
+        };
+
+        self.rebalance();
+
+        Some(rtn)
+    }
+
+    /// removes all values
+    pub fn clear(&mut self) {
+        *self = Store::new();
+    }
+
+    /// keeps only the versions that the callback returns true for
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&u64, &mut T) -> bool
+    {
+        match &mut self.inner {
+            Inner::Dense { slots, len, .. } => {
+                for slot in slots.iter_mut() {
+                    let keep = match slot {
+                        Some((k, v)) => f(k, v),
+                        None => true
+                    };
+
+                    if !keep {
+                        *slot = None;
+                        *len -= 1;
+                    }
+                }
+            }
+            Inner::Sparse(map) => map.retain(f),
+        }
+
+        self.rebalance();
+    }
+
+    /// splits the store at the given version
+    ///
+    /// returns everything after and including the given version
+    pub fn split_off(&mut self, version: &u64) -> Self {
+        let mut rtn = match &mut self.inner {
+            Inner::Dense { base, slots, len } => {
+                if *version <= *base {
+                    return core::mem::take(self);
+                }
+
+                let index = (*version - *base).min(slots.len() as u64) as usize;
+                let split = slots.split_off(index);
+                let split_len = split.iter().filter(|s| s.is_some()).count();
+
+                *len -= split_len;
+
+                Store {
+                    inner: Inner::Dense {
+                        base: *version,
+                        slots: split,
+                        len: split_len,
+                    }
+                }
+            }
+            Inner::Sparse(map) => Store {
+                inner: Inner::Sparse(map.split_off(version)),
+            },
+        };
+
+        self.rebalance();
+        rtn.rebalance();
+        rtn
+    }
+
+    /// returns the bytes allocated for the values of the store
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        match &self.inner {
+            Inner::Dense { slots, .. } => {
+                slots.capacity() * core::mem::size_of::<Option<(u64, T)>>()
+            }
+            Inner::Sparse(map) => map.len() * core::mem::size_of::<(u64, T)>(),
+        }
+    }
+
+    /// returns an iterator of versions and values from oldest to newest
+    pub fn iter(&self) -> Iter<'_, T> {
+        match &self.inner {
+            Inner::Dense { slots, len, .. } => Iter::Dense {
+                slots: slots.iter(),
+                remaining: *len,
+            },
+            Inner::Sparse(map) => Iter::Sparse(map.iter()),
+        }
+    }
+
+    /// returns an iterator of the versions and values in the range from
+    /// oldest to newest
+    pub fn range<R>(&self, range: R) -> Range<'_, T>
+    where
+        R: RangeBounds<u64>
+    {
+        let Some((start, end)) = inclusive(&range) else {
+            return Range::Empty;
+        };
+
+        match &self.inner {
+            Inner::Dense { base, slots, .. } => {
+                let count = slots.len() as u64;
+                let first = start.saturating_sub(*base).min(count);
+                let last = end.saturating_sub(*base).saturating_add(1).min(count);
+
+                if end < *base || first >= last {
+                    return Range::Empty;
+                }
+
+                Range::Dense(slots.range(first as usize..last as usize))
+            }
+            Inner::Sparse(map) => Range::Sparse(map.range(start..=end)),
+        }
+    }
+
+    /// returns an iterator of the stored versions from oldest to newest
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &u64> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// returns an iterator of the stored values from oldest to newest
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// trims empty slots and switches between dense and sparse based on the
+    /// span of the stored versions
+    fn rebalance(&mut self) {
+        match &mut self.inner {
+            Inner::Dense { base, slots, len } => {
+                while let Some(None) = slots.back() {
+                    slots.pop_back();
+                }
+
+                while let Some(None) = slots.front() {
+                    slots.pop_front();
+                    *base += 1;
+                }
+
+                if !fits_dense(slots.len() as u64, *len) {
+                    self.make_sparse();
+                }
+            }
+            Inner::Sparse(map) => {
+                let dense = match (map.first_key_value(), map.last_key_value()) {
+                    (Some((first, _)), Some((last, _))) => {
+                        prefers_dense(span(*first, *last), map.len())
+                    }
+                    _ => true,
+                };
+
+                if dense {
+                    self.make_dense();
+                }
+            }
+        }
+    }
+
+    /// moves the values from the slots to a BTreeMap
+    fn make_sparse(&mut self) {
+        if let Inner::Dense { slots, .. } = &mut self.inner {
+            let map = core::mem::take(slots).into_iter()
+                .flatten()
+                .collect();
+
+            self.inner = Inner::Sparse(map);
+        }
+    }
+
+    /// moves the values from the BTreeMap to slots
+    ///
+    /// only called once the span of the versions is known to be small
+    fn make_dense(&mut self) {
+        if let Inner::Sparse(map) = &mut self.inner {
+            let map = core::mem::take(map);
+            let base = map.first_key_value()
+                .map(|(k, _)| *k)
+                .unwrap_or(0);
+            let len = map.len();
+            let mut slots = VecDeque::with_capacity(len);
+
+            for (version, value) in map {
+                slots.resize_with((version - base) as usize, || None);
+                slots.push_back(Some((version, value)));
+            }
+
+            self.inner = Inner::Dense { base, slots, len };
+        }
+    }
+}
+
+/// returns the index of the version in the slots of a dense store
+#[inline]
+fn dense_index(base: u64, count: usize, version: &u64) -> Option<usize> {
+    let index = version.checked_sub(base)?;
+
+    if index < count as u64 {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+impl<T> core::default::Default for Store<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Store<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter())
+            .finish()
+    }
+}
+
+impl<T> Clone for Store<T>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            Inner::Dense { base, slots, len } => Inner::Dense {
+                base: *base,
+                slots: slots.clone(),
+                len: *len,
+            },
+            Inner::Sparse(map) => Inner::Sparse(map.clone()),
+        };
+
+        Store { inner }
+    }
+}
+
+impl<T> PartialEq for Store<T>
+where
+    T: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> Eq for Store<T>
+where
+    T: Eq
+{}
+
+impl<T> FromIterator<(u64, T)> for Store<T> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, T)>
+    {
+        let mut rtn = Store::new();
+        rtn.extend(iter);
+        rtn
+    }
+}
+
+impl<T> Extend<(u64, T)> for Store<T> {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (u64, T)>
+    {
+        for (version, value) in iter {
+            self.insert(version, value);
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Store<T> {
+    type Item = (&'a u64, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Store<T> {
+    type Item = (u64, T);
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.inner {
+            Inner::Dense { slots, len, .. } => IntoIter::Dense {
+                slots: slots.into_iter(),
+                remaining: len,
+            },
+            Inner::Sparse(map) => IntoIter::Sparse(map.into_iter()),
+        }
+    }
+}
+
+/// iterator over the versions and values of a Store
+///
+/// iterates from oldest to newest and implements DoubleEndedIterator for
+/// newest to oldest
+pub enum Iter<'a, T> {
+    #[doc(hidden)]
+    Dense {
+        slots: vec_deque::Iter<'a, Option<(u64, T)>>,
+        remaining: usize,
+    },
+    #[doc(hidden)]
+    Sparse(btree_map::Iter<'a, u64, T>),
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (&'a u64, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Dense { slots, remaining } => {
+                let (k, v) = slots.by_ref().flatten().next()?;
+
+                *remaining -= 1;
+
+                Some((k, v))
+            }
+            Iter::Sparse(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::Dense { remaining, .. } => (*remaining, Some(*remaining)),
+            Iter::Sparse(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Dense { slots, remaining } => {
+                let (k, v) = slots.by_ref().rev().flatten().next()?;
+
+                *remaining -= 1;
+
+                Some((k, v))
+            }
+            Iter::Sparse(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Iter::Dense { slots, remaining } => Iter::Dense {
+                slots: slots.clone(),
+                remaining: *remaining,
+            },
+            Iter::Sparse(iter) => Iter::Sparse(iter.clone()),
+        }
+    }
+}
+
+/// iterator over a range of the versions and values of a Store
+pub enum Range<'a, T> {
+    #[doc(hidden)]
+    Dense(vec_deque::Iter<'a, Option<(u64, T)>>),
+    #[doc(hidden)]
+    Sparse(btree_map::Range<'a, u64, T>),
+    #[doc(hidden)]
+    Empty,
+}
+
+impl<'a, T> Iterator for Range<'a, T> {
+    type Item = (&'a u64, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Range::Dense(slots) => slots.by_ref()
+                .flatten()
+                .next()
+                .map(|(k, v)| (k, v)),
+            Range::Sparse(iter) => iter.next(),
+            Range::Empty => None,
+        }
+    }
+}
+
+impl<T> DoubleEndedIterator for Range<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Range::Dense(slots) => slots.by_ref()
+                .rev()
+                .flatten()
+                .next()
+                .map(|(k, v)| (k, v)),
+            Range::Sparse(iter) => iter.next_back(),
+            Range::Empty => None,
+        }
+    }
+}
+
+/// owning iterator over the versions and values of a Store
+pub enum IntoIter<T> {
+    #[doc(hidden)]
+    Dense {
+        slots: vec_deque::IntoIter<Option<(u64, T)>>,
+        remaining: usize,
+    },
+    #[doc(hidden)]
+    Sparse(btree_map::IntoIter<u64, T>),
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = (u64, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Dense { slots, remaining } => {
+                let rtn = slots.by_ref().flatten().next()?;
+
+                *remaining -= 1;
+
+                Some(rtn)
+            }
+            IntoIter::Sparse(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IntoIter::Dense { remaining, .. } => (*remaining, Some(*remaining)),
+            IntoIter::Sparse(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Dense { slots, remaining } => {
+                let rtn = slots.by_ref().rev().flatten().next()?;
+
+                *remaining -= 1;
+
+                Some(rtn)
+            }
+            IntoIter::Sparse(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+#[cfg(feature = "serde")]
+use serde::{
+    ser::{
+        Serialize,
+        Serializer,
+        SerializeMap,
+    },
+    de::{
+        Deserialize,
+        Deserializer,
+        Visitor,
+        MapAccess,
+    }
+};
+
+/// serialized as a map of versions to values, the same as a BTreeMap
+#[cfg(feature = "serde")]
+impl<T> Serialize for Store<T>
+where
+    T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        let mut state = serializer.serialize_map(Some(self.len()))?;
+
+        for (k, v) in self.iter() {
+            state.serialize_entry(k, v)?;
+        }
+
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Store<T>
+where
+    T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        struct StoreVisitor<T> {
+            _type: core::marker::PhantomData<T>
+        }
+
+        impl<'de, T> Visitor<'de> for StoreVisitor<T>
+        where
+            T: Deserialize<'de>
+        {
+            type Value = Store<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of versions to values")
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>
+            {
+                let mut rtn = Store::new();
+
+                while let Some((version, value)) = map.next_entry()? {
+                    rtn.insert(version, value);
+                }
+
+                Ok(rtn)
+            }
+        }
+
+        deserializer.deserialize_map(StoreVisitor {
+            _type: core::marker::PhantomData
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_remove() {
+        let mut store: Store<u64> = Store::new();
+        store.insert(5, 1);
+        store.insert(7, 2);
+        store.insert(2, 3);
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(&7), Some(&2));
+        assert_eq!(store.get(&6), None);
+        assert_eq!(store.first_key_value(), Some((&2, &3)));
+
+        assert_eq!(store.remove(&2), Some(3));
+        assert_eq!(store.remove(&7), Some(2));
+        assert_eq!(store.remove(&7), None);
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.first_key_value(), Some((&5, &1)));
+        assert_eq!(store.last_key_value(), Some((&5, &1)));
+    }
+
+    #[test]
+    fn iter() {
+        let store: Store<u64> = [(1, 1), (3, 3), (4, 4)].into_iter().collect();
+
+        assert!(store.iter().map(|(k, _)| *k).eq([1, 3, 4]), "unexpected iterator order");
+        assert!(store.iter().rev().map(|(k, _)| *k).eq([4, 3, 1]), "unexpected reverse order");
+        assert_eq!(store.iter().len(), 3);
+        assert!(store.range(2..=4).map(|(k, _)| *k).eq([3, 4]), "unexpected range");
+        assert!(store.range(5..).next().is_none(), "range past the end was not empty");
+    }
+
+    #[test]
+    fn split_off() {
+        let mut store: Store<u64> = (0..10).map(|v| (v, v)).collect();
+
+        let after = store.split_off(&6);
+
+        assert!(store.iter().map(|(k, _)| *k).eq(0..6), "unexpected remaining versions");
+        assert!(after.iter().map(|(k, _)| *k).eq(6..10), "unexpected split versions");
+        assert_eq!(store.len(), 6);
+        assert_eq!(after.len(), 4);
+    }
+
+    #[test]
+    fn sparse() {
+        let mut store: Store<u64> = Store::new();
+        store.insert(0, 0);
+        store.insert(u64::MAX - 1, 1);
+
+        assert!(store.is_sparse(), "far apart versions were kept dense");
+        assert!(store.allocated_bytes() < 1024, "sparse versions allocated slots");
+        assert_eq!(store.get(&(u64::MAX - 1)), Some(&1));
+        assert!(store.range(1..).map(|(k, _)| *k).eq([u64::MAX - 1]), "unexpected range");
+
+        store.remove(&(u64::MAX - 1));
+
+        assert!(!store.is_sparse(), "store did not switch back to dense");
+
+        store.extend((1..100).map(|v| (v, v)));
+
+        for version in 1..99 {
+            store.remove(&version);
+        }
+
+        assert!(store.is_sparse(), "mostly empty slots were kept");
+        assert!(store.iter().map(|(k, _)| *k).eq([0, 99]));
+    }
+}
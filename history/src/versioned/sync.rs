@@ -1,30 +1,54 @@
 use std::collections::BTreeMap;
-use std::sync::{Mutex, RwLock};
-use std::sync::RwLockReadGuard;
-//use std::ptr::NonNull;
+use std::sync::{Mutex, RwLock, TryLockError};
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+use std::ops::{Bound, Range, RangeBounds};
 use std::fmt;
 
-/*
+use super::Versioned;
+use super::listener::Listeners;
+
 /// reference struct for the stored value
 ///
-/// contains the read guard from the rwlock in RwVersioned
+/// contains the read guard from the rwlock in RwVersioned. the value is
+/// looked up from the guard when accessed and will always exist since the
+/// store cannot be modified while the guard is held
 pub struct Value<'a, T> {
     reader: RwLockReadGuard<'a, BTreeMap<u64, T>>,
-    value: NonNull<T>
+    version: u64,
 }
 
 impl<'a, T> Value<'a, T> {
+    /// returns the version of the value
+    pub fn version(&self) -> &u64 {
+        &self.version
+    }
+
     /// returns reference to value
-    pub fn value(&self) -> &'a T {
-        unsafe { self.value.as_ref() }
+    pub fn value(&self) -> &T {
+        self.reader.get(&self.version)
+            .expect("version missing from locked store")
     }
 }
 
 impl<'a, T> std::ops::Deref for Value<'a, T> {
     type Target = T;
 
-    fn deref(&self) -> &'a Self::Target {
-        unsafe { self.value.as_ref() }
+    fn deref(&self) -> &Self::Target {
+        self.value()
+    }
+}
+
+impl<'a, T> fmt::Debug for Value<'a, T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Value")
+            .field("version", &self.version)
+            .field("value", self.value())
+            .finish()
     }
 }
 
@@ -32,37 +56,50 @@ impl<'a, T> std::ops::Deref for Value<'a, T> {
 ///
 /// contains the read guard from the rwlock in RwVersioned
 pub struct KeyValue<'a, T> {
-    reader: RwLockReadGuard<'a, BTreeMap<u64, T>>,
-    key: NonNull<u64>,
-    value: NonNull<T>,
+    value: Value<'a, T>,
 }
 
 impl<'a, T> KeyValue<'a, T> {
     /// returns reference to key
-    pub fn key(&self) -> &'a u64 {
-        unsafe { self.key.as_ref() }
+    pub fn key(&self) -> &u64 {
+        &self.value.version
     }
 
     /// returns reference to value
-    pub fn value(&self) -> &'a T {
-        unsafe { self.value.as_ref() }
+    pub fn value(&self) -> &T {
+        self.value.value()
+    }
+}
+
+impl<'a, T> fmt::Debug for KeyValue<'a, T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyValue")
+            .field("key", self.key())
+            .field("value", self.value())
+            .finish()
     }
 }
-*/
 
 /// possible errors from methods in RwVersioned
 pub enum Error {
-    /// the mutex containing count has been poisoned
-    CountPoisoned,
     /// the rwlock containing known versions has been poisoned
     StorePoisoned,
+    /// the expected version was not the latest version in the store,
+    /// contains the actual latest version
+    NotLatest(Option<u64>),
+    /// the count has reached u64::MAX so no more versions can be created
+    VersionExhausted,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::CountPoisoned => f.write_str("CountPoisoned"),
             Error::StorePoisoned => f.write_str("StorePoisoned"),
+            Error::NotLatest(latest) => write!(f, "NotLatest({:?})", latest),
+            Error::VersionExhausted => f.write_str("VersionExhausted"),
         }
     }
 }
@@ -70,21 +107,186 @@ impl fmt::Display for Error {
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::CountPoisoned => f.write_str("CountPoisoned"),
             Error::StorePoisoned => f.write_str("StorePoisoned"),
+            Error::NotLatest(latest) => write!(f, "NotLatest({:?})", latest),
+            Error::VersionExhausted => f.write_str("VersionExhausted"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+/// a change made to an RwVersioned sent to subscribers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// a new version was stored
+    Updated(u64),
+    /// the version was dropped
+    Dropped(u64),
+}
+
+/// registry of channels to send events to
+struct Subscribers {
+    senders: Mutex<Vec<mpsc::Sender<Event>>>,
+}
+
+impl Subscribers {
+    fn new() -> Self {
+        Subscribers {
+            senders: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn add(&self) -> mpsc::Receiver<Event> {
+        let (tx, rx) = mpsc::channel();
+
+        self.senders.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(tx);
+
+        rx
+    }
+
+    /// sends the event to all subscribers dropping any that have
+    /// disconnected
+    fn send(&self, event: Event) {
+        let mut senders = self.senders.lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        senders.retain(|tx| tx.send(event).is_ok());
+    }
+}
+
+/// the sender of the latest version and how to clone values for it
+///
+/// the clone function is stored so that update does not need T: Clone
+#[cfg(feature = "tokio")]
+struct Watch<T> {
+    tx: tokio::sync::watch::Sender<Option<(u64, T)>>,
+    clone: fn(&T) -> T,
+}
+
+/// counters used to create Stats
+#[cfg(feature = "metrics")]
+struct Metrics {
+    created: std::time::Instant,
+    updates: AtomicU64,
+    drops: AtomicU64,
+    write_locks: AtomicU64,
+    write_wait: AtomicU64,
+    max_write_wait: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    fn new() -> Self {
+        Metrics {
+            created: std::time::Instant::now(),
+            updates: AtomicU64::new(0),
+            drops: AtomicU64::new(0),
+            write_locks: AtomicU64::new(0),
+            write_wait: AtomicU64::new(0),
+            max_write_wait: AtomicU64::new(0),
+        }
+    }
+
+    fn waited(&self, wait: std::time::Duration) {
+        let nanos = u64::try_from(wait.as_nanos()).unwrap_or(u64::MAX);
+
+        self.write_locks.fetch_add(1, Ordering::Relaxed);
+        self.write_wait.fetch_add(nanos, Ordering::Relaxed);
+        self.max_write_wait.fetch_max(nanos, Ordering::Relaxed);
+    }
+}
+
+/// usage and contention statistics for an RwVersioned
+///
+/// created by RwVersioned::stats
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    elapsed: std::time::Duration,
+    updates: u64,
+    drops: u64,
+    write_locks: u64,
+    write_wait: std::time::Duration,
+    max_write_wait: std::time::Duration,
+    len: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl Stats {
+    /// returns the time since the RwVersioned was created
+    pub fn elapsed(&self) -> &std::time::Duration {
+        &self.elapsed
+    }
+
+    /// returns total versions stored
+    pub fn updates(&self) -> &u64 {
+        &self.updates
+    }
+
+    /// returns total versions removed
+    pub fn drops(&self) -> &u64 {
+        &self.drops
+    }
+
+    /// returns the average updates per second since creation
+    pub fn update_rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.updates as f64 / secs
+        }
+    }
+
+    /// returns total times the store was locked for writing
+    pub fn write_locks(&self) -> &u64 {
+        &self.write_locks
+    }
+
+    /// returns the total time spent waiting on the write lock
+    pub fn write_wait(&self) -> &std::time::Duration {
+        &self.write_wait
+    }
+
+    /// returns the longest time spent waiting on the write lock
+    pub fn max_write_wait(&self) -> &std::time::Duration {
+        &self.max_write_wait
+    }
+
+    /// returns the average time spent waiting on the write lock
+    pub fn avg_write_wait(&self) -> std::time::Duration {
+        match u32::try_from(self.write_locks) {
+            Ok(0) => std::time::Duration::ZERO,
+            Ok(locks) => self.write_wait / locks,
+            Err(_) => self.write_wait.div_f64(self.write_locks as f64),
+        }
+    }
+
+    /// returns total stored values when the stats were created
+    pub fn len(&self) -> &usize {
+        &self.len
+    }
+}
+
 /// stores changes to a given value and applies a counted number to each update
 ///
 /// values are stored in an RwLock that contains a BTreeMap and the counted
-/// version is a u64 stored behind a Mutex
+/// version is an AtomicU64. the count is only changed while the store is
+/// locked for writing so version numbers are handed out in the same order
+/// that values are inserted and a count that has been read will never be
+/// ahead of the store once the store can be locked
 pub struct RwVersioned<T> {
     store: RwLock<BTreeMap<u64, T>>,
-    count: Mutex<u64>,
+    count: AtomicU64,
+    subscribers: Subscribers,
+    #[cfg(feature = "tokio")]
+    watch: std::sync::OnceLock<Watch<T>>,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
 }
 
 impl<T> RwVersioned<T> {
@@ -92,18 +294,20 @@ impl<T> RwVersioned<T> {
     pub fn new() -> Self {
         RwVersioned {
             store: RwLock::new(BTreeMap::new()),
-            count: Mutex::new(0)
+            count: AtomicU64::new(0),
+            subscribers: Subscribers::new(),
+            #[cfg(feature = "tokio")]
+            watch: std::sync::OnceLock::new(),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::new(),
         }
     }
 
     /// retuns the next version number to use
     ///
-    /// locks the count aand returns a copied value
-    pub fn count(&self) -> Result<u64, Error> {
-        let count_lock = self.count.lock()
-            .map_err(|_| Error::CountPoisoned)?;
-
-        Ok(*count_lock)
+    /// does not lock the store
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Acquire)
     }
 
     /// returns read guard to current store
@@ -111,58 +315,319 @@ impl<T> RwVersioned<T> {
         self.store.read().map_err(|_| Error::StorePoisoned)
     }
 
+    /// locks the store for writing
+    ///
+    /// records the time spent waiting for the lock when metrics are enabled
+    fn write(&self) -> Result<RwLockWriteGuard<'_, BTreeMap<u64, T>>, Error> {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+
+        let store_writer = self.store.write()
+            .map_err(|_| Error::StorePoisoned)?;
+
+        #[cfg(feature = "metrics")]
+        self.metrics.waited(start.elapsed());
+
+        Ok(store_writer)
+    }
+
+    /// records the number of stored and removed versions when metrics are
+    /// enabled
+    #[inline]
+    fn record(&self, _updates: u64, _drops: u64) {
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.updates.fetch_add(_updates, Ordering::Relaxed);
+            self.metrics.drops.fetch_add(_drops, Ordering::Relaxed);
+        }
+    }
+
+    /// returns the current usage and contention statistics
+    ///
+    /// only locks the store for reading to get the length
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> Result<Stats, Error> {
+        let len = self.len()?;
+
+        Ok(Stats {
+            elapsed: self.metrics.created.elapsed(),
+            updates: self.metrics.updates.load(Ordering::Relaxed),
+            drops: self.metrics.drops.load(Ordering::Relaxed),
+            write_locks: self.metrics.write_locks.load(Ordering::Relaxed),
+            write_wait: std::time::Duration::from_nanos(self.metrics.write_wait.load(Ordering::Relaxed)),
+            max_write_wait: std::time::Duration::from_nanos(self.metrics.max_write_wait.load(Ordering::Relaxed)),
+            len,
+        })
+    }
+
+    /// returns total stored values
+    ///
+    /// only locks the store for reading
+    pub fn len(&self) -> Result<usize, Error> {
+        Ok(self.store()?.len())
+    }
+
+    /// checks if there are no stored values
+    ///
+    /// only locks the store for reading
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        Ok(self.store()?.is_empty())
+    }
+
+    /// checks if the given version is stored
+    ///
+    /// only locks the store for reading
+    pub fn contains(&self, version: &u64) -> Result<bool, Error> {
+        Ok(self.store()?.contains_key(version))
+    }
+
     /// updates the value returning the version number used
     ///
-    /// count will be locked first and incremented once the store has been
-    /// updated
+    /// the version number is taken from the count while the store is locked
+    /// for writing. the last version that can be used is u64::MAX - 1 after
+    /// which a VersionExhausted error is returned
     pub fn update(&self, value: T) -> Result<u64, Error> {
-        let mut count_lock = self.count.lock()
-            .map_err(|_| Error::CountPoisoned)?;
-        let new_version = *count_lock;
+        let mut store_writer = self.write()?;
+        let new_version = self.reserve(1)?;
 
-        {
-            let mut store_writer = self.store.write()
-                .map_err(|_| Error::StorePoisoned)?;
+        store_writer.insert(new_version, value);
+
+        self.subscribers.send(Event::Updated(new_version));
+        self.record(1, 0);
+        self.send_latest(&store_writer);
 
-            store_writer.insert(new_version, value);
+        Ok(new_version)
+    }
+
+    /// updates the value only if the latest stored version is the expected
+    /// version returning the version number used
+    ///
+    /// expected should be None if the store is expected to be empty. the
+    /// check and insert happen while holding the write lock so no other
+    /// update can happen in between. a NotLatest error containing the actual
+    /// latest version is returned if the check fails
+    pub fn update_if_latest(&self, expected: Option<u64>, value: T) -> Result<u64, Error> {
+        let mut store_writer = self.write()?;
+
+        let latest = store_writer.last_key_value().map(|(k, _)| *k);
+
+        if latest != expected {
+            return Err(Error::NotLatest(latest));
         }
 
-        *count_lock += 1;
+        let new_version = self.reserve(1)?;
+
+        store_writer.insert(new_version, value);
+
+        self.subscribers.send(Event::Updated(new_version));
+        self.record(1, 0);
+        self.send_latest(&store_writer);
 
         Ok(new_version)
     }
 
+    /// stores each value as a consecutive version returning the range of
+    /// versions used
+    ///
+    /// the store is locked once for the whole batch so readers will see
+    /// either none or all of the values. a VersionExhausted error is
+    /// returned without storing any value if the batch does not fit in the
+    /// remaining versions
+    pub fn update_many<I>(&self, values: I) -> Result<Range<u64>, Error>
+    where
+        I: IntoIterator<Item = T>
+    {
+        let values: Vec<T> = values.into_iter().collect();
+        let mut store_writer = self.write()?;
+        let start = self.reserve(values.len() as u64)?;
+        let end = start + values.len() as u64;
+
+        store_writer.extend((start..end).zip(values));
+
+        for version in start..end {
+            self.subscribers.send(Event::Updated(version));
+        }
+
+        self.send_latest(&store_writer);
+        self.record(end - start, 0);
+
+        Ok(start..end)
+    }
+
     /// drops the desired version returning the value found
     ///
     /// only locks the store
     pub fn drop(&self, version: &u64) -> Result<Option<T>, Error> {
-        let mut store_writer = self.store.write()
-            .map_err(|_| Error::StorePoisoned)?;
+        let mut store_writer = self.write()?;
+
+        let rtn = store_writer.remove(version);
+
+        if rtn.is_some() {
+            self.subscribers.send(Event::Dropped(*version));
+            self.send_latest(&store_writer);
+            self.record(0, 1);
+        }
 
-        Ok(store_writer.remove(version))
+        Ok(rtn)
     }
 
-    /*
-    /// returns a reference to the desired version
+    /// removes all versions newer than the given version and resets the
+    /// count to continue from it returning the removed versions in
+    /// ascending order
     ///
-    /// the struct returned contains the value and RwLockReadGuard used to
-    /// retrieve the value
-    pub fn get(&self, version: &u64) -> Result<Option<Value<'_, T>>, Error> {
-        let store_reader = self.store.read()
-            .map_err(|_| Error::StorePoisoned)?;
+    /// performed under a single write lock of the store so no updates can
+    /// happen in between. nothing is changed if the version is not less than
+    /// the count
+    pub fn rollback_to(&self, version: &u64) -> Result<Vec<(u64, T)>, Error> {
+        let mut store_writer = self.write()?;
+        let next = version.saturating_add(1);
+
+        if next >= self.count.load(Ordering::Acquire) {
+            return Ok(Vec::new());
+        }
+
+        let removed = store_writer.split_off(&next);
+        self.count.store(next, Ordering::Release);
+
+        for version in removed.keys() {
+            self.subscribers.send(Event::Dropped(*version));
+        }
 
-        let mut rtn = Value {
-            reader: store_reader,
-            value: NonNull::dangling(),
+        self.send_latest(&store_writer);
+        self.record(0, removed.len() as u64);
+
+        Ok(removed.into_iter().collect())
+    }
+
+    /// takes the given number of versions from the count returning the
+    /// first one
+    ///
+    /// must only be called while the store is locked for writing
+    fn reserve(&self, len: u64) -> Result<u64, Error> {
+        let start = self.count.load(Ordering::Acquire);
+
+        if u64::MAX - start < len {
+            return Err(Error::VersionExhausted);
+        }
+
+        self.count.store(start + len, Ordering::Release);
+
+        Ok(start)
+    }
+
+    /// clears the poisoned state of the store after a panic returning the
+    /// count
+    ///
+    /// the count is rebuilt from the largest stored version if it is not
+    /// already larger so that stored versions are not handed out again. the
+    /// store itself is left as is since every change to it is a single
+    /// insert or remove
+    pub fn recover(&self) -> u64 {
+        let store_writer = self.store.write()
+            .unwrap_or_else(|err| err.into_inner());
+
+        if let Some((version, _)) = store_writer.last_key_value() {
+            self.count.fetch_max(version.saturating_add(1), Ordering::AcqRel);
+        }
+
+        self.store.clear_poison();
+
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// sends the latest version to watchers if it has changed
+    #[cfg(feature = "tokio")]
+    fn send_latest(&self, store: &BTreeMap<u64, T>) {
+        let Some(watch) = self.watch.get() else {
+            return;
         };
 
-        let Some(value) = rtn.reader.get(version) else {
-            return Ok(None);
+        let latest = store.last_key_value();
+
+        watch.tx.send_if_modified(|current| {
+            if current.as_ref().map(|(k, _)| k) == latest.map(|(k, _)| k) {
+                return false;
+            }
+
+            *current = latest.map(|(k, v)| (*k, (watch.clone)(v)));
+
+            true
+        });
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    #[inline]
+    fn send_latest(&self, _store: &BTreeMap<u64, T>) {}
+
+    /// returns a receiver that will be sent an event after every successful
+    /// update or drop
+    ///
+    /// events are sent while the store is still locked so they will arrive
+    /// in the order the changes were made. dropping the receiver will
+    /// unsubscribe it on the next change
+    pub fn subscribe(&self) -> mpsc::Receiver<Event> {
+        self.subscribers.add()
+    }
+
+    /// removes all but the latest n versions returning the removed versions
+    /// in ascending order
+    ///
+    /// performed under a single write lock of the store
+    pub fn retain_latest(&self, n: usize) -> Result<Vec<(u64, T)>, Error> {
+        let mut store_writer = self.write()?;
+
+        let removed = if n == 0 {
+            std::mem::take(&mut *store_writer)
+        } else if let Some(version) = store_writer.keys().nth_back(n - 1).copied() {
+            split_before(&mut store_writer, &version)
+        } else {
+            return Ok(Vec::new());
         };
 
-        rtn.value = NonNull::from(value);
+        for version in removed.keys() {
+            self.subscribers.send(Event::Dropped(*version));
+        }
+
+        self.send_latest(&store_writer);
+        self.record(0, removed.len() as u64);
+
+        Ok(removed.into_iter().collect())
+    }
+
+    /// removes all versions older than the given version returning the
+    /// removed versions in ascending order
+    ///
+    /// performed under a single write lock of the store
+    pub fn remove_older_than(&self, version: &u64) -> Result<Vec<(u64, T)>, Error> {
+        let mut store_writer = self.write()?;
+
+        let removed = split_before(&mut store_writer, version);
+
+        for version in removed.keys() {
+            self.subscribers.send(Event::Dropped(*version));
+        }
+
+        self.send_latest(&store_writer);
+        self.record(0, removed.len() as u64);
+
+        Ok(removed.into_iter().collect())
+    }
+
+    /// returns a reference to the desired version
+    ///
+    /// the struct returned contains the RwLockReadGuard used to retrieve the
+    /// value so writers will be blocked until it is dropped
+    pub fn get(&self, version: &u64) -> Result<Option<Value<'_, T>>, Error> {
+        let reader = self.store()?;
+
+        if !reader.contains_key(version) {
+            return Ok(None);
+        }
 
-        Ok(Some(rtn))
+        Ok(Some(Value {
+            reader,
+            version: *version,
+        }))
     }
 
     /// returns the latest version of the value
@@ -170,21 +635,13 @@ impl<T> RwVersioned<T> {
     /// similar to get in that both the value and guard are returned in the
     /// struct
     pub fn latest(&self) -> Result<Option<Value<'_, T>>, Error> {
-        let store_reader = self.store.read()
-            .map_err(|_| Error::StorePoisoned)?;
-
-        let mut rtn = Value {
-            reader: store_reader,
-            value: NonNull::dangling(),
-        };
+        let reader = self.store()?;
 
-        let Some((_, value)) = rtn.reader.last_key_value() else {
+        let Some(version) = reader.last_key_value().map(|(k, _)| *k) else {
             return Ok(None);
         };
 
-        rtn.value = NonNull::from(value);
-
-        Ok(Some(rtn))
+        Ok(Some(Value { reader, version }))
     }
 
     /// returns the latest version of the value along with the version number
@@ -192,30 +649,205 @@ impl<T> RwVersioned<T> {
     /// similar to get in that both the value and guard are returned in the
     /// struct along with the version associated with the value
     pub fn latest_version(&self) -> Result<Option<KeyValue<'_, T>>, Error> {
-        let store_reader = self.store.read()
-            .map_err(|_| Error::StorePoisoned)?;
+        Ok(self.latest()?.map(|value| KeyValue { value }))
+    }
 
-        let mut rtn = KeyValue {
-            reader: store_reader,
-            key: NonNull::dangling(),
-            value: NonNull::dangling(),
-        };
+    /// calls the given function with a reference to the desired version
+    ///
+    /// the read lock is only held while the function runs
+    pub fn with_version<F, R>(&self, version: &u64, f: F) -> Result<Option<R>, Error>
+    where
+        F: FnOnce(&T) -> R
+    {
+        let reader = self.store()?;
 
-        let Some((key, value)) = rtn.reader.last_key_value() else {
-            return Ok(None);
+        Ok(reader.get(version).map(f))
+    }
+
+    /// calls the given function with the latest version number and value
+    ///
+    /// the read lock is only held while the function runs
+    pub fn with_latest<F, R>(&self, f: F) -> Result<Option<R>, Error>
+    where
+        F: FnOnce(&u64, &T) -> R
+    {
+        let reader = self.store()?;
+
+        Ok(reader.last_key_value().map(|(k, v)| f(k, v)))
+    }
+}
+
+impl<T> RwVersioned<T>
+where
+    T: Clone
+{
+    /// copies the store and count into an owned Versioned
+    ///
+    /// the count is read while the store is locked for reading so the
+    /// snapshot will not contain a partially applied update. the lock is
+    /// released before returning so the Versioned can be iterated or
+    /// serialized without blocking writers
+    pub fn snapshot(&self) -> Result<Versioned<T>, Error> {
+        let store_reader = self.store()?;
+        let count = self.count.load(Ordering::Acquire);
+
+        Ok(Versioned {
+            store: store_reader.iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            count,
+            listeners: Listeners::new(),
+        })
+    }
+
+    /// returns a clone of the desired version
+    ///
+    /// the read lock is released before returning
+    pub fn get_cloned(&self, version: &u64) -> Result<Option<T>, Error> {
+        Ok(self.store()?.get(version).cloned())
+    }
+
+    /// returns a clone of the latest version of the value
+    ///
+    /// the read lock is released before returning
+    pub fn latest_cloned(&self) -> Result<Option<T>, Error> {
+        Ok(self.store()?.last_key_value().map(|(_, v)| v.clone()))
+    }
+
+    /// returns a clone of the latest version of the value along with the
+    /// version number
+    pub fn latest_version_cloned(&self) -> Result<Option<(u64, T)>, Error> {
+        Ok(self.store()?.last_key_value().map(|(k, v)| (*k, v.clone())))
+    }
+
+    /// returns a receiver that always holds a clone of the latest version
+    ///
+    /// the first call creates the sender with the current latest version.
+    /// the value is replaced whenever the latest version changes from an
+    /// update or removal so async consumers can await changes instead of
+    /// locking the store
+    #[cfg(feature = "tokio")]
+    pub fn watch(&self) -> Result<tokio::sync::watch::Receiver<Option<(u64, T)>>, Error> {
+        if let Some(watch) = self.watch.get() {
+            return Ok(watch.tx.subscribe());
+        }
+
+        // the write lock prevents an update from happening between creating
+        // the sender and storing it
+        let store_writer = self.write()?;
+
+        let watch = self.watch.get_or_init(|| {
+            let latest = store_writer.last_key_value()
+                .map(|(k, v)| (*k, v.clone()));
+
+            Watch {
+                tx: tokio::sync::watch::Sender::new(latest),
+                clone: T::clone,
+            }
+        });
+
+        Ok(watch.tx.subscribe())
+    }
+
+    /// returns an owning iterator of clones of all stored versions in
+    /// ascending order
+    ///
+    /// the versions are copied under a single read lock that is released
+    /// before returning
+    pub fn iter_cloned(&self) -> Result<std::vec::IntoIter<(u64, T)>, Error> {
+        Ok(self.range_cloned(..)?.into_iter())
+    }
+
+    /// returns clones of the stored versions in the given range in ascending
+    /// order
+    ///
+    /// the versions are copied under a single read lock that is released
+    /// before returning
+    pub fn range_cloned<R>(&self, range: R) -> Result<Vec<(u64, T)>, Error>
+    where
+        R: RangeBounds<u64>
+    {
+        Ok(self.store()?.range(range)
+            .map(|(k, v)| (*k, v.clone()))
+            .collect())
+    }
+
+    /// returns clones of all stored versions newer than the given version in
+    /// ascending order
+    ///
+    /// the versions are copied under a single read lock so no update can be
+    /// seen partially
+    pub fn changes_since(&self, version: u64) -> Result<Vec<(u64, T)>, Error> {
+        self.range_cloned((Bound::Excluded(version), Bound::Unbounded))
+    }
+}
+
+/// removes all values before the given version from the store returning
+/// them
+fn split_before<T>(store: &mut BTreeMap<u64, T>, version: &u64) -> BTreeMap<u64, T> {
+    let kept = store.split_off(version);
+
+    std::mem::replace(store, kept)
+}
+
+impl<T> std::default::Default for RwVersioned<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// best effort output of the store and count
+///
+/// the locks are only tried so a locked value will be shown as `<locked>`
+/// instead of blocking. poisoned values are still shown
+impl<T> fmt::Debug for RwVersioned<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("RwVersioned");
+
+        match self.store.try_read() {
+            Ok(store) => debug.field("store", &*store),
+            Err(TryLockError::Poisoned(err)) => debug.field("store", &*err.into_inner()),
+            Err(TryLockError::WouldBlock) => debug.field("store", &format_args!("<locked>")),
         };
 
-        rtn.key = NonNull::from(key);
-        rtn.value = NonNull::from(value);
+        debug.field("count", &self.count.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+/// creates a snapshot of the store and count
+///
+/// the count is read while the store is locked for reading so the clone will
+/// not contain a partially applied update. a poisoned store is ignored and the
+/// data inside is cloned as is
+impl<T> Clone for RwVersioned<T>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        let store_reader = self.store.read()
+            .unwrap_or_else(|err| err.into_inner());
 
-        Ok(Some(rtn))
+        RwVersioned {
+            store: RwLock::new(store_reader.clone()),
+            count: AtomicU64::new(self.count.load(Ordering::Acquire)),
+            subscribers: Subscribers::new(),
+            #[cfg(feature = "tokio")]
+            watch: std::sync::OnceLock::new(),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::new(),
+        }
     }
-    */
 }
 
 #[cfg(feature = "serde")]
 use serde::{
     ser::{
+        self,
         Serialize,
         Serializer,
         SerializeStruct,
@@ -239,9 +871,12 @@ where
     where
         S: Serializer
     {
+        let store_reader = self.store.read()
+            .map_err(|_| ser::Error::custom("lock poison error while serializing"))?;
+
         let mut state = serializer.serialize_struct("RwVersioned", 2)?;
-        state.serialize_field("store", &self.store)?;
-        state.serialize_field("count", &self.count)?;
+        state.serialize_field("store", &*store_reader)?;
+        state.serialize_field("count", &self.count.load(Ordering::Acquire))?;
         state.end()
     }
 }
@@ -315,7 +950,15 @@ where
                 let count = seq.next_element()?
                     .ok_or_else(|| de::Error::invalid_length(0, &self))?;
 
-                Ok(RwVersioned { store, count })
+                Ok(RwVersioned {
+                    store,
+                    count,
+                    subscribers: Subscribers::new(),
+                    #[cfg(feature = "tokio")]
+                    watch: std::sync::OnceLock::new(),
+                    #[cfg(feature = "metrics")]
+                    metrics: Metrics::new(),
+                })
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
@@ -347,7 +990,15 @@ where
                 let store = store.ok_or_else(|| de::Error::missing_field("store"))?;
                 let count = count.ok_or_else(|| de::Error::missing_field("count"))?;
 
-                Ok(RwVersioned { store, count })
+                Ok(RwVersioned {
+                    store,
+                    count,
+                    subscribers: Subscribers::new(),
+                    #[cfg(feature = "tokio")]
+                    watch: std::sync::OnceLock::new(),
+                    #[cfg(feature = "metrics")]
+                    metrics: Metrics::new(),
+                })
             }
         }
 
@@ -381,6 +1032,261 @@ mod test {
         assert_eq!(*v, 2);
     }
 
+    #[test]
+    fn queries() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+
+        assert!(store.is_empty().unwrap());
+
+        store.update(1).unwrap();
+        let drop = store.update(2).unwrap();
+        store.drop(&drop).unwrap();
+
+        assert_eq!(store.len().unwrap(), 1);
+        assert!(!store.is_empty().unwrap());
+        assert!(store.contains(&0).unwrap());
+        assert!(!store.contains(&drop).unwrap());
+    }
+
+    #[test]
+    fn debug_clone() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1).unwrap();
+
+        let cloned = store.clone();
+        store.update(2).unwrap();
+
+        assert_eq!(cloned.count(), 1);
+        assert_eq!(format!("{:?}", cloned), "RwVersioned { store: {0: 1}, count: 1 }");
+
+        let _writer = store.store.write().unwrap();
+
+        assert_eq!(format!("{:?}", store), "RwVersioned { store: <locked>, count: 2 }");
+    }
+
+    #[test]
+    fn snapshot() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1).unwrap();
+        let drop = store.update(2).unwrap();
+        store.update(3).unwrap();
+        store.drop(&drop).unwrap();
+
+        let snapshot = store.snapshot().unwrap();
+
+        assert_eq!(snapshot.count(), &3);
+        assert_eq!(snapshot.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), vec![(0, 1), (2, 3)]);
+    }
+
+    #[test]
+    fn update_many() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1).unwrap();
+
+        let range = store.update_many([2, 3, 4]).unwrap();
+
+        assert_eq!(range, 1..4);
+        assert_eq!(store.count(), 4);
+        assert_eq!(store.with_latest(|k, v| (*k, *v)).unwrap(), Some((3, 4)));
+    }
+
+    #[test]
+    fn cloned() {
+        let store: RwVersioned<String> = RwVersioned::new();
+        let version = store.update(String::from("first")).unwrap();
+        store.update(String::from("second")).unwrap();
+
+        assert_eq!(store.get_cloned(&version).unwrap(), Some(String::from("first")));
+        assert_eq!(store.latest_cloned().unwrap(), Some(String::from("second")));
+        assert_eq!(store.latest_version_cloned().unwrap(), Some((1, String::from("second"))));
+    }
+
+    #[test]
+    fn subscribe() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        let rx = store.subscribe();
+
+        let version = store.update(1).unwrap();
+        store.update_many([2, 3]).unwrap();
+        store.drop(&version).unwrap();
+        store.drop(&version).unwrap();
+
+        assert_eq!(rx.try_iter().collect::<Vec<Event>>(), vec![
+            Event::Updated(0),
+            Event::Updated(1),
+            Event::Updated(2),
+            Event::Dropped(0),
+        ]);
+
+        drop(rx);
+        store.update(4).unwrap();
+
+        assert!(store.subscribers.senders.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn prune() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update_many([1, 2, 3, 4, 5]).unwrap();
+
+        assert_eq!(store.remove_older_than(&2).unwrap(), vec![(0, 1), (1, 2)]);
+        assert_eq!(store.retain_latest(5).unwrap(), vec![]);
+        assert_eq!(store.retain_latest(2).unwrap(), vec![(2, 3)]);
+        assert_eq!(store.len().unwrap(), 2);
+        assert_eq!(store.retain_latest(0).unwrap(), vec![(3, 4), (4, 5)]);
+        assert!(store.is_empty().unwrap());
+    }
+
+    #[test]
+    fn update_if_latest() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+
+        let first = store.update_if_latest(None, 1).unwrap();
+        store.update(2).unwrap();
+
+        match store.update_if_latest(Some(first), 3) {
+            Err(Error::NotLatest(latest)) => assert_eq!(latest, Some(1)),
+            result => panic!("unexpected update result: {:?}", result),
+        }
+
+        assert_eq!(store.update_if_latest(Some(1), 3).unwrap(), 2);
+    }
+
+    #[test]
+    fn exhausted() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.count.store(u64::MAX - 2, Ordering::Release);
+
+        assert!(matches!(store.update_many([1, 2, 3]), Err(Error::VersionExhausted)));
+        assert!(store.is_empty().unwrap(), "values were stored after exhaustion");
+
+        assert_eq!(store.update_many([1, 2]).unwrap(), (u64::MAX - 2)..u64::MAX);
+        assert!(matches!(store.update(3), Err(Error::VersionExhausted)));
+        assert!(matches!(store.update_if_latest(Some(u64::MAX - 1), 3), Err(Error::VersionExhausted)));
+        assert_eq!(store.count(), u64::MAX);
+        assert_eq!(store.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn recover() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1).unwrap();
+
+        std::thread::scope(|scope| {
+            let _ = scope.spawn(|| {
+                let mut writer = store.store.write().unwrap();
+                writer.insert(5, 2);
+
+                panic!("poisoning store");
+            }).join();
+        });
+
+        assert!(matches!(store.update(3), Err(Error::StorePoisoned)));
+
+        assert_eq!(store.recover(), 6);
+        assert_eq!(store.update(3).unwrap(), 6);
+    }
+
+    #[test]
+    fn range_cloned() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update_many([1, 2, 3, 4]).unwrap();
+
+        assert_eq!(store.range_cloned(1..3).unwrap(), vec![(1, 2), (2, 3)]);
+        assert_eq!(store.iter_cloned().unwrap().map(|(_, v)| v).sum::<u64>(), 10);
+    }
+
+    #[test]
+    fn changes_since() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update_many([1, 2, 3, 4]).unwrap();
+        store.drop(&2).unwrap();
+
+        assert_eq!(store.changes_since(0).unwrap(), vec![(1, 2), (3, 4)]);
+        assert_eq!(store.changes_since(3).unwrap(), vec![]);
+        assert_eq!(store.changes_since(u64::MAX).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rollback_to() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update_many([1, 2, 3, 4]).unwrap();
+
+        assert_eq!(store.rollback_to(&1).unwrap(), vec![(2, 3), (3, 4)]);
+        assert_eq!(store.count(), 2);
+        assert_eq!(store.rollback_to(&5).unwrap(), vec![]);
+        assert_eq!(store.update(5).unwrap(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn watch() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1).unwrap();
+
+        let mut rx = store.watch().unwrap();
+
+        assert_eq!(*rx.borrow_and_update(), Some((0, 1)));
+
+        store.update(2).unwrap();
+
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(*rx.borrow_and_update(), Some((1, 2)));
+
+        store.drop(&0).unwrap();
+
+        assert!(!rx.has_changed().unwrap(), "dropping an older version changed the latest");
+
+        store.drop(&1).unwrap();
+
+        assert_eq!(*rx.borrow_and_update(), None);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn stats() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update_many([1, 2, 3]).unwrap();
+        store.update(4).unwrap();
+        store.drop(&0).unwrap();
+        store.retain_latest(1).unwrap();
+
+        let stats = store.stats().unwrap();
+
+        assert_eq!(stats.updates(), &4);
+        assert_eq!(stats.drops(), &3);
+        assert_eq!(stats.write_locks(), &4);
+        assert_eq!(stats.len(), &1);
+        assert!(stats.max_write_wait() <= stats.write_wait());
+    }
+
+    #[test]
+    fn guards() {
+        let store: RwVersioned<u64> = RwVersioned::new();
+        store.update(1).unwrap();
+        store.update(2).unwrap();
+
+        {
+            let value = store.get(&0).unwrap()
+                .expect("failed to find version");
+
+            assert_eq!(*value, 1);
+            assert_eq!(value.version(), &0);
+        }
+
+        assert!(store.get(&5).unwrap().is_none());
+
+        let latest = store.latest_version().unwrap()
+            .expect("failed to find latest");
+
+        assert_eq!((latest.key(), latest.value()), (&1, &2));
+
+        drop(latest);
+
+        assert_eq!(store.with_latest(|k, v| k + v).unwrap(), Some(3));
+        assert_eq!(store.with_version(&0, |v| *v).unwrap(), Some(1));
+    }
+
     #[allow(dead_code)]
     #[inline]
     fn rw_versioned_eq<T>(a: &RwVersioned<T>, b: &RwVersioned<T>)
@@ -394,12 +1300,7 @@ mod test {
             assert_eq!(*a_store, *b_store, "store values are not equal");
         }
 
-        {
-            let a_count = a.count.lock().unwrap();
-            let b_count = b.count.lock().unwrap();
-
-            assert_eq!(*a_count, *b_count, "count values are not equal");
-        }
+        assert_eq!(a.count(), b.count(), "count values are not equal");
     }
 
     #[cfg(feature = "serde")]
@@ -0,0 +1,539 @@
+use core::fmt;
+use core::fmt::Write as _;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use super::Versioned;
+
+/// total lines of unchanged context to show around a change in a unified
+/// diff
+const CONTEXT: usize = 3;
+
+/// a single operation in a Delta
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Op {
+    /// copy the given number of lines from the source
+    Keep(usize),
+    /// skip the given number of lines from the source
+    Delete(usize),
+    /// add the given lines
+    Insert(Vec<String>),
+}
+
+/// the operations needed to turn the text of one version into another
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Delta {
+    ops: Vec<Op>,
+}
+
+impl Delta {
+    /// creates a delta that turns the lines of from into the lines of to
+    fn new(from: &[&str], to: &[&str]) -> Self {
+        let mut ops: Vec<Op> = Vec::new();
+
+        for line in diff_lines(from, to) {
+            match (line, ops.last_mut()) {
+                (Line::Equal(_), Some(Op::Keep(count))) => *count += 1,
+                (Line::Equal(_), _) => ops.push(Op::Keep(1)),
+                (Line::Delete(_), Some(Op::Delete(count))) => *count += 1,
+                (Line::Delete(_), _) => ops.push(Op::Delete(1)),
+                (Line::Insert(l), Some(Op::Insert(lines))) => lines.push(l.to_owned()),
+                (Line::Insert(l), _) => ops.push(Op::Insert(vec![l.to_owned()])),
+            }
+        }
+
+        Delta { ops }
+    }
+
+    /// applies the delta to the given source text
+    fn apply(&self, source: &str) -> String {
+        let mut lines = source.split_inclusive('\n');
+        let mut rtn = String::with_capacity(source.len());
+
+        for op in &self.ops {
+            match op {
+                Op::Keep(count) => {
+                    for line in lines.by_ref().take(*count) {
+                        rtn.push_str(line);
+                    }
+                }
+                Op::Delete(count) => {
+                    lines.by_ref().take(*count).for_each(drop);
+                }
+                Op::Insert(insert) => {
+                    for line in insert {
+                        rtn.push_str(line);
+                    }
+                }
+            }
+        }
+
+        rtn
+    }
+
+    /// returns the number of bytes of text stored in the delta
+    fn text_len(&self) -> usize {
+        self.ops.iter()
+            .map(|op| match op {
+                Op::Insert(lines) => lines.iter().map(String::len).sum(),
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// a line in the difference between two texts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// the most lines that can be deleted or inserted in the middle of two
+/// texts before the diff gives up on finding common lines
+///
+/// the search keeps O(MAX_EDITS^2) offsets in memory so this bounds the
+/// memory and time used by large rewrites
+const MAX_EDITS: usize = 1024;
+
+/// calculates the line differences between two lists of lines
+///
+/// common lines at the start and end are trimmed before running the Myers
+/// O(ND) diff over the remaining lines. if the remaining lines need more
+/// than MAX_EDITS deletes and inserts then they are all deleted and
+/// inserted instead
+fn diff_lines<'a>(from: &[&'a str], to: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = from.iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = from[prefix..].iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let from_mid = &from[prefix..from.len() - suffix];
+    let to_mid = &to[prefix..to.len() - suffix];
+
+    let mut rtn = Vec::with_capacity(from.len().max(to.len()));
+    rtn.extend(from[..prefix].iter().map(|l| Line::Equal(l)));
+
+    match myers(from_mid, to_mid) {
+        Some(lines) => rtn.extend(lines),
+        None => {
+            rtn.extend(from_mid.iter().map(|l| Line::Delete(l)));
+            rtn.extend(to_mid.iter().map(|l| Line::Insert(l)));
+        }
+    }
+
+    rtn.extend(from[from.len() - suffix..].iter().map(|l| Line::Equal(l)));
+    rtn
+}
+
+/// finds the shortest list of deletes and inserts that turns from into to
+///
+/// returns None if more than MAX_EDITS are needed
+fn myers<'a>(from: &[&'a str], to: &[&'a str]) -> Option<Vec<Line<'a>>> {
+    let n = from.len() as isize;
+    let m = to.len() as isize;
+    let max = (from.len() + to.len()).min(MAX_EDITS) as isize;
+
+    // v[k + offset] is the furthest x reached on diagonal k = x - y
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+
+    // trace[d] holds diagonals -(d + 1)..=(d + 1) of v before step d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && from[x as usize] == to[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[index] = x;
+
+            if x >= n && y >= m {
+                return Some(backtrack(from, to, &trace));
+            }
+        }
+    }
+
+    None
+}
+
+/// walks the trace of myers back from the end of both lists
+fn backtrack<'a>(from: &[&'a str], to: &[&'a str], trace: &[Vec<isize>]) -> Vec<Line<'a>> {
+    let mut rtn = Vec::with_capacity(from.len() + to.len());
+    let mut x = from.len() as isize;
+    let mut y = to.len() as isize;
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| v[(k + d + 1) as usize];
+
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            rtn.push(Line::Equal(from[x as usize]));
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                rtn.push(Line::Insert(to[(y - 1) as usize]));
+            } else {
+                rtn.push(Line::Delete(from[(x - 1) as usize]));
+            }
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    rtn.reverse();
+    rtn
+}
+
+/// writes a single line of a unified diff with the given prefix
+fn write_line(output: &mut String, prefix: char, line: &str) {
+    output.push(prefix);
+    output.push_str(line);
+
+    if !line.ends_with('\n') {
+        output.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// writes the hunk header for the given start and length of lines
+fn write_range(output: &mut String, start: usize, len: usize) {
+    // an empty range refers to the line before it
+    let start = if len == 0 { start } else { start + 1 };
+
+    let _ = write!(output, "{},{}", start, len);
+}
+
+/// a Versioned string that only stores the line differences between
+/// versions
+///
+/// the latest version is kept in full and every older version stores the
+/// changes needed to get back to it from the version after it. retrieving an
+/// older version requires walking back from the latest version so the cost
+/// grows with the distance from the latest. versions cannot be removed since
+/// that would break the chain of differences.
+pub struct VersionedString {
+    latest: String,
+    deltas: Versioned<Delta>,
+}
+
+impl VersionedString {
+    /// creates an empty struct
+    pub fn new() -> Self {
+        VersionedString {
+            latest: String::new(),
+            deltas: Versioned::new(),
+        }
+    }
+
+    /// returns next version number to use
+    pub fn count(&self) -> &u64 {
+        self.deltas.count()
+    }
+
+    /// returns total stored versions
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// checks if there are no stored versions
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// checks if the given version is stored
+    pub fn contains_version(&self, version: &u64) -> bool {
+        self.deltas.contains_version(version)
+    }
+
+    /// returns an iterator of the stored version numbers in ascending order
+    pub fn versions(&self) -> impl DoubleEndedIterator<Item = &u64> {
+        self.deltas.iter().map(|(k, _)| k)
+    }
+
+    /// updates the text returning the version number used
+    pub fn update<S>(&mut self, text: S) -> u64
+    where
+        S: Into<String>
+    {
+        let text = text.into();
+
+        if let Some(version) = self.deltas.latest_version().map(|(k, _)| *k) {
+            let from: Vec<&str> = text.split_inclusive('\n').collect();
+            let to: Vec<&str> = self.latest.split_inclusive('\n').collect();
+            let delta = Delta::new(&from, &to);
+
+            if let Some(stored) = self.deltas.store.get_mut(&version) {
+                *stored = delta;
+            }
+        }
+
+        self.latest = text;
+        self.deltas.update(Delta::default())
+    }
+
+    /// returns the latest version of the text
+    pub fn latest(&self) -> Option<&str> {
+        if self.deltas.is_empty() {
+            None
+        } else {
+            Some(&self.latest)
+        }
+    }
+
+    /// returns the latest version of the text along with the version number
+    pub fn latest_version(&self) -> Option<(&u64, &str)> {
+        self.deltas.latest_version()
+            .map(|(k, _)| (k, self.latest.as_str()))
+    }
+
+    /// rebuilds the text of the desired version
+    pub fn get(&self, version: &u64) -> Option<String> {
+        if !self.deltas.contains_version(version) {
+            return None;
+        }
+
+        let mut text = self.latest.clone();
+
+        // the latest version does not have a delta to apply
+        for (key, delta) in self.deltas.iter_desc().skip(1) {
+            if key < version {
+                break;
+            }
+
+            text = delta.apply(&text);
+        }
+
+        Some(text)
+    }
+
+    /// creates a unified diff of the changes from version a to version b
+    ///
+    /// returns None if either version is not stored
+    pub fn diff(&self, a: &u64, b: &u64) -> Option<String> {
+        let a_text = self.get(a)?;
+        let b_text = self.get(b)?;
+        let from: Vec<&str> = a_text.split_inclusive('\n').collect();
+        let to: Vec<&str> = b_text.split_inclusive('\n').collect();
+        let lines = diff_lines(&from, &to);
+
+        let mut output = format!("--- version {}\n+++ version {}\n", a, b);
+
+        // line positions in from and to before each entry in lines
+        let mut positions = Vec::with_capacity(lines.len() + 1);
+        let mut from_pos = 0;
+        let mut to_pos = 0;
+
+        for line in &lines {
+            positions.push((from_pos, to_pos));
+
+            match line {
+                Line::Equal(_) => {
+                    from_pos += 1;
+                    to_pos += 1;
+                }
+                Line::Delete(_) => from_pos += 1,
+                Line::Insert(_) => to_pos += 1,
+            }
+        }
+
+        positions.push((from_pos, to_pos));
+
+        let changes: Vec<usize> = lines.iter()
+            .enumerate()
+            .filter(|(_, l)| !matches!(l, Line::Equal(_)))
+            .map(|(i, _)| i)
+            .collect();
+        let mut index = 0;
+
+        while index < changes.len() {
+            let mut last = index;
+
+            while last + 1 < changes.len() && changes[last + 1] - changes[last] <= CONTEXT * 2 + 1 {
+                last += 1;
+            }
+
+            let start = changes[index].saturating_sub(CONTEXT);
+            let end = (changes[last] + 1 + CONTEXT).min(lines.len());
+            let (from_start, to_start) = positions[start];
+            let (from_end, to_end) = positions[end];
+
+            output.push_str("@@ -");
+            write_range(&mut output, from_start, from_end - from_start);
+            output.push_str(" +");
+            write_range(&mut output, to_start, to_end - to_start);
+            output.push_str(" @@\n");
+
+            for line in &lines[start..end] {
+                match line {
+                    Line::Equal(l) => write_line(&mut output, ' ', l),
+                    Line::Delete(l) => write_line(&mut output, '-', l),
+                    Line::Insert(l) => write_line(&mut output, '+', l),
+                }
+            }
+
+            index = last + 1;
+        }
+
+        Some(output)
+    }
+
+    /// returns the total bytes of text stored for all versions
+    ///
+    /// this counts the full latest text and the inserted lines of every
+    /// delta
+    pub fn text_len(&self) -> usize {
+        self.latest.len() + self.deltas.iter()
+            .map(|(_, delta)| delta.text_len())
+            .sum::<usize>()
+    }
+}
+
+impl core::default::Default for VersionedString {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for VersionedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VersionedString")
+            .field("latest", &self.latest)
+            .field("deltas", &self.deltas)
+            .finish()
+    }
+}
+
+impl Clone for VersionedString {
+    fn clone(&self) -> Self {
+        VersionedString {
+            latest: self.latest.clone(),
+            deltas: self.deltas.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reconstruct() {
+        let texts = [
+            "one\ntwo\nthree\n",
+            "one\n2\nthree\nfour\n",
+            "zero\none\n2\nthree\nfour",
+            "",
+            "four\nthree\n",
+        ];
+        let mut versioned = VersionedString::new();
+
+        for text in texts {
+            versioned.update(text);
+        }
+
+        for (version, text) in texts.iter().enumerate() {
+            assert_eq!(
+                versioned.get(&(version as u64)).as_deref(),
+                Some(*text),
+                "invalid text for version {}", version
+            );
+        }
+
+        assert_eq!(versioned.latest(), Some("four\nthree\n"));
+        assert_eq!(versioned.get(&5), None);
+    }
+
+    #[test]
+    fn unified_diff() {
+        let mut versioned = VersionedString::new();
+        let a = versioned.update("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        let b = versioned.update("a\nb\nC\nd\ne\nf\ng\nh\ni\nj\nk");
+
+        let expected = "\
+--- version 0
++++ version 1
+@@ -1,6 +1,6 @@
+ a
+ b
+-c
++C
+ d
+ e
+ f
+@@ -8,3 +8,4 @@
+ h
+ i
+ j
++k
+\\ No newline at end of file
+";
+
+        assert_eq!(versioned.diff(&a, &b).as_deref(), Some(expected));
+        assert_eq!(versioned.diff(&a, &a).as_deref(), Some("--- version 0\n+++ version 0\n"));
+        assert_eq!(versioned.diff(&a, &2), None);
+    }
+
+    #[test]
+    fn large_rewrite() {
+        let from: String = (0..50_000).map(|i| format!("from {}\n", i)).collect();
+        let to: String = (0..50_000).map(|i| format!("to {}\n", i)).collect();
+        let mut versioned = VersionedString::new();
+
+        let a = versioned.update(from.as_str());
+        let b = versioned.update(to.as_str());
+
+        assert_eq!(versioned.get(&a).as_deref(), Some(from.as_str()));
+        assert_eq!(versioned.get(&b).as_deref(), Some(to.as_str()));
+    }
+
+    #[test]
+    fn scattered_changes() {
+        let from: Vec<String> = (0..2_000).map(|i| format!("{}\n", i)).collect();
+        let mut to = from.clone();
+
+        for i in (0..to.len()).step_by(100) {
+            to[i] = format!("changed {}\n", i);
+        }
+
+        let from_lines: Vec<&str> = from.iter().map(String::as_str).collect();
+        let to_lines: Vec<&str> = to.iter().map(String::as_str).collect();
+        let delta = Delta::new(&from_lines, &to_lines);
+
+        assert_eq!(delta.apply(&from.concat()), to.concat());
+        assert_eq!(delta.text_len(), to_lines.iter().step_by(100).map(|l| l.len()).sum::<usize>());
+    }
+}
@@ -0,0 +1,261 @@
+use std::time::{Duration, SystemTime};
+
+use super::Versioned;
+
+/// a value paired with the time that it was recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamped<T> {
+    time: SystemTime,
+    value: T,
+}
+
+impl<T> Timestamped<T> {
+    /// creates a new Timestamped with the given time
+    pub fn new(value: T, time: SystemTime) -> Self {
+        Timestamped { time, value }
+    }
+
+    /// creates a new Timestamped with the current system time
+    pub fn now(value: T) -> Self {
+        Timestamped {
+            time: SystemTime::now(),
+            value,
+        }
+    }
+
+    /// returns the time the value was recorded
+    pub fn time(&self) -> &SystemTime {
+        &self.time
+    }
+
+    /// returns a reference to the value
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// consumes the struct returning the value
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Versioned<Timestamped<T>> {
+    /// updates the value with the current system time returning the version
+    /// number used
+    pub fn update_now(&mut self, value: T) -> u64 {
+        self.update(Timestamped::now(value))
+    }
+
+    /// removes all versions recorded before the given time
+    ///
+    /// the removed versions are returned in ascending order
+    pub fn evict_before(&mut self, time: SystemTime) -> Vec<(u64, Timestamped<T>)> {
+        let versions: Vec<u64> = self.store.iter()
+            .filter(|(_, v)| v.time < time)
+            .map(|(k, _)| *k)
+            .collect();
+        let mut rtn = Vec::with_capacity(versions.len());
+
+        for version in versions {
+            if let Some(value) = self.remove(&version) {
+                rtn.push((version, value));
+            }
+        }
+
+        rtn
+    }
+
+    /// removes all versions that are older than the given duration from the
+    /// current system time
+    ///
+    /// the removed versions are returned in ascending order
+    pub fn evict_older_than(&mut self, age: Duration) -> Vec<(u64, Timestamped<T>)> {
+        let Some(cutoff) = SystemTime::now().checked_sub(age) else {
+            return Vec::new();
+        };
+
+        self.evict_before(cutoff)
+    }
+}
+
+#[cfg(feature = "serde")]
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{
+    ser::{
+        Serialize,
+        Serializer,
+        SerializeStruct,
+    },
+    de::{
+        self,
+        Deserialize,
+        Deserializer,
+        Visitor,
+        MapAccess,
+        SeqAccess,
+    }
+};
+
+#[cfg(feature = "serde")]
+impl<T> Serialize for Timestamped<T>
+where
+    T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer
+    {
+        let mut state = serializer.serialize_struct("Timestamped", 2)?;
+        state.serialize_field("time", &self.time)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for Timestamped<T>
+where
+    T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        const STRUCT_FIELDS: &[&str] = &["time", "value"];
+
+        enum StructField {
+            Time,
+            Value,
+        }
+
+        impl<'de> Deserialize<'de> for StructField {
+            fn deserialize<D>(deserializer: D) -> Result<StructField, D::Error>
+            where
+                D: Deserializer<'de>
+            {
+                struct StructFieldVisitor;
+
+                impl<'de> Visitor<'de> for StructFieldVisitor {
+                    type Value = StructField;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("'time' or 'value'")
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+                    where
+                        E: de::Error
+                    {
+                        match value {
+                            "time" => Ok(StructField::Time),
+                            "value" => Ok(StructField::Value),
+                            _ => Err(de::Error::unknown_field(value, STRUCT_FIELDS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(StructFieldVisitor)
+            }
+        }
+
+        struct TimestampedVisitor<T> {
+            _type: std::marker::PhantomData<T>
+        }
+
+        impl<'de, T> Visitor<'de> for TimestampedVisitor<T>
+        where
+            T: Deserialize<'de>
+        {
+            type Value = Timestamped<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct Timestamped")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: SeqAccess<'de>
+            {
+                let time = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let value = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+                Ok(Timestamped { time, value })
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+            where
+                V: MapAccess<'de>
+            {
+                let mut time = None;
+                let mut value = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        StructField::Time => {
+                            if time.is_some() {
+                                return Err(de::Error::duplicate_field("time"));
+                            }
+
+                            time = Some(map.next_value()?);
+                        }
+                        StructField::Value => {
+                            if value.is_some() {
+                                return Err(de::Error::duplicate_field("value"));
+                            }
+
+                            value = Some(map.next_value()?);
+                        }
+                    }
+                }
+
+                let time = time.ok_or_else(|| de::Error::missing_field("time"))?;
+                let value = value.ok_or_else(|| de::Error::missing_field("value"))?;
+
+                Ok(Timestamped { time, value })
+            }
+        }
+
+        deserializer.deserialize_struct(
+            "Timestamped",
+            STRUCT_FIELDS,
+            TimestampedVisitor {
+                _type: std::marker::PhantomData
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evict() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(60 * 60 * 24);
+
+        let mut versioned: Versioned<Timestamped<u64>> = Versioned::new();
+        versioned.update(Timestamped::new(1, now - day * 100));
+        versioned.update(Timestamped::new(2, now - day * 95));
+        versioned.update(Timestamped::new(3, now - day * 10));
+        versioned.update_now(4);
+
+        let evicted = versioned.evict_older_than(day * 90);
+
+        assert_eq!(
+            evicted.iter().map(|(k, _)| *k).collect::<Vec<u64>>(),
+            vec![0, 1],
+            "unexpected evicted versions"
+        );
+        assert_eq!(versioned.len(), 2);
+
+        let evicted = versioned.evict_before(now);
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(versioned.latest().map(|v| *v.value()), Some(4));
+    }
+}